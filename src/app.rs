@@ -1,18 +1,40 @@
-use crate::recorder::is_command_available;
+use crate::chapters::{Mark, MarkRequest};
+use crate::condense::CondenseOptions;
+use crate::config::{Config, Profile};
+use crate::hotkeys::{HotkeyAction, HotkeyListener};
+use crate::library::{Library, LibraryEntry};
+use crate::media::MediaInfo;
+use crate::naming::OverwritePolicy;
+use crate::preview::{PreviewFrames, SourcePreview};
+use crate::recorder::{is_command_available, CursorMode, VideoCodec};
+use crate::redact::Redaction;
+use crate::screenshot::Geometry;
+use crate::sidecar::RecordingMetadata;
+use crate::trim::Timeline;
+use crate::watermark::Watermark;
+use crate::webcam::WebcamOverlay;
 use eframe::{egui, App, Frame};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Child;
 use std::sync::atomic::AtomicBool;
-use std::sync::mpsc::Receiver;
+use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 // App states
 #[derive(PartialEq, Clone)]
 pub enum AppState {
     Setup,
     Main,
+    Library,
+    Countdown,
     Recording,
     Converting,
     Testing,
+    Trim,
+    Crop,
+    Redact,
 }
 
 // UI state
@@ -21,23 +43,107 @@ pub struct RcrdrApp {
     pub logs: Vec<String>,
 
     // Recording settings
-    pub output_path: String,
+    pub filename_template: String,
+    pub overwrite: OverwritePolicy,
     pub duration: u64,
+    pub countdown: u64,
+    pub minimize_on_record: bool,
     pub fps: u32,
+    pub timelapse_enabled: bool,
+    pub timelapse_interval: f64,
+    pub source_input: String,
+    pub codec: VideoCodec,
+    pub audio: bool,
+    pub cursor: CursorMode,
+    pub log_events: bool,
+    pub annotate_events: bool,
+    pub condense_after: bool,
+    pub condense: CondenseOptions,
+    pub condense_speed_up: bool,
+    pub condense_speed: f64,
+    pub webcam_enabled: bool,
+    pub webcam: WebcamOverlay,
+    pub webcam_border_color: String,
+    pub watermark_enabled: bool,
+    pub watermark_exports: bool,
+    pub watermark_use_image: bool,
+    pub watermark: Watermark,
+    pub watermark_text: String,
+    pub watermark_image: String,
+    pub watermark_font: String,
+    pub watermark_color: String,
+    pub output_dir: String,
+    pub screenshot_clipboard: bool,
+
+    // Profiles
+    pub config: Config,
+    pub active_profile: Option<String>,
+    pub new_profile_name: String,
 
     // Conversion settings
     pub input_video_path: String,
     pub output_gif_path: String,
 
+    // Library
+    pub library: Library,
+    pub library_thumbnails: HashMap<PathBuf, Option<egui::TextureHandle>>,
+    /// Entries built on worker threads, since probing a video and extracting its
+    /// thumbnail run ffmpeg
+    pub library_entry_sender: Sender<Result<LibraryEntry, String>>,
+    pub library_entry_receiver: Receiver<Result<LibraryEntry, String>>,
+    pub library_entries_pending: usize,
+    /// Metadata read from each recording's .rcrdr.json, keyed by the video
+    pub library_recordings: HashMap<PathBuf, Option<RecordingMetadata>>,
+    /// Recordings ticked for concatenation, in the order they were ticked
+    pub library_selected: Vec<PathBuf>,
+    pub concat_crossfade_enabled: bool,
+    pub concat_crossfade: f64,
+    pub concat_log_receiver: Option<Receiver<String>>,
+    pub concat_done_receiver: Option<Receiver<Option<PathBuf>>>,
+    pub library_rename: Option<(PathBuf, String)>,
+    pub library_pending_delete: Option<PathBuf>,
+
+    // Live preview
+    pub show_preview: bool,
+    pub source_preview: Option<SourcePreview>,
+    pub preview_source: Option<String>,
+    pub preview_frames: Option<PreviewFrames>,
+    pub preview_texture: Option<egui::TextureHandle>,
+
+    // Global hotkeys
+    pub hotkeys_initialized: bool,
+    pub hotkey_listener: Option<HotkeyListener>,
+    pub hotkey_receiver: Option<Receiver<HotkeyAction>>,
+    pub hotkey_inputs: Vec<String>,
+    pub hotkey_status: String,
+
     // Setup
     pub ffmpeg_installed: bool,
     pub installation_logs: Vec<String>,
 
+    // Countdown state
+    pub countdown_deadline: Option<Instant>,
+    pub countdown_overlay: Option<Child>,
+    pub countdown_minimize_pending: bool,
+
     // Recording state
     pub recording_start_time: Option<Instant>,
     pub recording_stop_flag: Option<Arc<AtomicBool>>,
     pub recording_log_receiver: Option<Receiver<String>>,
+    /// Sent by the recording thread once it and all post-processing are done, with
+    /// the copies it made next to the recording
+    pub recording_done_receiver: Option<Receiver<Result<Vec<PathBuf>, String>>>,
     pub recording_output_path: Option<String>,
+    pub recording_pause_flag: Option<Arc<AtomicBool>>,
+    pub recording_paused_at: Option<Instant>,
+    pub recording_paused_total: Duration,
+    pub recording_marks: Vec<Mark>,
+    pub recording_mark_label: String,
+    /// Marks go to the recording thread, which places them once it knows when
+    /// capture started
+    pub recording_mark_sender: Option<Sender<MarkRequest>>,
+    pub recording_timelapse: Option<f64>,
+    pub last_media_info: Option<MediaInfo>,
 
     // Converting state
     pub converting_log_receiver: Option<Receiver<String>>,
@@ -45,44 +151,229 @@ pub struct RcrdrApp {
 
     // Testing state
     pub testing_log_receiver: Option<Receiver<String>>,
+
+    // Screenshot state
+    pub screenshot_log_receiver: Option<Receiver<String>>,
+
+    // Trim editor
+    pub trim_input: Option<PathBuf>,
+    pub trim_output: String,
+    pub trim_duration: f64,
+    /// Length of one frame, the step of the arrow keys
+    pub trim_frame_secs: f64,
+    pub trim_playhead: f64,
+    pub trim_in: f64,
+    pub trim_out: f64,
+    pub trim_timeline: Option<Timeline>,
+    pub trim_timeline_receiver: Option<Receiver<Result<Timeline, String>>>,
+    pub trim_frames: Vec<egui::TextureHandle>,
+    pub trim_log_receiver: Option<Receiver<String>>,
+    pub trim_export_receiver: Option<Receiver<bool>>,
+
+    // Crop editor
+    pub crop_input: Option<PathBuf>,
+    pub crop_output: String,
+    /// Size of the video, the crop rectangle is in its pixels
+    pub crop_size: (u32, u32),
+    pub crop_rect: Option<Geometry>,
+    pub crop_rect_input: String,
+    pub crop_scale_input: String,
+    /// Where a drag across the frame started, in video pixels
+    pub crop_drag_start: Option<(u32, u32)>,
+    pub crop_frame: Option<egui::TextureHandle>,
+    pub crop_frame_receiver: Option<Receiver<Result<PathBuf, String>>>,
+    pub crop_log_receiver: Option<Receiver<String>>,
+    pub crop_export_receiver: Option<Receiver<bool>>,
+
+    // Redaction editor
+    pub redact_input: Option<PathBuf>,
+    pub redact_output: String,
+    pub redact_size: (u32, u32),
+    pub redact_duration: f64,
+    /// Time of the paused frame the boxes are drawn on
+    pub redact_time: f64,
+    pub redact_regions: Vec<Redaction>,
+    pub redact_selected: Option<usize>,
+    pub redact_drag_start: Option<(u32, u32)>,
+    /// The box being dragged out, added as a region when the drag ends
+    pub redact_drawing: Option<Geometry>,
+    pub redact_frame: Option<egui::TextureHandle>,
+    pub redact_frame_receiver: Option<Receiver<Result<PathBuf, String>>>,
+    pub redact_log_receiver: Option<Receiver<String>>,
+    pub redact_export_receiver: Option<Receiver<bool>>,
 }
 
 impl Default for RcrdrApp {
     fn default() -> Self {
         let ffmpeg_installed = is_command_available("ffmpeg");
 
-        let default_output = format!(
-            "recording_{}.mp4",
-            chrono::Local::now().format("%Y%m%d_%H%M%S")
-        );
+        let mut logs = Vec::new();
+        let config = Config::load().unwrap_or_else(|e| {
+            logs.push(format!("Failed to load config, using defaults: {}", e));
+            Config::default()
+        });
+        let default_profile = config.default_profile.clone();
+        let library = Library::load().unwrap_or_else(|e| {
+            logs.push(format!("Failed to load recording library: {}", e));
+            Library::default()
+        });
+        let (library_entry_sender, library_entry_receiver) = channel();
+        let (output_dir, filename_template) = config.output_target(&Profile::default());
+        let hotkey_inputs = HotkeyAction::ALL
+            .iter()
+            .map(|action| config.hotkeys.get(*action).to_string())
+            .collect();
 
-        Self {
+        let mut app = Self {
             state: if ffmpeg_installed {
                 AppState::Main
             } else {
                 AppState::Setup
             },
-            logs: Vec::new(),
-            output_path: default_output,
+            logs,
+            filename_template,
+            overwrite: config.overwrite,
             duration: 0,
+            countdown: 0,
+            minimize_on_record: false,
             fps: 30,
+            timelapse_enabled: false,
+            timelapse_interval: 2.0,
+            source_input: "screen".to_string(),
+            codec: VideoCodec::default(),
+            audio: false,
+            cursor: CursorMode::default(),
+            log_events: false,
+            annotate_events: false,
+            condense_after: false,
+            condense: CondenseOptions::default(),
+            condense_speed_up: false,
+            condense_speed: 8.0,
+            webcam_enabled: false,
+            webcam: WebcamOverlay::default(),
+            webcam_border_color: WebcamOverlay::default().border_color.to_string(),
+            watermark_enabled: false,
+            watermark_exports: false,
+            watermark_use_image: false,
+            watermark: Watermark::default(),
+            watermark_text: String::new(),
+            watermark_image: String::new(),
+            watermark_font: String::new(),
+            watermark_color: Watermark::default().color.to_string(),
+            output_dir: output_dir.to_string_lossy().to_string(),
+            screenshot_clipboard: false,
+            config,
+            active_profile: None,
+            new_profile_name: String::new(),
             input_video_path: String::new(),
             output_gif_path: String::new(),
+            library,
+            library_thumbnails: HashMap::new(),
+            library_entry_sender,
+            library_entry_receiver,
+            library_entries_pending: 0,
+            library_recordings: HashMap::new(),
+            library_selected: Vec::new(),
+            concat_crossfade_enabled: false,
+            concat_crossfade: 0.5,
+            concat_log_receiver: None,
+            concat_done_receiver: None,
+            library_rename: None,
+            library_pending_delete: None,
+            show_preview: false,
+            source_preview: None,
+            preview_source: None,
+            preview_frames: None,
+            preview_texture: None,
+            hotkeys_initialized: false,
+            hotkey_listener: None,
+            hotkey_receiver: None,
+            hotkey_inputs,
+            hotkey_status: String::new(),
             ffmpeg_installed,
             installation_logs: Vec::new(),
+            countdown_deadline: None,
+            countdown_overlay: None,
+            countdown_minimize_pending: false,
             recording_start_time: None,
             recording_stop_flag: None,
             recording_log_receiver: None,
+            recording_done_receiver: None,
             recording_output_path: None,
+            recording_pause_flag: None,
+            recording_paused_at: None,
+            recording_paused_total: Duration::ZERO,
+            recording_marks: Vec::new(),
+            recording_mark_label: String::new(),
+            recording_mark_sender: None,
+            recording_timelapse: None,
+            last_media_info: None,
             converting_log_receiver: None,
             converting_progress: 0.0,
             testing_log_receiver: None,
+            screenshot_log_receiver: None,
+            trim_input: None,
+            trim_output: String::new(),
+            trim_duration: 0.0,
+            trim_frame_secs: 1.0 / 30.0,
+            trim_playhead: 0.0,
+            trim_in: 0.0,
+            trim_out: 0.0,
+            trim_timeline: None,
+            trim_timeline_receiver: None,
+            trim_frames: Vec::new(),
+            trim_log_receiver: None,
+            trim_export_receiver: None,
+            crop_input: None,
+            crop_output: String::new(),
+            crop_size: (0, 0),
+            crop_rect: None,
+            crop_rect_input: String::new(),
+            crop_scale_input: String::new(),
+            crop_drag_start: None,
+            crop_frame: None,
+            crop_frame_receiver: None,
+            crop_log_receiver: None,
+            crop_export_receiver: None,
+            redact_input: None,
+            redact_output: String::new(),
+            redact_size: (0, 0),
+            redact_duration: 0.0,
+            redact_time: 0.0,
+            redact_regions: Vec::new(),
+            redact_selected: None,
+            redact_drag_start: None,
+            redact_drawing: None,
+            redact_frame: None,
+            redact_frame_receiver: None,
+            redact_log_receiver: None,
+            redact_export_receiver: None,
+        };
+
+        if let Some(name) = default_profile {
+            app.apply_profile(&name);
         }
+
+        app
     }
 }
 
 impl App for RcrdrApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut Frame) {
+        // The hotkey listener needs the context to wake the window up
+        if !self.hotkeys_initialized {
+            self.hotkeys_initialized = true;
+            self.register_hotkeys(ctx);
+        }
+        self.handle_hotkeys();
+        self.receive_library_entries(ctx);
+
+        // Get out of the way of the countdown overlay and the recording
+        if self.countdown_minimize_pending {
+            self.countdown_minimize_pending = false;
+            frame.set_minimized(true);
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| match self.state {
             AppState::Setup => {
                 self.show_setup_screen(ui);
@@ -90,6 +381,12 @@ impl App for RcrdrApp {
             AppState::Main => {
                 self.show_main_screen(ui, ctx);
             }
+            AppState::Library => {
+                self.show_library_screen(ui, ctx);
+            }
+            AppState::Countdown => {
+                self.show_countdown_screen(ui);
+            }
             AppState::Recording => {
                 self.show_recording_screen(ui, ctx);
             }
@@ -99,11 +396,23 @@ impl App for RcrdrApp {
             AppState::Testing => {
                 self.show_testing_screen(ui);
             }
+            AppState::Trim => {
+                self.show_trim_screen(ui, ctx);
+            }
+            AppState::Crop => {
+                self.show_crop_screen(ui, ctx);
+            }
+            AppState::Redact => {
+                self.show_redact_screen(ui, ctx);
+            }
         });
 
         // Request continuous repainting while in active states
         match self.state {
-            AppState::Recording | AppState::Converting | AppState::Testing => {
+            AppState::Countdown
+            | AppState::Recording
+            | AppState::Converting
+            | AppState::Testing => {
                 ctx.request_repaint();
             }
             _ => {}
@@ -0,0 +1,350 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+
+use crate::ffmpeg::{Encoder, FfmpegCommand, Input};
+use crate::media::probe;
+use crate::recorder::timelapse_length;
+use crate::runner::CommandRunner;
+
+// Containers ffmpeg can write chapters into
+const CHAPTER_CONTAINERS: [&str; 5] = ["mp4", "m4v", "mov", "mkv", "webm"];
+
+/// A marker dropped `t` seconds into the recording, not counting pauses.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mark {
+    pub t: f64,
+    pub label: Option<String>,
+}
+
+impl Mark {
+    /// A mark at `elapsed`, without a label when `label` is blank.
+    pub fn new(elapsed: Duration, label: &str) -> Self {
+        let label = label.trim();
+        Self {
+            t: elapsed.as_secs_f64(),
+            label: (!label.is_empty()).then(|| label.to_string()),
+        }
+    }
+}
+
+/// A mark asked for from stdin or the control socket at `at`, before capture
+/// has necessarily started.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarkRequest {
+    pub at: Instant,
+    pub label: String,
+}
+
+impl MarkRequest {
+    pub fn now(label: &str) -> Self {
+        Self {
+            at: Instant::now(),
+            label: label.to_string(),
+        }
+    }
+
+    /// The mark on the recording's clock, which started at `capture_start`.
+    /// Marks asked for before that land at the start.
+    pub fn place(&self, capture_start: Instant) -> Mark {
+        Mark::new(
+            self.at.saturating_duration_since(capture_start),
+            &self.label,
+        )
+    }
+}
+
+/// A chapter of the finished recording, from one mark to the next.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Chapter {
+    pub start: f64,
+    pub end: f64,
+    pub title: String,
+}
+
+/// "clip.mp4" -> "clip.mp4.chapters.json", where the chapters are listed.
+pub fn chapters_path(video: &Path) -> PathBuf {
+    let mut name = video.as_os_str().to_os_string();
+    name.push(".chapters.json");
+    PathBuf::from(name)
+}
+
+/// Marks taken on the wall clock, moved to where they play in a timelapse that
+/// captured a frame every `interval` seconds.
+pub fn timelapse_marks(marks: Vec<Mark>, interval: f64) -> Vec<Mark> {
+    marks
+        .into_iter()
+        .map(|mark| Mark {
+            t: timelapse_length(Duration::from_secs_f64(mark.t), interval).as_secs_f64(),
+            ..mark
+        })
+        .collect()
+}
+
+/// Chapters of a `duration` second video, each starting at a mark. The part
+/// before the first mark is a chapter of its own.
+pub fn chapters(marks: &[Mark], duration: f64) -> Vec<Chapter> {
+    let mut marks: Vec<&Mark> = marks
+        .iter()
+        .filter(|mark| mark.t >= 0.0 && mark.t < duration)
+        .collect();
+    marks.sort_by(|a, b| a.t.total_cmp(&b.t));
+
+    let mut chapters = Vec::new();
+    if let Some(first) = marks.first() {
+        if first.t > 0.0 {
+            chapters.push(Chapter {
+                start: 0.0,
+                end: first.t,
+                title: "Start".to_string(),
+            });
+        }
+    }
+    for (i, mark) in marks.iter().enumerate() {
+        chapters.push(Chapter {
+            start: mark.t,
+            end: marks.get(i + 1).map_or(duration, |next| next.t),
+            title: mark
+                .label
+                .clone()
+                .unwrap_or_else(|| format!("Chapter {}", i + 1)),
+        });
+    }
+    chapters
+}
+
+/// The chapters in ffmpeg's metadata file format.
+pub fn ffmetadata(chapters: &[Chapter]) -> String {
+    let mut metadata = ";FFMETADATA1\n".to_string();
+    for chapter in chapters {
+        metadata.push_str(&format!(
+            "[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+            (chapter.start * 1000.0).round() as u64,
+            (chapter.end * 1000.0).round() as u64,
+            escape_metadata(&chapter.title)
+        ));
+    }
+    metadata
+}
+
+// '=', ';', '#', '\' and line breaks are special in metadata files
+fn escape_metadata(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Whether chapters can be written into `video`, going by its extension.
+pub fn supports_chapters(video: &Path) -> bool {
+    video
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            CHAPTER_CONTAINERS
+                .iter()
+                .any(|container| extension.eq_ignore_ascii_case(container))
+        })
+}
+
+/// The ffmpeg command that copies `input` with the chapters of the `metadata`
+/// file to `output`, without re-encoding.
+pub fn chapters_command(input: &Path, metadata: &Path, output: &Path) -> FfmpegCommand {
+    FfmpegCommand::new()
+        .overwrite()
+        .input(Input::file(input))
+        .input(Input::file(metadata))
+        .map("0")
+        .video_encoder(Encoder::new("copy"))
+        .audio_encoder(Encoder::new("copy"))
+        .output_option("map_chapters", 1)
+        .output(output)
+}
+
+/// List the chapters starting at `marks` next to `video` and, for MP4, MOV and
+/// Matroska files, write them into the video too, replacing it.
+pub fn add_chapters(
+    runner: &dyn CommandRunner,
+    video: &Path,
+    marks: &[Mark],
+) -> Result<Vec<Chapter>, Box<dyn std::error::Error>> {
+    let duration = probe(runner, video)?.duration_secs;
+    let chapters = chapters(marks, duration);
+    if chapters.is_empty() {
+        return Ok(chapters);
+    }
+    fs::write(
+        chapters_path(video),
+        serde_json::to_string_pretty(&chapters)?,
+    )?;
+    if !supports_chapters(video) {
+        return Ok(chapters);
+    }
+
+    let metadata_path = video.with_extension("ffmetadata.txt");
+    let chaptered = video.with_extension(format!(
+        "chaptered.{}",
+        video.extension().unwrap_or_default().to_string_lossy()
+    ));
+    fs::write(&metadata_path, ffmetadata(&chapters))?;
+
+    let args = chapters_command(video, &metadata_path, &chaptered).args()?;
+    let result = runner.output("ffmpeg", &args);
+    let _ = fs::remove_file(&metadata_path);
+
+    let result = result?;
+    if !result.success {
+        let _ = fs::remove_file(&chaptered);
+        let error = String::from_utf8_lossy(&result.stderr);
+        return Err(format!("Writing the chapters failed: {}", error).into());
+    }
+
+    fs::rename(&chaptered, video)?;
+    Ok(chapters)
+}
+
+pub fn add_chapters_gui(
+    runner: &dyn CommandRunner,
+    video: &Path,
+    marks: &[Mark],
+    log_sender: Sender<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log_sender.send(format!("Adding {} chapter marks...", marks.len()))?;
+
+    match add_chapters(runner, video, marks) {
+        Ok(chapters) => {
+            log_sender.send(format!(
+                "Wrote {} chapters, listed in {}",
+                chapters.len(),
+                chapters_path(video).display()
+            ))?;
+            Ok(())
+        }
+        Err(e) => {
+            log_sender.send(e.to_string())?;
+            Err(e)
+        }
+    }
+}
+
+/// Read marks from stdin while recording, one per line, with the line as the
+/// label.
+pub fn read_marks_from_stdin(marks: Sender<MarkRequest>) {
+    std::thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            let request = MarkRequest::now(line.trim());
+            match request.label.as_str() {
+                "" => println!("\rMarked a chapter"),
+                label => println!("\rMarked {}", label),
+            }
+            if marks.send(request).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::ScriptedRunner;
+
+    fn mark(t: f64, label: &str) -> Mark {
+        Mark::new(Duration::from_secs_f64(t), label)
+    }
+
+    #[test]
+    fn marks_start_chapters_until_the_next_one() {
+        let marks = [
+            mark(30.0, ""),
+            mark(10.0, "Setup = done"),
+            mark(90.0, "late"),
+        ];
+
+        assert_eq!(
+            chapters(&marks, 60.0),
+            [
+                Chapter {
+                    start: 0.0,
+                    end: 10.0,
+                    title: "Start".to_string()
+                },
+                Chapter {
+                    start: 10.0,
+                    end: 30.0,
+                    title: "Setup = done".to_string()
+                },
+                Chapter {
+                    start: 30.0,
+                    end: 60.0,
+                    title: "Chapter 2".to_string()
+                },
+            ]
+        );
+        assert!(chapters(&[], 60.0).is_empty());
+    }
+
+    #[test]
+    fn requests_are_placed_on_the_capture_clock() {
+        let capture_start = Instant::now();
+        let early = MarkRequest::now("Too soon");
+        let later = MarkRequest {
+            at: capture_start + Duration::from_millis(2500),
+            label: " ".to_string(),
+        };
+
+        assert_eq!(early.place(capture_start + Duration::from_secs(1)).t, 0.0);
+        assert_eq!(later.place(capture_start), mark(2.5, ""));
+    }
+
+    #[test]
+    fn metadata_lists_chapters_in_milliseconds() {
+        let chapters = chapters(&[mark(0.0, "Intro; part #1"), mark(1.5, "")], 2.25);
+
+        assert_eq!(
+            ffmetadata(&chapters),
+            ";FFMETADATA1\n\
+             [CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=1500\ntitle=Intro\\; part \\#1\n\
+             [CHAPTER]\nTIMEBASE=1/1000\nSTART=1500\nEND=2250\ntitle=Chapter 2\n"
+        );
+    }
+
+    #[test]
+    fn chapters_are_written_into_the_video_and_next_to_it() {
+        let dir = std::env::temp_dir().join(format!("rcrdr-chapters-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let video = dir.join("clip.mp4");
+        fs::write(&video, "recording").unwrap();
+        // What ffmpeg would write
+        fs::write(dir.join("clip.chaptered.mp4"), "with chapters").unwrap();
+
+        let info = r#"{"format": {"duration": "20.0", "format_name": "mp4"}, "streams": []}"#;
+        let runner = ScriptedRunner::new()
+            .respond(true, info, "")
+            .respond(true, "", "");
+        let chapters = add_chapters(&runner, &video, &[mark(5.0, "Demo")]).unwrap();
+
+        assert_eq!(chapters.len(), 2);
+        let listed: Vec<Chapter> =
+            serde_json::from_str(&fs::read_to_string(chapters_path(&video)).unwrap()).unwrap();
+        assert_eq!(listed, chapters);
+
+        let calls = runner.calls();
+        assert_eq!(calls.len(), 2);
+        let args = &calls[1];
+        assert!(args.windows(2).any(|pair| pair == ["-map_chapters", "1"]));
+        assert!(args.windows(2).any(|pair| pair == ["-c:v", "copy"]));
+        assert_eq!(fs::read_to_string(&video).unwrap(), "with chapters");
+        assert!(!dir.join("clip.ffmetadata.txt").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,4 +1,12 @@
-use clap::{Parser, Subcommand};
+use crate::condense::{CondenseOptions, IdleAction};
+use crate::crop::ScaleSpec;
+use crate::naming::OverwritePolicy;
+use crate::recorder::{CaptureSource, CursorMode, VideoCodec};
+use crate::screenshot::Geometry;
+use crate::subtitle::{CaptionPosition, CaptionStyle};
+use crate::watermark::Watermark;
+use crate::webcam::{Corner, Rgb, WebcamShape};
+use clap::{Args, Parser, Subcommand};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -11,17 +19,122 @@ pub struct Cli {
 pub enum Commands {
     /// Record screen to video file
     Record {
-        /// Output file path
-        #[arg(short, long, default_value = "output.mp4")]
-        output: String,
+        /// Output file path [default: the filename template in the output directory]
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Filename template, e.g. "{profile}_{date}_{counter}.mp4". Tokens: {date},
+        /// {time}, {profile}, {window_title}, {monitor}, {counter}, {hostname}
+        #[arg(short, long, conflicts_with = "output")]
+        template: Option<String>,
+
+        /// What to do if the output file already exists [default: from config, or increment]
+        #[arg(long, value_enum)]
+        overwrite: Option<OverwritePolicy>,
 
         /// Recording duration in seconds (0 for manual stop with Ctrl+C)
         #[arg(short, long, default_value_t = 0)]
         duration: u64,
 
-        /// Frame rate
-        #[arg(short, long, default_value_t = 30)]
-        fps: u32,
+        /// Seconds to count down before capture starts, to switch to the window to record
+        #[arg(long, default_value_t = 0)]
+        countdown: u64,
+
+        /// Frame rate [default: 30, or the profile's frame rate]
+        #[arg(short, long)]
+        fps: Option<u32>,
+
+        /// Named profile from the config file
+        #[arg(short, long)]
+        profile: Option<String>,
+
+        /// Capture source: "screen", "synthetic" (lavfi test pattern) or "display:<ffmpeg input>"
+        #[arg(long)]
+        source: Option<CaptureSource>,
+
+        /// Video codec
+        #[arg(long, value_enum)]
+        codec: Option<VideoCodec>,
+
+        /// Mouse pointer: drawn as usual, hidden, or highlighted with a halo drawn in
+        /// after recording (X11 only) [default: show, or the profile's setting]
+        #[arg(long, value_enum)]
+        cursor: Option<CursorMode>,
+
+        /// Record audio from the default input device
+        #[arg(long, conflicts_with = "no_audio")]
+        audio: bool,
+
+        /// Don't record audio, even if the profile enables it
+        #[arg(long)]
+        no_audio: bool,
+
+        /// Capture one frame every INTERVAL seconds and play them back at 30 fps. Audio
+        /// is not recorded
+        #[arg(long, value_name = "INTERVAL", value_parser = parse_interval, conflicts_with = "audio")]
+        timelapse: Option<f64>,
+
+        /// Overlay a V4L2 webcam, e.g. /dev/video0, in a corner of the recording (Linux only)
+        #[arg(long, value_name = "DEVICE")]
+        webcam: Option<String>,
+
+        /// Corner the webcam sits in
+        #[arg(long, value_enum, default_value_t = Corner::BottomRight, requires = "webcam")]
+        webcam_position: Corner,
+
+        /// Webcam width in pixels (height keeps the aspect ratio)
+        #[arg(long, default_value_t = 320, requires = "webcam")]
+        webcam_width: u32,
+
+        /// Webcam shape, a circle is cut out of the middle of the picture
+        #[arg(long, value_enum, default_value_t = WebcamShape::Rect, requires = "webcam")]
+        webcam_shape: WebcamShape,
+
+        /// Webcam border thickness in pixels
+        #[arg(long, default_value_t = 0, requires = "webcam")]
+        webcam_border: u32,
+
+        /// Webcam border color as hex, e.g. ff8800
+        #[arg(long, default_value = "ffffff", requires = "webcam")]
+        webcam_border_color: Rgb,
+
+        /// Also save the webcam on its own to <output>.webcam.<ext>
+        #[arg(long, requires = "webcam")]
+        webcam_file: bool,
+
+        /// Log mouse clicks and key presses to <output>.events.jsonl (X11 only)
+        #[arg(long)]
+        log_events: bool,
+
+        /// Burn the logged clicks and key presses into <output>.annotated.<ext> after
+        /// recording. Implies --log-events
+        #[arg(long)]
+        annotate: bool,
+
+        /// Read chapter marks from stdin, one label per line, even when it isn't a
+        /// terminal. Marks are read from a terminal without this
+        #[arg(long)]
+        marks_from_stdin: bool,
+
+        /// Listen on a Unix socket at PATH for "mark [label]" and "stop" commands,
+        /// one per line
+        #[arg(long, value_name = "PATH")]
+        control_socket: Option<String>,
+
+        /// Also write <output>.condensed.<ext> with the still, silent stretches cut
+        /// out or sped up, tuned with the condense flags
+        #[arg(long)]
+        condense: bool,
+
+        #[command(flatten)]
+        condense_options: CondenseArgs,
+
+        #[command(flatten)]
+        watermark: WatermarkArgs,
+
+        /// Print the ffmpeg command line instead of running it
+        #[arg(long)]
+        print_command: bool,
     },
 
     /// Convert video to GIF
@@ -33,6 +146,268 @@ pub enum Commands {
         /// Output GIF file
         #[arg(short, long, default_value = "output.gif")]
         output: String,
+
+        /// GIF frame rate
+        #[arg(long, default_value_t = 10)]
+        fps: u32,
+
+        /// GIF width in pixels (height keeps the aspect ratio)
+        #[arg(short, long, default_value_t = 640)]
+        width: u32,
+
+        /// Burn in the captions of this SRT file
+        #[arg(long)]
+        srt: Option<String>,
+
+        #[command(flatten)]
+        captions: CaptionStyleArgs,
+
+        #[command(flatten)]
+        watermark: WatermarkArgs,
+
+        /// Print the ffmpeg command line instead of running it
+        #[arg(long)]
+        print_command: bool,
+    },
+
+    /// Extract a poster frame or a contact sheet from a video as PNG
+    Thumbnail {
+        /// Input video file
+        input: String,
+
+        /// Output PNG file [default: <input>.png, or <input>_sheet.png with --grid]
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Time of the poster frame, in seconds or [HH:]MM:SS[.ms] [default: 10% in]
+        #[arg(long, value_parser = parse_seconds, conflicts_with = "grid")]
+        at: Option<f64>,
+
+        /// Build a contact sheet with COLUMNSxROWS evenly spaced frames, e.g. 4x4
+        #[arg(long, value_parser = parse_grid)]
+        grid: Option<(u32, u32)>,
+
+        /// Width in pixels of the poster frame, or of each contact sheet tile
+        #[arg(short, long, default_value_t = 320)]
+        width: u32,
+
+        /// Print the ffmpeg command line instead of running it
+        #[arg(long)]
+        print_command: bool,
+    },
+
+    /// Cut a part out of a recording, without re-encoding when it starts on a keyframe
+    Trim {
+        /// Input video file
+        input: String,
+
+        /// Start of the cut, in seconds or [HH:]MM:SS[.ms]
+        #[arg(long, value_parser = parse_seconds, default_value = "0")]
+        from: f64,
+
+        /// End of the cut, in seconds or [HH:]MM:SS[.ms] [default: the end of the video]
+        #[arg(long, value_parser = parse_seconds)]
+        to: Option<f64>,
+
+        /// Output video file [default: <input>.trimmed.<ext>]
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Re-encode even if the cut starts on a keyframe
+        #[arg(long)]
+        reencode: bool,
+
+        #[command(flatten)]
+        watermark: WatermarkArgs,
+
+        /// Print the ffmpeg command line instead of running it
+        #[arg(long)]
+        print_command: bool,
+    },
+
+    /// Crop a recording to a rectangle and/or scale it to another size
+    Crop {
+        /// Input video file
+        input: String,
+
+        /// Keep only this rectangle, as WIDTHxHEIGHT+X+Y, e.g. 1280x720+320+180
+        #[arg(long, required_unless_present = "scale")]
+        rect: Option<Geometry>,
+
+        /// Scale to a width ("1280"), a size ("1280x720") or a percentage ("50%")
+        #[arg(long)]
+        scale: Option<ScaleSpec>,
+
+        /// Output video file [default: <input>.cropped.<ext>]
+        #[arg(short, long)]
+        output: Option<String>,
+
+        #[command(flatten)]
+        watermark: WatermarkArgs,
+
+        /// Print the ffmpeg command line instead of running it
+        #[arg(long)]
+        print_command: bool,
+    },
+
+    /// Blur or pixelate rectangles of a recording while they show private data
+    Redact {
+        /// Input video file
+        input: String,
+
+        /// JSON or TOML file listing the rectangles, each with its start and end time
+        #[arg(long)]
+        spec: String,
+
+        /// Output video file [default: <input>.redacted.<ext>]
+        #[arg(short, long)]
+        output: Option<String>,
+
+        #[command(flatten)]
+        watermark: WatermarkArgs,
+
+        /// Print the ffmpeg command line instead of running it
+        #[arg(long)]
+        print_command: bool,
+    },
+
+    /// Add captions from an SRT file as a subtitle track, or burn them into the picture
+    Subtitle {
+        /// Input video file
+        input: String,
+
+        /// SRT file with the captions
+        #[arg(long)]
+        srt: String,
+
+        /// Draw the captions into the picture, styled with the --caption-* flags,
+        /// instead of adding a subtitle track players can switch on
+        #[arg(long)]
+        burn: bool,
+
+        /// Output video file, MP4, MOV, MKV or WebM for a subtitle track
+        /// [default: <input>.subtitled.<ext>]
+        #[arg(short, long)]
+        output: Option<String>,
+
+        #[command(flatten)]
+        captions: CaptionStyleArgs,
+
+        /// Print the ffmpeg command line instead of running it
+        #[arg(long)]
+        print_command: bool,
+    },
+
+    /// Cut out or speed up the stretches of a recording where nothing moves and
+    /// nothing is said, and report how much time that saved
+    Condense {
+        /// Input video file
+        input: String,
+
+        /// Output video file [default: <input>.condensed.<ext>]
+        #[arg(short, long)]
+        output: Option<String>,
+
+        #[command(flatten)]
+        options: CondenseArgs,
+
+        #[command(flatten)]
+        watermark: WatermarkArgs,
+    },
+
+    /// Join recordings into one video, in the order given
+    Concat {
+        /// Input video files
+        #[arg(required = true, num_args = 2..)]
+        inputs: Vec<String>,
+
+        /// Output video file
+        #[arg(short, long)]
+        output: String,
+
+        /// Crossfade between the recordings for this many seconds. Always re-encodes
+        #[arg(long, value_name = "SECONDS", value_parser = parse_interval)]
+        crossfade: Option<f64>,
+
+        #[command(flatten)]
+        watermark: WatermarkArgs,
+    },
+
+    /// Save a still of the screen, a region, a window or a monitor as PNG
+    Screenshot {
+        /// Output PNG file [default: screenshot_<date>_<time>.png in the output directory]
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Named profile from the config file, for its capture source and output directory
+        #[arg(short, long)]
+        profile: Option<String>,
+
+        /// Capture source: "screen", "synthetic" (lavfi test pattern) or "display:<ffmpeg input>"
+        #[arg(long)]
+        source: Option<CaptureSource>,
+
+        /// Only this region of the screen, as WIDTHxHEIGHT+X+Y, e.g. 800x600+100+50
+        #[arg(long, conflicts_with_all = ["window", "monitor"])]
+        region: Option<Geometry>,
+
+        /// Only this window, by X11 window id [default: the active window] (X11 only)
+        #[arg(long, value_name = "ID", num_args = 0..=1, default_missing_value = "active", conflicts_with = "monitor")]
+        window: Option<String>,
+
+        /// Only this monitor, by xrandr name or index, e.g. HDMI-1 (X11 only)
+        #[arg(long)]
+        monitor: Option<String>,
+
+        /// Mouse pointer: drawn as usual or hidden [default: show, or the profile's setting]
+        #[arg(long, value_enum)]
+        cursor: Option<CursorMode>,
+
+        /// Also copy the image to the clipboard (xclip on Linux)
+        #[arg(long)]
+        clipboard: bool,
+
+        /// Print the ffmpeg command line instead of running it
+        #[arg(long)]
+        print_command: bool,
+    },
+
+    /// Burn click ripples and key captions from an event log into a copy of a recording
+    Annotate {
+        /// Input video file
+        input: String,
+
+        /// Event log written by `record --log-events` [default: <input>.events.jsonl]
+        #[arg(long)]
+        events: Option<String>,
+
+        /// Output video file [default: <input>.annotated.<ext>]
+        #[arg(short, long)]
+        output: Option<String>,
+
+        #[command(flatten)]
+        watermark: WatermarkArgs,
+
+        /// Print the ffmpeg command line instead of running it
+        #[arg(long)]
+        print_command: bool,
+    },
+
+    /// The GUI's countdown window, run as a separate process
+    #[command(hide = true)]
+    CountdownOverlay {
+        /// Seconds to count down
+        seconds: u64,
+    },
+
+    /// Show duration, container and stream details of a media file
+    Info {
+        /// Input media file
+        input: String,
+
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
     },
 
     /// Run a test recording to verify everything works
@@ -42,3 +417,268 @@ pub enum Commands {
         output: String,
     },
 }
+
+/// Watermark flags shared by recording and every export.
+#[derive(Args, Debug, Clone, Default)]
+pub struct WatermarkArgs {
+    /// Draw this text as a watermark
+    #[arg(long, value_name = "TEXT", conflicts_with = "watermark_image")]
+    pub watermark_text: Option<String>,
+
+    /// Lay this PNG logo over the video as a watermark
+    #[arg(long, value_name = "PNG")]
+    pub watermark_image: Option<String>,
+
+    /// Use the watermark saved in this profile
+    #[arg(long, value_name = "PROFILE")]
+    pub watermark_profile: Option<String>,
+
+    /// Watermark font, a fontconfig name such as "DejaVu Sans" or a font file
+    #[arg(long)]
+    pub watermark_font: Option<String>,
+
+    /// Watermark text size in pixels [default: 24]
+    #[arg(long)]
+    pub watermark_size: Option<u32>,
+
+    /// Watermark text color as hex, e.g. ff8800 [default: ffffff]
+    #[arg(long)]
+    pub watermark_color: Option<Rgb>,
+
+    /// Watermark opacity from 0 to 1 [default: 0.7]
+    #[arg(long)]
+    pub watermark_opacity: Option<f32>,
+
+    /// Corner the watermark sits in [default: bottom-right]
+    #[arg(long, value_enum)]
+    pub watermark_position: Option<Corner>,
+
+    /// Watermark distance from the edges in pixels [default: 20]
+    #[arg(long)]
+    pub watermark_margin: Option<u32>,
+
+    /// Leave out the watermark, even if the profile has one
+    #[arg(long, conflicts_with_all = ["watermark_text", "watermark_image", "watermark_profile"])]
+    pub no_watermark: bool,
+}
+
+impl WatermarkArgs {
+    /// The watermark to draw: `saved`, usually from a profile, with the flags
+    /// applied over it. A text or image flag replaces what `saved` draws.
+    pub fn resolve(&self, saved: Option<Watermark>) -> Result<Option<Watermark>, String> {
+        if self.no_watermark {
+            return Ok(None);
+        }
+
+        let mut watermark = match (&self.watermark_text, &self.watermark_image, saved) {
+            (None, None, None) => return Ok(None),
+            (None, None, Some(saved)) => saved,
+            (text, image, saved) => Watermark {
+                text: text.clone(),
+                image: image.as_ref().map(Into::into),
+                ..saved.unwrap_or_default()
+            },
+        };
+        if let Some(font) = &self.watermark_font {
+            watermark.font = Some(font.clone());
+        }
+        if let Some(size) = self.watermark_size {
+            watermark.size = size;
+        }
+        if let Some(color) = self.watermark_color {
+            watermark.color = color;
+        }
+        if let Some(opacity) = self.watermark_opacity {
+            watermark.opacity = opacity;
+        }
+        if let Some(corner) = self.watermark_position {
+            watermark.corner = corner;
+        }
+        if let Some(margin) = self.watermark_margin {
+            watermark.margin = margin;
+        }
+
+        watermark.check()?;
+        Ok(Some(watermark))
+    }
+}
+
+/// Styling flags for burnt-in captions, shared by videos and GIFs.
+#[derive(Args, Debug, Clone, Default)]
+pub struct CaptionStyleArgs {
+    /// Caption font, a fontconfig name such as "DejaVu Sans"
+    #[arg(long)]
+    pub caption_font: Option<String>,
+
+    /// Caption font size, scaled with the video height [default: 20]
+    #[arg(long)]
+    pub caption_size: Option<u32>,
+
+    /// Caption text color as hex, e.g. ffff00 [default: ffffff]
+    #[arg(long)]
+    pub caption_color: Option<Rgb>,
+
+    /// Caption outline thickness, 0 for none [default: 2]
+    #[arg(long)]
+    pub caption_outline: Option<u32>,
+
+    /// Caption outline color as hex [default: 000000]
+    #[arg(long)]
+    pub caption_outline_color: Option<Rgb>,
+
+    /// Where the captions sit [default: bottom]
+    #[arg(long, value_enum)]
+    pub caption_position: Option<CaptionPosition>,
+
+    /// Caption distance from the top or bottom edge [default: 20]
+    #[arg(long)]
+    pub caption_margin: Option<u32>,
+}
+
+impl CaptionStyleArgs {
+    /// The default style with the flags applied over it.
+    pub fn style(&self) -> CaptionStyle {
+        let defaults = CaptionStyle::default();
+        CaptionStyle {
+            font: self.caption_font.clone(),
+            size: self.caption_size.unwrap_or(defaults.size),
+            color: self.caption_color.unwrap_or(defaults.color),
+            outline: self.caption_outline.unwrap_or(defaults.outline),
+            outline_color: self.caption_outline_color.unwrap_or(defaults.outline_color),
+            position: self.caption_position.unwrap_or(defaults.position),
+            margin: self.caption_margin.unwrap_or(defaults.margin),
+        }
+    }
+}
+
+/// Thresholds for condensing idle stretches, shared by `condense` and `record --condense`.
+#[derive(Args, Debug, Clone)]
+pub struct CondenseArgs {
+    /// Shortest still, silent stretch to condense, in seconds [default: 2]
+    #[arg(long, value_name = "SECONDS", value_parser = parse_interval)]
+    pub min_idle: Option<f64>,
+
+    /// How much frames may change and still count as still, from 0 to 1 [default: 0.003]
+    #[arg(long, value_name = "NOISE")]
+    pub freeze_noise: Option<f64>,
+
+    /// Audio below this level in dB counts as silence [default: -50]
+    #[arg(long, value_name = "DB", allow_hyphen_values = true)]
+    pub silence_db: Option<f64>,
+
+    /// Seconds kept at each end of an idle stretch [default: 0.25]
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    pub padding: Option<f64>,
+
+    /// Play idle stretches this many times faster instead of cutting them out
+    #[arg(long, value_name = "FACTOR", value_parser = parse_speed)]
+    pub speed: Option<f64>,
+}
+
+impl CondenseArgs {
+    /// The default options with the flags applied over them.
+    pub fn options(&self) -> CondenseOptions {
+        let defaults = CondenseOptions::default();
+        CondenseOptions {
+            min_idle: self.min_idle.unwrap_or(defaults.min_idle),
+            freeze_noise: self.freeze_noise.unwrap_or(defaults.freeze_noise),
+            silence_db: self.silence_db.unwrap_or(defaults.silence_db),
+            padding: self.padding.unwrap_or(defaults.padding),
+            action: self.speed.map_or(IdleAction::Cut, IdleAction::SpeedUp),
+        }
+    }
+}
+
+impl Commands {
+    /// Whether the command runs ffmpeg. Only printing an ffmpeg command line needs
+    /// no ffmpeg install, but thumbnails still probe the input to place their frames.
+    pub fn needs_ffmpeg(&self) -> bool {
+        !matches!(
+            self,
+            Commands::Record {
+                print_command: true,
+                ..
+            } | Commands::ConvertToGif {
+                print_command: true,
+                ..
+            } | Commands::Screenshot {
+                print_command: true,
+                ..
+            } | Commands::Annotate {
+                print_command: true,
+                ..
+            } | Commands::Crop {
+                print_command: true,
+                ..
+            } | Commands::Redact {
+                print_command: true,
+                ..
+            } | Commands::Subtitle {
+                print_command: true,
+                ..
+            } | Commands::CountdownOverlay { .. }
+        )
+    }
+}
+
+/// Parse a time given in seconds ("90", "12.5") or as [HH:]MM:SS[.ms] ("1:30").
+pub fn parse_seconds(value: &str) -> Result<f64, String> {
+    let mut seconds = 0.0;
+    for part in value.trim().split(':') {
+        let part: f64 = part
+            .parse()
+            .map_err(|_| format!("Invalid time '{}', expected seconds or [HH:]MM:SS", value))?;
+        seconds = seconds * 60.0 + part;
+    }
+
+    if value.split(':').count() > 3 || seconds < 0.0 {
+        return Err(format!(
+            "Invalid time '{}', expected seconds or [HH:]MM:SS",
+            value
+        ));
+    }
+
+    Ok(seconds)
+}
+
+/// Parse a speed-up factor, more than 1 and at most 100, as far as atempo goes.
+pub fn parse_speed(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(speed) if speed > 1.0 && speed <= 100.0 => Ok(speed),
+        _ => Err(format!(
+            "Invalid speed '{}', expected a factor above 1, up to 100",
+            value
+        )),
+    }
+}
+
+/// Parse a timelapse interval, like `parse_seconds` but more than zero.
+pub fn parse_interval(value: &str) -> Result<f64, String> {
+    match parse_seconds(value)? {
+        seconds if seconds > 0.0 => Ok(seconds),
+        _ => Err(format!(
+            "Invalid interval '{}', expected more than 0 seconds",
+            value
+        )),
+    }
+}
+
+/// Parse a grid layout such as "4x4" into (columns, rows).
+pub fn parse_grid(value: &str) -> Result<(u32, u32), String> {
+    let error = || {
+        format!(
+            "Invalid grid '{}', expected COLUMNSxROWS such as 4x4",
+            value
+        )
+    };
+
+    let (columns, rows) = value.trim().split_once(['x', 'X']).ok_or_else(error)?;
+    let columns: u32 = columns.parse().map_err(|_| error())?;
+    let rows: u32 = rows.parse().map_err(|_| error())?;
+
+    if columns == 0 || rows == 0 {
+        return Err(error());
+    }
+
+    Ok((columns, rows))
+}
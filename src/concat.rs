@@ -0,0 +1,509 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+
+use crate::ffmpeg::{FfmpegCommand, FilterGraph, Input};
+use crate::media::{probe, MediaInfo, StreamInfo, StreamKind};
+use crate::recorder::VideoCodec;
+use crate::runner::CommandRunner;
+use crate::watermark::{apply_watermark, Watermark};
+
+/// How the recordings are joined.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConcatPlan {
+    /// The streams match, the packets are copied one file after the other
+    Demuxer,
+    /// Scaled, padded and resampled to the first recording's format, then encoded
+    Normalize {
+        width: u32,
+        height: u32,
+        fps: f64,
+        audio: bool,
+    },
+}
+
+impl ConcatPlan {
+    /// Copy when every recording has the same video and audio format, otherwise
+    /// normalize. Crossfades and watermarks always need the filter graph.
+    pub fn for_inputs(
+        infos: &[MediaInfo],
+        crossfade: Option<f64>,
+        watermark: Option<&Watermark>,
+    ) -> Result<Self, String> {
+        let first = infos.first().ok_or("Nothing to concatenate.")?;
+        let video = first
+            .video_stream()
+            .ok_or_else(|| format!("{} has no video stream", first.path.display()))?;
+
+        for info in infos {
+            if info.video_stream().is_none() {
+                return Err(format!("{} has no video stream", info.path.display()));
+            }
+        }
+
+        let matching = infos.iter().all(|info| {
+            same_video(info.video_stream(), Some(video))
+                && same_audio(audio_stream(info), audio_stream(first))
+        });
+        if matching && crossfade.is_none() && watermark.is_none() {
+            return Ok(ConcatPlan::Demuxer);
+        }
+
+        Ok(ConcatPlan::Normalize {
+            width: video.width.unwrap_or(1280),
+            height: video.height.unwrap_or(720),
+            fps: video.fps.unwrap_or(30.0),
+            // Audio is only kept when there is some to join from every recording
+            audio: infos.iter().all(|info| audio_stream(info).is_some()),
+        })
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ConcatPlan::Demuxer => "stream copy",
+            ConcatPlan::Normalize { .. } => "re-encode",
+        }
+    }
+}
+
+fn audio_stream(info: &MediaInfo) -> Option<&StreamInfo> {
+    info.streams
+        .iter()
+        .find(|stream| stream.kind == StreamKind::Audio)
+}
+
+fn same_video(a: Option<&StreamInfo>, b: Option<&StreamInfo>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            a.codec == b.codec
+                && a.width == b.width
+                && a.height == b.height
+                && a.pixel_format == b.pixel_format
+                && match (a.fps, b.fps) {
+                    (Some(a), Some(b)) => (a - b).abs() < 0.01,
+                    (a, b) => a == b,
+                }
+        }
+        _ => false,
+    }
+}
+
+fn same_audio(a: Option<&StreamInfo>, b: Option<&StreamInfo>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            a.codec == b.codec && a.sample_rate == b.sample_rate && a.channels == b.channels
+        }
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// The concat demuxer's list of `inputs`, with absolute paths so the list can
+/// live anywhere.
+pub fn demuxer_list(inputs: &[PathBuf]) -> Result<String, Box<dyn std::error::Error>> {
+    let mut list = String::new();
+    for input in inputs {
+        let path = fs::canonicalize(input)
+            .map_err(|e| format!("Cannot read {}: {}", input.display(), e))?;
+        list.push_str(&format!(
+            "file '{}'\n",
+            path.to_string_lossy().replace('\'', r"'\''")
+        ));
+    }
+    Ok(list)
+}
+
+/// The ffmpeg command that joins the recordings in the demuxer `list` without re-encoding.
+pub fn demuxer_command(list: &Path, output: &Path) -> FfmpegCommand {
+    FfmpegCommand::new()
+        .overwrite()
+        .input(Input::file(list).option("f", "concat").option("safe", 0))
+        .output_option("c", "copy")
+        .output(output)
+}
+
+/// The ffmpeg command that brings the recordings to one format and joins them,
+/// crossfading for `crossfade` seconds between them if asked.
+#[allow(clippy::too_many_arguments)]
+pub fn normalize_command(
+    infos: &[MediaInfo],
+    output: &Path,
+    width: u32,
+    height: u32,
+    fps: f64,
+    audio: bool,
+    crossfade: Option<f64>,
+    watermark: Option<&Watermark>,
+) -> Result<FfmpegCommand, Box<dyn std::error::Error>> {
+    let mut command = FfmpegCommand::new().overwrite();
+    let mut chains = Vec::new();
+
+    for (i, info) in infos.iter().enumerate() {
+        command = command.input(Input::file(&info.path));
+        chains.push(format!(
+            "[{i}:v]scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,setsar=1,fps={fps},format=yuv420p[v{i}]",
+            i = i,
+            w = width,
+            h = height,
+            fps = fps
+        ));
+        if audio {
+            chains.push(format!(
+                "[{i}:a]aresample=48000,aformat=channel_layouts=stereo[a{i}]",
+                i = i
+            ));
+        }
+    }
+
+    match crossfade {
+        None => {
+            let pads: String = (0..infos.len())
+                .map(|i| {
+                    if audio {
+                        format!("[v{i}][a{i}]", i = i)
+                    } else {
+                        format!("[v{}]", i)
+                    }
+                })
+                .collect();
+            let (sound, outputs) = if audio { (1, "[v][a]") } else { (0, "[v]") };
+            chains.push(format!(
+                "{}concat=n={}:v=1:a={}{}",
+                pads,
+                infos.len(),
+                sound,
+                outputs
+            ));
+        }
+        Some(fade) => {
+            if let Some(short) = infos.iter().find(|info| info.duration_secs <= fade) {
+                return Err(format!(
+                    "{} is too short for a {}s crossfade",
+                    short.path.display(),
+                    fade
+                )
+                .into());
+            }
+
+            // Each fade starts `fade` seconds before the end of everything so far
+            let (mut video, mut sound) = ("[v0]".to_string(), "[a0]".to_string());
+            let mut offset = 0.0;
+            for i in 1..infos.len() {
+                offset += infos[i - 1].duration_secs - fade;
+                chains.push(format!(
+                    "{}[v{}]xfade=transition=fade:duration={}:offset={:.3}[x{}]",
+                    video, i, fade, offset, i
+                ));
+                video = format!("[x{}]", i);
+                if audio {
+                    chains.push(format!("{}[a{}]acrossfade=d={}[ax{}]", sound, i, fade, i));
+                    sound = format!("[ax{}]", i);
+                }
+            }
+
+            // Rename the last labels to the outputs
+            chains.push(format!("{}null[v]", video));
+            if audio {
+                chains.push(format!("{}anull[a]", sound));
+            }
+        }
+    }
+
+    let codec = VideoCodec::for_path(output);
+    command = command
+        .filter(FilterGraph::Complex(chains))
+        .map("[v]")
+        .video_encoder(codec.encoder());
+    if audio {
+        command = command.map("[a]").audio_encoder(codec.audio_encoder());
+    }
+
+    Ok(apply_watermark(command, watermark).output(output))
+}
+
+/// Join `inputs` into `output` in order, copying the streams when they match.
+pub fn concat(
+    runner: &dyn CommandRunner,
+    inputs: &[PathBuf],
+    output: &Path,
+    crossfade: Option<f64>,
+    watermark: Option<&Watermark>,
+) -> Result<ConcatPlan, Box<dyn std::error::Error>> {
+    if inputs.len() < 2 {
+        return Err("Concatenating needs at least two recordings.".into());
+    }
+    if inputs.iter().any(|input| input == output) {
+        return Err(format!("The output file {} is also an input.", output.display()).into());
+    }
+
+    let infos = inputs
+        .iter()
+        .map(|input| probe(runner, input))
+        .collect::<Result<Vec<_>, _>>()?;
+    let plan = ConcatPlan::for_inputs(&infos, crossfade, watermark)?;
+
+    let result = match plan {
+        ConcatPlan::Demuxer => {
+            let list = output.with_extension("concat.txt");
+            fs::write(&list, demuxer_list(inputs)?)?;
+            let result = runner.output("ffmpeg", &demuxer_command(&list, output).args()?);
+            let _ = fs::remove_file(&list);
+            result?
+        }
+        ConcatPlan::Normalize {
+            width,
+            height,
+            fps,
+            audio,
+        } => {
+            let command = normalize_command(
+                &infos, output, width, height, fps, audio, crossfade, watermark,
+            )?;
+            runner.output("ffmpeg", &command.args()?)?
+        }
+    };
+
+    if !result.success {
+        let _ = fs::remove_file(output);
+        let error = String::from_utf8_lossy(&result.stderr);
+        return Err(format!("Concatenating failed: {}", error).into());
+    }
+
+    Ok(plan)
+}
+
+pub fn concat_gui(
+    runner: &dyn CommandRunner,
+    inputs: &[PathBuf],
+    output: &Path,
+    crossfade: Option<f64>,
+    watermark: Option<&Watermark>,
+    log_sender: Sender<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log_sender.send(format!("Concatenating {} recordings...", inputs.len()))?;
+
+    match concat(runner, inputs, output, crossfade, watermark) {
+        Ok(plan) => {
+            log_sender.send(format!(
+                "Concatenated to {} ({})",
+                output.display(),
+                plan.label()
+            ))?;
+            Ok(())
+        }
+        Err(e) => {
+            log_sender.send(e.to_string())?;
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(path: &str, width: u32, fps: f64, audio: bool, duration: f64) -> MediaInfo {
+        let mut streams = vec![StreamInfo {
+            index: 0,
+            kind: StreamKind::Video,
+            codec: "h264".to_string(),
+            width: Some(width),
+            height: Some(width * 9 / 16),
+            fps: Some(fps),
+            pixel_format: Some("yuv420p".to_string()),
+            sample_rate: None,
+            channels: None,
+            bitrate: None,
+        }];
+        if audio {
+            streams.push(StreamInfo {
+                index: 1,
+                kind: StreamKind::Audio,
+                codec: "aac".to_string(),
+                width: None,
+                height: None,
+                fps: None,
+                pixel_format: None,
+                sample_rate: Some(48000),
+                channels: Some(2),
+                bitrate: None,
+            });
+        }
+
+        MediaInfo {
+            path: PathBuf::from(path),
+            container: "mov,mp4,m4a,3gp,3g2,mj2".to_string(),
+            duration_secs: duration,
+            size_bytes: None,
+            bitrate: None,
+            streams,
+        }
+    }
+
+    #[test]
+    fn matching_recordings_are_copied() {
+        let infos = [
+            info("a.mp4", 1280, 30.0, true, 5.0),
+            info("b.mp4", 1280, 29.999, true, 3.0),
+        ];
+        assert_eq!(
+            ConcatPlan::for_inputs(&infos, None, None),
+            Ok(ConcatPlan::Demuxer)
+        );
+
+        // Crossfades and watermarks have to be rendered
+        assert!(matches!(
+            ConcatPlan::for_inputs(&infos, Some(0.5), None),
+            Ok(ConcatPlan::Normalize { audio: true, .. })
+        ));
+        let watermark = Watermark {
+            text: Some("internal".to_string()),
+            ..Watermark::default()
+        };
+        assert!(matches!(
+            ConcatPlan::for_inputs(&infos, None, Some(&watermark)),
+            Ok(ConcatPlan::Normalize { .. })
+        ));
+    }
+
+    #[test]
+    fn different_recordings_are_normalized_to_the_first() {
+        let infos = [
+            info("a.mp4", 1920, 30.0, true, 5.0),
+            info("b.mp4", 1280, 60.0, false, 3.0),
+        ];
+        let plan = ConcatPlan::for_inputs(&infos, None, None).unwrap();
+        assert_eq!(
+            plan,
+            ConcatPlan::Normalize {
+                width: 1920,
+                height: 1080,
+                fps: 30.0,
+                audio: false
+            }
+        );
+
+        let args = normalize_command(
+            &infos,
+            Path::new("out.mp4"),
+            1920,
+            1080,
+            30.0,
+            false,
+            None,
+            None,
+        )
+        .unwrap()
+        .args()
+        .unwrap();
+        let graph = &args[args
+            .iter()
+            .position(|arg| arg == "-filter_complex")
+            .unwrap()
+            + 1];
+        assert_eq!(
+            graph,
+            "[0:v]scale=1920:1080:force_original_aspect_ratio=decrease,pad=1920:1080:(ow-iw)/2:(oh-ih)/2,setsar=1,fps=30,format=yuv420p[v0];\
+             [1:v]scale=1920:1080:force_original_aspect_ratio=decrease,pad=1920:1080:(ow-iw)/2:(oh-ih)/2,setsar=1,fps=30,format=yuv420p[v1];\
+             [v0][v1]concat=n=2:v=1:a=0[v]"
+        );
+    }
+
+    #[test]
+    fn crossfades_overlap_the_recordings() {
+        let infos = [
+            info("a.mp4", 1280, 30.0, true, 5.0),
+            info("b.mp4", 1280, 30.0, true, 3.0),
+            info("c.mp4", 1280, 30.0, true, 4.0),
+        ];
+        let args = normalize_command(
+            &infos,
+            Path::new("out.mp4"),
+            1280,
+            720,
+            30.0,
+            true,
+            Some(1.0),
+            None,
+        )
+        .unwrap()
+        .args()
+        .unwrap();
+        let graph = &args[args
+            .iter()
+            .position(|arg| arg == "-filter_complex")
+            .unwrap()
+            + 1];
+
+        assert!(graph.contains("[v0][v1]xfade=transition=fade:duration=1:offset=4.000[x1]"));
+        assert!(graph.contains("[x1][v2]xfade=transition=fade:duration=1:offset=6.000[x2]"));
+        assert!(graph.contains("[ax1][a2]acrossfade=d=1[ax2]"));
+        assert!(graph.ends_with("[x2]null[v];[ax2]anull[a]"));
+
+        assert!(args.windows(2).any(|pair| pair == ["-c:a", "aac"]));
+
+        let short = [
+            info("a.mp4", 1280, 30.0, true, 5.0),
+            info("b.mp4", 1280, 30.0, true, 0.5),
+        ];
+        assert!(normalize_command(
+            &short,
+            Path::new("out.mp4"),
+            1280,
+            720,
+            30.0,
+            true,
+            Some(1.0),
+            None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn webm_output_takes_vp9_and_opus() {
+        let infos = [
+            info("a.mp4", 1280, 30.0, true, 5.0),
+            info("b.mp4", 1280, 30.0, true, 3.0),
+        ];
+        let args = normalize_command(
+            &infos,
+            Path::new("out.webm"),
+            1280,
+            720,
+            30.0,
+            true,
+            None,
+            None,
+        )
+        .unwrap()
+        .args()
+        .unwrap();
+
+        let graph = args
+            .iter()
+            .position(|arg| arg == "-filter_complex")
+            .unwrap();
+        assert_eq!(
+            args[graph + 2..],
+            [
+                "-map",
+                "[v]",
+                "-map",
+                "[a]",
+                "-c:v",
+                "libvpx-vp9",
+                "-pix_fmt",
+                "yuv420p",
+                "-crf",
+                "32",
+                "-b:v",
+                "0",
+                "-c:a",
+                "libopus",
+                "-b:a",
+                "128k",
+                "out.webm"
+            ]
+        );
+    }
+}
@@ -0,0 +1,489 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+
+use crate::ffmpeg::{FfmpegCommand, FilterGraph, Input};
+use crate::media::{probe, StreamKind};
+use crate::recorder::VideoCodec;
+use crate::runner::CommandRunner;
+use crate::watermark::{apply_watermark, Watermark};
+
+/// What happens to idle stretches.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IdleAction {
+    Cut,
+    /// Play them this many times faster
+    SpeedUp(f64),
+}
+
+/// Thresholds for finding the dead time in a recording.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CondenseOptions {
+    /// Shortest still, silent stretch that is condensed, in seconds
+    pub min_idle: f64,
+    /// How much frames may differ and still count as still (freezedetect's noise)
+    pub freeze_noise: f64,
+    /// Audio quieter than this, in dB, counts as silence
+    pub silence_db: f64,
+    /// Seconds left alone at each end of a stretch, so cuts don't feel abrupt
+    pub padding: f64,
+    pub action: IdleAction,
+}
+
+impl Default for CondenseOptions {
+    fn default() -> Self {
+        Self {
+            min_idle: 2.0,
+            freeze_noise: 0.003,
+            silence_db: -50.0,
+            padding: 0.25,
+            action: IdleAction::Cut,
+        }
+    }
+}
+
+/// How much a condensed recording got shorter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CondenseReport {
+    pub stretches: usize,
+    pub original: f64,
+    pub removed: f64,
+}
+
+impl fmt::Display for CondenseReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Removed {:.1}s of {:.1}s in {} idle {}",
+            self.removed,
+            self.original,
+            self.stretches,
+            if self.stretches == 1 {
+                "stretch"
+            } else {
+                "stretches"
+            }
+        )
+    }
+}
+
+// A part of the recording played at `speed`
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Segment {
+    start: f64,
+    end: f64,
+    speed: f64,
+}
+
+/// "clip.mp4" -> "clip.condensed.mp4", where the condensed copy goes by default.
+pub fn condensed_path(video: &Path) -> PathBuf {
+    match video.extension() {
+        Some(extension) => {
+            video.with_extension(format!("condensed.{}", extension.to_string_lossy()))
+        }
+        None => video.with_extension("condensed"),
+    }
+}
+
+/// The ffmpeg command that logs still stretches of `input`, and silent ones when
+/// it has `audio`, without writing anything.
+pub fn detect_command(input: &Path, options: &CondenseOptions, audio: bool) -> FfmpegCommand {
+    let mut chains = vec![format!(
+        "[0:v]freezedetect=n={}:d={}[v]",
+        options.freeze_noise, options.min_idle
+    )];
+    if audio {
+        chains.push(format!(
+            "[0:a]silencedetect=n={}dB:d={}[a]",
+            options.silence_db, options.min_idle
+        ));
+    }
+
+    let mut command = FfmpegCommand::new()
+        .input(Input::file(input))
+        .filter(FilterGraph::Complex(chains))
+        .map("[v]");
+    if audio {
+        command = command.map("[a]");
+    }
+    command.output_option("f", "null").output("-")
+}
+
+// The number logged after `key`, as in "lavfi.freezedetect.freeze_start: 3.5"
+fn value_after(line: &str, key: &str) -> Option<f64> {
+    let rest = &line[line.find(key)? + key.len()..];
+    rest.trim_start_matches([':', ' '])
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// The stretches between `start_key` and `end_key` in a detection log. One
+/// still going at the end lasts until `duration`.
+pub fn detected_stretches(
+    log: &str,
+    start_key: &str,
+    end_key: &str,
+    duration: f64,
+) -> Vec<(f64, f64)> {
+    let mut stretches = Vec::new();
+    let mut start = None;
+    for line in log.lines() {
+        if let Some(t) = value_after(line, start_key) {
+            start = Some(t);
+        } else if let Some(t) = value_after(line, end_key) {
+            if let Some(start) = start.take() {
+                stretches.push((start, t));
+            }
+        }
+    }
+    if let Some(start) = start {
+        stretches.push((start, duration));
+    }
+    stretches
+}
+
+// The overlap of two sorted lists of stretches
+fn intersect(a: &[(f64, f64)], b: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let (mut i, mut j) = (0, 0);
+    let mut overlap = Vec::new();
+    while i < a.len() && j < b.len() {
+        let start = a[i].0.max(b[j].0);
+        let end = a[i].1.min(b[j].1);
+        if start < end {
+            overlap.push((start, end));
+        }
+        if a[i].1 < b[j].1 {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    overlap
+}
+
+/// The idle stretches to condense: still and, if there is audio, silent too, at
+/// least `min_idle` long, less the padding at each end.
+pub fn idle_stretches(
+    still: &[(f64, f64)],
+    silent: Option<&[(f64, f64)]>,
+    options: &CondenseOptions,
+) -> Vec<(f64, f64)> {
+    let idle = match silent {
+        Some(silent) => intersect(still, silent),
+        None => still.to_vec(),
+    };
+    idle.into_iter()
+        .filter(|(start, end)| end - start >= options.min_idle)
+        .map(|(start, end)| (start + options.padding, end - options.padding))
+        .filter(|(start, end)| start < end)
+        .collect()
+}
+
+// The parts of a `duration` second video to keep, with the idle ones sped up
+// or left out
+fn segments(idle: &[(f64, f64)], duration: f64, action: IdleAction) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut position = 0.0;
+    for &(start, end) in idle {
+        if start > position {
+            segments.push(Segment {
+                start: position,
+                end: start,
+                speed: 1.0,
+            });
+        }
+        if let IdleAction::SpeedUp(speed) = action {
+            segments.push(Segment { start, end, speed });
+        }
+        position = end;
+    }
+    if position < duration {
+        segments.push(Segment {
+            start: position,
+            end: duration,
+            speed: 1.0,
+        });
+    }
+    segments
+}
+
+/// Seconds taken out of the video by condensing `idle`.
+pub fn removed_time(idle: &[(f64, f64)], action: IdleAction) -> f64 {
+    let idle: f64 = idle.iter().map(|(start, end)| end - start).sum();
+    match action {
+        IdleAction::Cut => idle,
+        IdleAction::SpeedUp(speed) => idle * (1.0 - 1.0 / speed),
+    }
+}
+
+/// Filter chains that join the segments of input 0 back together as "[v]", and
+/// "[a]" with `audio`.
+fn condense_filters(segments: &[Segment], duration: f64, audio: bool) -> Vec<String> {
+    let trim = |segment: &Segment| match segment.end < duration {
+        true => format!("start={:.3}:end={:.3}", segment.start, segment.end),
+        false => format!("start={:.3}", segment.start),
+    };
+
+    let count = segments.len();
+    let labels =
+        |prefix: &str| -> String { (0..count).map(|i| format!("[{}{}]", prefix, i)).collect() };
+    let mut chains = vec![format!("[0:v]split={}{}", count, labels("sv"))];
+    if audio {
+        chains.push(format!("[0:a]asplit={}{}", count, labels("sa")));
+    }
+
+    let mut joined = String::new();
+    for (i, segment) in segments.iter().enumerate() {
+        let pts = match segment.speed == 1.0 {
+            true => "PTS-STARTPTS".to_string(),
+            false => format!("(PTS-STARTPTS)/{}", segment.speed),
+        };
+        chains.push(format!(
+            "[sv{i}]trim={},setpts={}[v{i}]",
+            trim(segment),
+            pts,
+            i = i
+        ));
+        joined.push_str(&format!("[v{}]", i));
+
+        if audio {
+            let tempo = match segment.speed == 1.0 {
+                true => String::new(),
+                false => format!(",atempo={}", segment.speed),
+            };
+            chains.push(format!(
+                "[sa{i}]atrim={},asetpts=PTS-STARTPTS{}[a{i}]",
+                trim(segment),
+                tempo,
+                i = i
+            ));
+            joined.push_str(&format!("[a{}]", i));
+        }
+    }
+
+    let outputs = if audio { "[v][a]" } else { "[v]" };
+    chains.push(format!(
+        "{}concat=n={}:v=1:a={}{}",
+        joined,
+        count,
+        u8::from(audio),
+        outputs
+    ));
+    chains
+}
+
+/// The ffmpeg command that writes `input` without, or with sped up, `idle`
+/// stretches to `output`.
+pub fn condense_command(
+    input: &Path,
+    output: &Path,
+    idle: &[(f64, f64)],
+    duration: f64,
+    audio: bool,
+    action: IdleAction,
+    watermark: Option<&Watermark>,
+) -> FfmpegCommand {
+    let segments = segments(idle, duration, action);
+    let mut command = FfmpegCommand::new()
+        .overwrite()
+        .input(Input::file(input))
+        .filter(FilterGraph::Complex(condense_filters(
+            &segments, duration, audio,
+        )))
+        .map("[v]");
+    if audio {
+        command = command.map("[a]");
+    }
+
+    apply_watermark(command, watermark)
+        .video_encoder(VideoCodec::for_path(output).encoder())
+        .output(output)
+}
+
+/// Find the idle stretches of `input` and write it condensed to `output`. Nothing
+/// is written when there are none.
+pub fn condense(
+    runner: &dyn CommandRunner,
+    input: &Path,
+    output: &Path,
+    options: &CondenseOptions,
+    watermark: Option<&Watermark>,
+) -> Result<CondenseReport, Box<dyn std::error::Error>> {
+    let info = probe(runner, input)?;
+    let duration = info.duration_secs;
+    let audio = info
+        .streams
+        .iter()
+        .any(|stream| stream.kind == StreamKind::Audio);
+
+    let args = detect_command(input, options, audio).args()?;
+    let result = runner.output("ffmpeg", &args)?;
+    let log = String::from_utf8_lossy(&result.stderr);
+    if !result.success {
+        return Err(format!("Detecting idle stretches failed: {}", log).into());
+    }
+
+    let still = detected_stretches(&log, "freeze_start", "freeze_end", duration);
+    let silent = detected_stretches(&log, "silence_start", "silence_end", duration);
+    let idle = idle_stretches(&still, audio.then_some(silent.as_slice()), options);
+
+    let report = CondenseReport {
+        stretches: idle.len(),
+        original: duration,
+        removed: removed_time(&idle, options.action),
+    };
+    if idle.is_empty() {
+        return Ok(report);
+    }
+
+    let command = condense_command(
+        input,
+        output,
+        &idle,
+        duration,
+        audio,
+        options.action,
+        watermark,
+    );
+    let result = runner.output("ffmpeg", &command.args()?)?;
+    if !result.success {
+        let _ = fs::remove_file(output);
+        let error = String::from_utf8_lossy(&result.stderr);
+        return Err(format!("Condensing failed: {}", error).into());
+    }
+    Ok(report)
+}
+
+pub fn condense_gui(
+    runner: &dyn CommandRunner,
+    input: &Path,
+    output: &Path,
+    options: &CondenseOptions,
+    log_sender: Sender<String>,
+) -> Result<CondenseReport, Box<dyn std::error::Error>> {
+    log_sender.send(format!(
+        "Looking for idle stretches in {}...",
+        input.display()
+    ))?;
+
+    match condense(runner, input, output, options, None) {
+        Ok(report) if report.stretches == 0 => {
+            log_sender.send("No idle stretches to condense.".to_string())?;
+            Ok(report)
+        }
+        Ok(report) => {
+            log_sender.send(format!("{}, saved to {}", report, output.display()))?;
+            Ok(report)
+        }
+        Err(e) => {
+            log_sender.send(e.to_string())?;
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::ScriptedRunner;
+
+    const LOG: &str = "\
+[freezedetect @ 0x55d1] lavfi.freezedetect.freeze_start: 2
+[freezedetect @ 0x55d1] lavfi.freezedetect.freeze_duration: 6
+[freezedetect @ 0x55d1] lavfi.freezedetect.freeze_end: 8
+[silencedetect @ 0x55d2] silence_start: 3.5
+[silencedetect @ 0x55d2] silence_end: 10 | silence_duration: 6.5
+[freezedetect @ 0x55d1] lavfi.freezedetect.freeze_start: 15
+";
+
+    #[test]
+    fn idle_stretches_are_still_and_silent() {
+        let still = detected_stretches(LOG, "freeze_start", "freeze_end", 20.0);
+        let silent = detected_stretches(LOG, "silence_start", "silence_end", 20.0);
+        assert_eq!(still, [(2.0, 8.0), (15.0, 20.0)]);
+        assert_eq!(silent, [(3.5, 10.0)]);
+
+        let options = CondenseOptions::default();
+        assert_eq!(
+            idle_stretches(&still, Some(&silent), &options),
+            [(3.75, 7.75)]
+        );
+        // Without audio every still stretch is idle
+        assert_eq!(
+            idle_stretches(&still, None, &options),
+            [(2.25, 7.75), (15.25, 19.75)]
+        );
+    }
+
+    #[test]
+    fn cut_joins_the_rest_and_speed_up_keeps_it_faster() {
+        let idle = [(2.0, 6.0)];
+        let args = condense_command(
+            Path::new("clip.mp4"),
+            Path::new("clip.condensed.mp4"),
+            &idle,
+            10.0,
+            true,
+            IdleAction::Cut,
+            None,
+        )
+        .args()
+        .unwrap();
+        assert_eq!(
+            args[4],
+            "[0:v]split=2[sv0][sv1];[0:a]asplit=2[sa0][sa1];\
+             [sv0]trim=start=0.000:end=2.000,setpts=PTS-STARTPTS[v0];\
+             [sa0]atrim=start=0.000:end=2.000,asetpts=PTS-STARTPTS[a0];\
+             [sv1]trim=start=6.000,setpts=PTS-STARTPTS[v1];\
+             [sa1]atrim=start=6.000,asetpts=PTS-STARTPTS[a1];\
+             [v0][a0][v1][a1]concat=n=2:v=1:a=1[v][a]"
+        );
+        assert_eq!(removed_time(&idle, IdleAction::Cut), 4.0);
+
+        let args = condense_command(
+            Path::new("clip.mp4"),
+            Path::new("clip.condensed.mp4"),
+            &idle,
+            10.0,
+            false,
+            IdleAction::SpeedUp(8.0),
+            None,
+        )
+        .args()
+        .unwrap();
+        assert!(args[4].contains("[sv1]trim=start=2.000:end=6.000,setpts=(PTS-STARTPTS)/8[v1]"));
+        assert!(args[4].ends_with("[v0][v1][v2]concat=n=3:v=1:a=0[v]"));
+        assert_eq!(removed_time(&idle, IdleAction::SpeedUp(8.0)), 3.5);
+    }
+
+    #[test]
+    fn nothing_is_written_without_idle_stretches() {
+        let info = r#"{"format": {"duration": "20.0", "format_name": "mp4"},
+            "streams": [{"index": 0, "codec_type": "video", "codec_name": "h264",
+                         "width": 640, "height": 480}]}"#;
+        let runner = ScriptedRunner::new().respond(true, info, "").respond(
+            true,
+            "",
+            "frame=  600 fps=300 q=-0.0 Lsize=N/A",
+        );
+
+        let report = condense(
+            &runner,
+            Path::new("clip.mp4"),
+            Path::new("clip.condensed.mp4"),
+            &CondenseOptions::default(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(report.stretches, 0);
+        assert_eq!(report.removed, 0.0);
+        let calls = runner.calls();
+        assert_eq!(calls.len(), 2);
+        assert!(calls[1].contains(&"[0:v]freezedetect=n=0.003:d=2[v]".to_string()));
+        assert!(!calls[1].iter().any(|arg| arg.contains("silencedetect")));
+    }
+}
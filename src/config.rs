@@ -0,0 +1,178 @@
+use crate::hotkeys::HotkeyBindings;
+use crate::naming::{default_output_dir, OverwritePolicy, DEFAULT_TEMPLATE};
+use crate::recorder::{CaptureSource, CursorMode, RecordSettings, VideoCodec};
+use crate::watermark::Watermark;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Settings persisted in `~/.config/rcrdr/config.toml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Profile used when none is requested explicitly
+    pub default_profile: Option<String>,
+    /// Where recordings go unless a profile says otherwise [default: XDG Videos]
+    pub output_dir: Option<PathBuf>,
+    /// File name for new recordings, see `naming::render_template` for the tokens
+    pub filename_template: String,
+    pub overwrite: OverwritePolicy,
+    /// Global hotkeys, active while the GUI is running
+    pub hotkeys: HotkeyBindings,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// A named bundle of recording settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub source: CaptureSource,
+    pub codec: VideoCodec,
+    pub fps: u32,
+    pub audio: bool,
+    pub cursor: CursorMode,
+    pub output_dir: Option<PathBuf>,
+    pub filename_template: Option<String>,
+    /// Drawn over recordings and over GIFs and videos exported with this profile
+    pub watermark: Option<Watermark>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        let settings = RecordSettings::default();
+
+        Self {
+            source: settings.source,
+            codec: settings.codec,
+            fps: settings.fps,
+            audio: settings.audio,
+            cursor: settings.cursor,
+            output_dir: None,
+            filename_template: None,
+            watermark: None,
+        }
+    }
+}
+
+impl Profile {
+    pub fn record_settings(&self) -> RecordSettings {
+        RecordSettings {
+            source: self.source.clone(),
+            codec: self.codec,
+            fps: self.fps,
+            audio: self.audio,
+            cursor: self.cursor,
+            webcam: None,
+            timelapse: None,
+            watermark: self.watermark.clone(),
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        // Starter profiles written out the first time the config is saved
+        let mut profiles = BTreeMap::new();
+        profiles.insert(
+            "demo".to_string(),
+            Profile {
+                fps: 30,
+                audio: true,
+                ..Profile::default()
+            },
+        );
+        profiles.insert(
+            "bugreport".to_string(),
+            Profile {
+                fps: 15,
+                audio: false,
+                ..Profile::default()
+            },
+        );
+        profiles.insert(
+            "hq".to_string(),
+            Profile {
+                codec: VideoCodec::H265,
+                fps: 60,
+                audio: true,
+                ..Profile::default()
+            },
+        );
+
+        Self {
+            default_profile: None,
+            output_dir: None,
+            filename_template: DEFAULT_TEMPLATE.to_string(),
+            overwrite: OverwritePolicy::default(),
+            hotkeys: HotkeyBindings::default(),
+            profiles,
+        }
+    }
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("rcrdr").join("config.toml"))
+    }
+
+    /// Load the config file, falling back to the defaults if it doesn't exist yet.
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        let path = match Self::path() {
+            Some(path) if path.exists() => path,
+            _ => return Ok(Self::default()),
+        };
+
+        let contents = fs::read_to_string(&path)?;
+        toml::from_str(&contents)
+            .map_err(|e| format!("Invalid config file {}: {}", path.display(), e).into())
+    }
+
+    pub fn save(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let path = Self::path().ok_or("Could not determine the config directory.")?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, toml::to_string_pretty(self)?)?;
+
+        Ok(path)
+    }
+
+    /// Look up the requested profile, or the default profile if none was requested.
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile, Box<dyn std::error::Error>> {
+        let name = match name.or(self.default_profile.as_deref()) {
+            Some(name) => name,
+            None => return Ok(Profile::default()),
+        };
+
+        match self.profiles.get(name) {
+            Some(profile) => Ok(profile.clone()),
+            None => {
+                let available: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+                Err(format!(
+                    "Unknown profile '{}'. Available profiles: {}",
+                    name,
+                    available.join(", ")
+                )
+                .into())
+            }
+        }
+    }
+
+    /// Output directory and filename template for a profile, falling back to
+    /// the global settings.
+    pub fn output_target(&self, profile: &Profile) -> (PathBuf, String) {
+        let dir = profile
+            .output_dir
+            .clone()
+            .or_else(|| self.output_dir.clone())
+            .unwrap_or_else(default_output_dir);
+        let template = profile
+            .filename_template
+            .clone()
+            .unwrap_or_else(|| self.filename_template.clone());
+
+        (dir, template)
+    }
+}
@@ -0,0 +1,208 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use crate::chapters::MarkRequest;
+
+/// A command for a running recording, sent over its control socket one per line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ControlCommand {
+    /// "mark" or "mark <label>"
+    Mark(String),
+    /// "stop"
+    Stop,
+}
+
+impl FromStr for ControlCommand {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let line = line.trim();
+        let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+        match command {
+            "mark" => Ok(ControlCommand::Mark(argument.trim().to_string())),
+            "stop" if argument.trim().is_empty() => Ok(ControlCommand::Stop),
+            _ => Err(format!(
+                "unknown command '{}', expected 'mark [label]' or 'stop'",
+                line
+            )),
+        }
+    }
+}
+
+/// A control socket being listened on. Stops listening and removes the socket
+/// file when dropped.
+pub struct ControlSocket {
+    path: PathBuf,
+    listening: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        self.listening.store(false, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Listen on a Unix socket at `path` for commands while recording: marks go to
+/// `marks` and "stop" clears `recording`. Each command is answered with "ok" or
+/// "error: <reason>".
+pub fn listen(
+    path: &Path,
+    marks: Sender<MarkRequest>,
+    recording: Arc<AtomicBool>,
+) -> Result<ControlSocket, Box<dyn std::error::Error>> {
+    if path.exists() {
+        return Err(format!(
+            "{} already exists. Remove it if no recording is using it.",
+            path.display()
+        )
+        .into());
+    }
+
+    #[cfg(unix)]
+    {
+        let listening = Arc::new(AtomicBool::new(true));
+        let thread = unix::serve(path, marks, recording, listening.clone())?;
+        Ok(ControlSocket {
+            path: path.to_path_buf(),
+            listening,
+            thread: Some(thread),
+        })
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = (marks, recording);
+        Err("Control sockets are only supported on Linux and macOS.".into())
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::ControlCommand;
+    use crate::chapters::MarkRequest;
+    use std::io::{self, BufRead, BufReader, Write};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::Path;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc::Sender;
+    use std::sync::Arc;
+    use std::thread::{self, JoinHandle};
+    use std::time::Duration;
+
+    pub fn serve(
+        path: &Path,
+        marks: Sender<MarkRequest>,
+        recording: Arc<AtomicBool>,
+        listening: Arc<AtomicBool>,
+    ) -> io::Result<JoinHandle<()>> {
+        let listener = UnixListener::bind(path)?;
+        // Polled, so the listener notices when it should stop
+        listener.set_nonblocking(true)?;
+
+        Ok(thread::spawn(move || {
+            while listening.load(Ordering::SeqCst) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let marks = marks.clone();
+                        let recording = recording.clone();
+                        thread::spawn(move || {
+                            let _ = handle(stream, &marks, &recording);
+                        });
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                        thread::sleep(Duration::from_millis(100));
+                    }
+                    Err(_) => break,
+                }
+            }
+        }))
+    }
+
+    fn handle(
+        stream: UnixStream,
+        marks: &Sender<MarkRequest>,
+        recording: &AtomicBool,
+    ) -> io::Result<()> {
+        stream.set_nonblocking(false)?;
+        let mut writer = stream.try_clone()?;
+
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let reply = match line.parse() {
+                Ok(ControlCommand::Mark(label)) => {
+                    let _ = marks.send(MarkRequest::now(&label));
+                    "ok".to_string()
+                }
+                Ok(ControlCommand::Stop) => {
+                    recording.store(false, Ordering::SeqCst);
+                    "ok".to_string()
+                }
+                Err(e) => format!("error: {}", e),
+            };
+            writeln!(writer, "{}", reply)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_parse_from_lines() {
+        assert_eq!(
+            "mark Setup done\n".parse(),
+            Ok(ControlCommand::Mark("Setup done".to_string()))
+        );
+        assert_eq!("mark".parse(), Ok(ControlCommand::Mark(String::new())));
+        assert_eq!(" stop ".parse(), Ok(ControlCommand::Stop));
+        assert!("stop now".parse::<ControlCommand>().is_err());
+        assert!("pause".parse::<ControlCommand>().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn socket_marks_and_stops_the_recording() {
+        use std::io::{BufRead, BufReader, Write};
+        use std::os::unix::net::UnixStream;
+        use std::sync::mpsc::channel;
+
+        let path = std::env::temp_dir().join(format!("rcrdr-control-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let recording = Arc::new(AtomicBool::new(true));
+        let (marks, requests) = channel();
+
+        let socket = listen(&path, marks, recording.clone()).unwrap();
+        assert!(listen(&path, channel().0, recording.clone()).is_err());
+
+        let mut stream = UnixStream::connect(&path).unwrap();
+        stream.write_all(b"mark Intro\nrewind\nstop\n").unwrap();
+        let replies: Vec<String> = BufReader::new(stream)
+            .lines()
+            .take(3)
+            .map(|line| line.unwrap())
+            .collect();
+
+        assert_eq!(replies[0], "ok");
+        assert!(replies[1].starts_with("error: unknown command 'rewind'"));
+        assert_eq!(replies[2], "ok");
+        assert_eq!(requests.try_recv().unwrap().label, "Intro");
+        assert!(!recording.load(Ordering::SeqCst));
+
+        drop(socket);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,277 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::mpsc::Sender;
+
+use crate::ffmpeg::{FfmpegCommand, FilterGraph, Input};
+use crate::media::probe;
+use crate::recorder::{export_audio_encoder, VideoCodec};
+use crate::runner::CommandRunner;
+use crate::screenshot::Geometry;
+use crate::watermark::{apply_watermark, Watermark};
+
+/// A target size for `--scale`: "1280" for a width, "1280x720", or "50%".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScaleSpec {
+    /// The height follows the aspect ratio
+    Width(u32),
+    Size(u32, u32),
+    Percent(u32),
+}
+
+impl fmt::Display for ScaleSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScaleSpec::Width(width) => write!(f, "{}", width),
+            ScaleSpec::Size(width, height) => write!(f, "{}x{}", width, height),
+            ScaleSpec::Percent(percent) => write!(f, "{}%", percent),
+        }
+    }
+}
+
+impl FromStr for ScaleSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || {
+            format!(
+                "Invalid scale '{}', expected a width, WIDTHxHEIGHT or a percentage such as 50%",
+                s
+            )
+        };
+        let number = |value: &str| match value.trim().parse::<u32>() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(error()),
+        };
+
+        let s = s.trim();
+        if let Some(percent) = s.strip_suffix('%') {
+            Ok(ScaleSpec::Percent(number(percent)?))
+        } else if let Some((width, height)) = s.split_once(['x', 'X']) {
+            Ok(ScaleSpec::Size(number(width)?, number(height)?))
+        } else {
+            Ok(ScaleSpec::Width(number(s)?))
+        }
+    }
+}
+
+impl ScaleSpec {
+    // Sizes are kept even, which yuv420p needs
+    fn filter(&self) -> String {
+        match self {
+            ScaleSpec::Width(width) => format!("scale={}:-2", even(*width)),
+            ScaleSpec::Size(width, height) => format!("scale={}:{}", even(*width), even(*height)),
+            ScaleSpec::Percent(percent) => {
+                format!("scale=trunc(iw*{}/200)*2:-2", percent)
+            }
+        }
+    }
+}
+
+fn even(n: u32) -> u32 {
+    (n & !1).max(2)
+}
+
+/// "clip.mp4" -> "clip.cropped.mp4", where the cropped copy goes by default.
+pub fn cropped_path(video: &Path) -> PathBuf {
+    match video.extension() {
+        Some(extension) => video.with_extension(format!("cropped.{}", extension.to_string_lossy())),
+        None => video.with_extension("cropped"),
+    }
+}
+
+/// Check that `rect` lies inside a `width` x `height` video.
+pub fn check_rect(rect: &Geometry, width: u32, height: u32) -> Result<(), String> {
+    let fits = |offset: u32, size: u32, limit: u32| {
+        offset.checked_add(size).is_some_and(|end| end <= limit)
+    };
+    if !fits(rect.x, rect.width, width) || !fits(rect.y, rect.height, height) {
+        return Err(format!(
+            "The crop rectangle {} does not fit the {}x{} video",
+            rect, width, height
+        ));
+    }
+    Ok(())
+}
+
+/// The ffmpeg command that crops `input` to `rect` and then scales it. Odd sizes
+/// are rounded down to even ones for the encoder.
+pub fn crop_command(
+    input: &Path,
+    output: &Path,
+    rect: Option<Geometry>,
+    scale: Option<ScaleSpec>,
+    watermark: Option<&Watermark>,
+) -> FfmpegCommand {
+    let mut filters = Vec::new();
+    if let Some(rect) = rect {
+        filters.push(format!(
+            "crop={}:{}:{}:{}",
+            even(rect.width),
+            even(rect.height),
+            rect.x,
+            rect.y
+        ));
+    }
+    if let Some(scale) = scale {
+        filters.push(scale.filter());
+    }
+
+    let command = FfmpegCommand::new()
+        .overwrite()
+        .input(Input::file(input))
+        .filter(FilterGraph::Video(filters))
+        .map("0:v")
+        .map("0:a?");
+
+    apply_watermark(command, watermark)
+        .video_encoder(VideoCodec::for_path(output).encoder())
+        .audio_encoder(export_audio_encoder(input, output))
+        .output(output)
+}
+
+/// Write `input` cropped to `rect` and scaled to `scale` to `output`.
+pub fn crop(
+    runner: &dyn CommandRunner,
+    input: &Path,
+    output: &Path,
+    rect: Option<Geometry>,
+    scale: Option<ScaleSpec>,
+    watermark: Option<&Watermark>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(rect) = &rect {
+        let info = probe(runner, input)?;
+        let video = info
+            .video_stream()
+            .ok_or_else(|| format!("{} has no video stream", input.display()))?;
+        check_rect(rect, video.width.unwrap_or(0), video.height.unwrap_or(0))?;
+    }
+
+    let args = crop_command(input, output, rect, scale, watermark).args()?;
+    let result = runner.output("ffmpeg", &args)?;
+    if !result.success {
+        let _ = fs::remove_file(output);
+        let error = String::from_utf8_lossy(&result.stderr);
+        return Err(format!("Cropping failed: {}", error).into());
+    }
+    Ok(())
+}
+
+pub fn crop_gui(
+    runner: &dyn CommandRunner,
+    input: &Path,
+    output: &Path,
+    rect: Option<Geometry>,
+    scale: Option<ScaleSpec>,
+    watermark: Option<&Watermark>,
+    log_sender: Sender<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log_sender.send(format!("Cropping {}...", input.display()))?;
+
+    if let Err(e) = crop(runner, input, output, rect, scale, watermark) {
+        log_sender.send(e.to_string())?;
+        return Err(e);
+    }
+
+    log_sender.send(format!("Cropped to {}", output.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::ScriptedRunner;
+
+    #[test]
+    fn scale_specs_parse() {
+        assert_eq!("1280".parse(), Ok(ScaleSpec::Width(1280)));
+        assert_eq!("1280x720".parse(), Ok(ScaleSpec::Size(1280, 720)));
+        assert_eq!(" 50% ".parse(), Ok(ScaleSpec::Percent(50)));
+
+        for invalid in ["", "0", "x720", "50 %%", "-5", "half"] {
+            assert!(invalid.parse::<ScaleSpec>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn crop_then_scale_to_even_sizes() {
+        let rect: Geometry = "1921x1079+0+1".parse().unwrap();
+        let args = crop_command(
+            Path::new("clip.mp4"),
+            Path::new("clip.cropped.mp4"),
+            Some(rect),
+            Some(ScaleSpec::Percent(50)),
+            None,
+        )
+        .args()
+        .unwrap();
+
+        assert_eq!(
+            args[..6],
+            [
+                "-y",
+                "-i",
+                "clip.mp4",
+                "-vf",
+                "crop=1920:1078:0:1,scale=trunc(iw*50/200)*2:-2",
+                "-map"
+            ]
+        );
+        assert!(args.windows(2).any(|pair| pair == ["-c:a", "copy"]));
+
+        // WebM can't hold the AAC of an MP4
+        let webm = crop_command(
+            Path::new("clip.mp4"),
+            Path::new("clip.webm"),
+            Some(rect),
+            None,
+            None,
+        )
+        .args()
+        .unwrap();
+        assert!(webm.windows(2).any(|pair| pair == ["-c:v", "libvpx-vp9"]));
+        assert!(webm.windows(2).any(|pair| pair == ["-c:a", "libopus"]));
+    }
+
+    #[test]
+    fn rectangle_must_fit_the_video() {
+        let info = r#"{"format": {"duration": "4.0", "format_name": "mp4"},
+            "streams": [{"index": 0, "codec_type": "video", "codec_name": "h264",
+                         "width": 1920, "height": 1080}]}"#;
+        let runner = ScriptedRunner::new().respond(true, info, "");
+
+        let error = crop(
+            &runner,
+            Path::new("clip.mp4"),
+            Path::new("out.mp4"),
+            Some("1920x100+1+0".parse().unwrap()),
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("does not fit the 1920x1080 video"));
+        assert_eq!(runner.calls().len(), 1);
+    }
+
+    #[test]
+    fn rectangles_past_the_integer_range_do_not_fit() {
+        let rect = Geometry {
+            width: 2,
+            height: 2,
+            x: u32::MAX,
+            y: 0,
+        };
+        assert!(check_rect(&rect, 1920, 1080)
+            .unwrap_err()
+            .contains("does not fit"));
+
+        let rect = Geometry {
+            y: u32::MAX,
+            ..rect
+        };
+        assert!(check_rect(&Geometry { x: 0, ..rect }, 1920, 1080).is_err());
+    }
+}
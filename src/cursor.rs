@@ -0,0 +1,199 @@
+use std::fs;
+use std::path::Path;
+use std::sync::mpsc::Sender;
+
+use crate::events::InputEvent;
+use crate::ffmpeg::{escape_filter_value, Encoder, FfmpegCommand, FilterGraph, Input};
+use crate::recorder::VideoCodec;
+use crate::runner::CommandRunner;
+
+// Diameter of the halo around the pointer, in pixels
+const HALO_SIZE: i32 = 56;
+
+// A translucent yellow disc, as a lavfi source
+fn halo_source() -> String {
+    format!(
+        "color=c=black:s={size}x{size},format=rgba,geq=r=255:g=220:b=0:a='110*lte(hypot(X-{radius},Y-{radius}),{radius})'",
+        size = HALO_SIZE,
+        radius = HALO_SIZE / 2
+    )
+}
+
+/// A sendcmd script that moves the halo to every recorded pointer position.
+pub fn halo_script(events: &[InputEvent]) -> String {
+    let mut script = String::new();
+    for event in events {
+        let (t, x, y) = match event {
+            InputEvent::Move { t, x, y } | InputEvent::Click { t, x, y, .. } => (t, x, y),
+            InputEvent::Key { .. } => continue,
+        };
+        script.push_str(&format!(
+            "{:.3} overlay@halo x {}, overlay@halo y {};\n",
+            t,
+            x - HALO_SIZE / 2,
+            y - HALO_SIZE / 2
+        ));
+    }
+    script
+}
+
+/// The ffmpeg command that draws the halo over `input`, following the positions
+/// in the sendcmd `script`.
+pub fn highlight_command(
+    input: &Path,
+    output: &Path,
+    script: &Path,
+    codec: VideoCodec,
+) -> FfmpegCommand {
+    FfmpegCommand::new()
+        .overwrite()
+        .input(Input::file(input))
+        .input(Input::lavfi(&halo_source()))
+        .filter(FilterGraph::Complex(vec![
+            format!(
+                "[0:v]sendcmd=f={}[captured]",
+                escape_filter_value(&script.to_string_lossy())
+            ),
+            // Off screen until the first position arrives
+            format!(
+                "[captured][1:v]overlay@halo=x=-{size}:y=-{size}:eval=frame:shortest=1[highlighted]",
+                size = HALO_SIZE
+            ),
+        ]))
+        .map("[highlighted]")
+        .map("0:a?")
+        .video_encoder(codec.encoder())
+        .audio_encoder(Encoder::new("copy"))
+        .output(output)
+}
+
+/// Draw a halo around the pointer into `video`, replacing it, using the pointer
+/// positions logged while it was recorded.
+pub fn highlight_cursor(
+    runner: &dyn CommandRunner,
+    video: &Path,
+    codec: VideoCodec,
+    events: &[InputEvent],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let script = halo_script(events);
+    if script.is_empty() {
+        return Ok(());
+    }
+
+    let script_path = video.with_extension("cursor.txt");
+    let highlighted = video.with_extension(format!(
+        "highlighted.{}",
+        video.extension().unwrap_or_default().to_string_lossy()
+    ));
+    fs::write(&script_path, script)?;
+
+    let args = highlight_command(video, &highlighted, &script_path, codec).args()?;
+    let result = runner.output("ffmpeg", &args);
+    let _ = fs::remove_file(&script_path);
+
+    let result = result?;
+    if !result.success {
+        let _ = fs::remove_file(&highlighted);
+        let error = String::from_utf8_lossy(&result.stderr);
+        return Err(format!("Highlighting the cursor failed: {}", error).into());
+    }
+
+    fs::rename(&highlighted, video)?;
+    Ok(())
+}
+
+pub fn highlight_cursor_gui(
+    runner: &dyn CommandRunner,
+    video: &Path,
+    codec: VideoCodec,
+    events: &[InputEvent],
+    log_sender: Sender<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log_sender.send("Highlighting the cursor...".to_string())?;
+
+    if let Err(e) = highlight_cursor(runner, video, codec, events) {
+        log_sender.send(e.to_string())?;
+        return Err(e);
+    }
+
+    log_sender.send("Cursor highlighted.".to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::ScriptedRunner;
+
+    #[test]
+    fn halo_follows_moves_and_clicks() {
+        let events = [
+            InputEvent::Move {
+                t: 0.5,
+                x: 100,
+                y: 200,
+            },
+            InputEvent::Key {
+                t: 0.75,
+                key: "a".to_string(),
+            },
+            InputEvent::Click {
+                t: 1.0,
+                x: 28,
+                y: 28,
+                button: 1,
+            },
+        ];
+
+        assert_eq!(
+            halo_script(&events),
+            "0.500 overlay@halo x 72, overlay@halo y 172;\n1.000 overlay@halo x 0, overlay@halo y 0;\n"
+        );
+    }
+
+    #[test]
+    fn highlight_overlays_the_halo_and_keeps_the_audio() {
+        let args = highlight_command(
+            Path::new("clip.mp4"),
+            Path::new("clip.highlighted.mp4"),
+            Path::new("/tmp/clip: 1.cursor.txt"),
+            VideoCodec::H264,
+        )
+        .args()
+        .unwrap();
+
+        let graph = &args[args
+            .iter()
+            .position(|arg| arg == "-filter_complex")
+            .unwrap()
+            + 1];
+        assert_eq!(
+            graph,
+            r"[0:v]sendcmd=f=/tmp/clip\\: 1.cursor.txt[captured];[captured][1:v]overlay@halo=x=-56:y=-56:eval=frame:shortest=1[highlighted]"
+        );
+        assert!(args.windows(2).any(|pair| pair == ["-map", "0:a?"]));
+        assert!(args.windows(2).any(|pair| pair == ["-c:a", "copy"]));
+    }
+
+    #[test]
+    fn failed_highlight_leaves_the_recording_alone() {
+        let dir = std::env::temp_dir().join(format!("rcrdr-cursor-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let video = dir.join("clip.mp4");
+        fs::write(&video, "recording").unwrap();
+
+        let runner = ScriptedRunner::new().respond(false, "", "No such filter: 'geq'");
+        let events = [InputEvent::Move { t: 0.0, x: 1, y: 1 }];
+        let error = highlight_cursor(&runner, &video, VideoCodec::H264, &events).unwrap_err();
+
+        assert!(error.to_string().contains("No such filter"));
+        assert_eq!(fs::read_to_string(&video).unwrap(), "recording");
+        assert!(!dir.join("clip.cursor.txt").exists());
+
+        // Nothing to draw without pointer positions
+        assert!(highlight_cursor(&runner, &video, VideoCodec::H264, &[]).is_ok());
+        assert_eq!(runner.calls().len(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
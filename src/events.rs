@@ -0,0 +1,857 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::ffmpeg::{escape_filter_value, Encoder, FfmpegCommand, FilterGraph, Input};
+use crate::recorder::{timelapse_length, VideoCodec};
+use crate::runner::CommandRunner;
+use crate::watermark::{apply_watermark, Watermark};
+
+/// A mouse click, key press or pointer position, `t` seconds into the recording.
+/// Positions are screen coordinates.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum InputEvent {
+    Click {
+        t: f64,
+        x: i32,
+        y: i32,
+        button: u32,
+    },
+    Key {
+        t: f64,
+        key: String,
+    },
+    /// Only logged while tracking the pointer, for the cursor highlight
+    Move {
+        t: f64,
+        x: i32,
+        y: i32,
+    },
+}
+
+impl InputEvent {
+    fn t_mut(&mut self) -> &mut f64 {
+        match self {
+            InputEvent::Click { t, .. }
+            | InputEvent::Key { t, .. }
+            | InputEvent::Move { t, .. } => t,
+        }
+    }
+}
+
+/// Events logged on the wall clock, moved to where they play in a timelapse that
+/// captured a frame every `interval` seconds.
+pub fn timelapse_events(events: Vec<InputEvent>, interval: f64) -> Vec<InputEvent> {
+    events
+        .into_iter()
+        .map(|mut event| {
+            let t = event.t_mut();
+            *t = timelapse_length(Duration::from_secs_f64(t.max(0.0)), interval).as_secs_f64();
+            event
+        })
+        .collect()
+}
+
+/// Whether there are clicks or key presses to draw, not just pointer positions.
+pub fn has_annotations(events: &[InputEvent]) -> bool {
+    events
+        .iter()
+        .any(|event| !matches!(event, InputEvent::Move { .. }))
+}
+
+/// "clip.mp4" -> "clip.mp4.events.jsonl", the event log next to a recording.
+pub fn event_log_path(video: &Path) -> PathBuf {
+    let mut name = video.as_os_str().to_os_string();
+    name.push(".events.jsonl");
+    PathBuf::from(name)
+}
+
+/// "clip.mp4" -> "clip.annotated.mp4", where the annotated copy goes by default.
+pub fn annotated_path(video: &Path) -> PathBuf {
+    match video.extension() {
+        Some(extension) => {
+            video.with_extension(format!("annotated.{}", extension.to_string_lossy()))
+        }
+        None => video.with_extension("annotated"),
+    }
+}
+
+/// Write the events as JSON Lines, one event per line.
+pub fn write_event_log(
+    path: &Path,
+    events: &[InputEvent],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = fs::File::create(path)?;
+    for event in events {
+        writeln!(file, "{}", serde_json::to_string(event)?)?;
+    }
+    Ok(())
+}
+
+pub fn read_event_log(path: &Path) -> Result<Vec<InputEvent>, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read event log {}: {}", path.display(), e))?;
+
+    let mut events = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event = serde_json::from_str(line)
+            .map_err(|e| format!("{}:{}: invalid event: {}", path.display(), number + 1, e))?;
+        events.push(event);
+    }
+    Ok(events)
+}
+
+/// Modifier keys held while another key was pressed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub super_key: bool,
+}
+
+impl Modifiers {
+    // Track a modifier key by its X keysym name. Returns false for other keys.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn update(&mut self, keysym: &str, down: bool) -> bool {
+        match keysym {
+            "Control_L" | "Control_R" => self.ctrl = down,
+            "Alt_L" | "Alt_R" | "Meta_L" | "Meta_R" => self.alt = down,
+            "Shift_L" | "Shift_R" => self.shift = down,
+            "Super_L" | "Super_R" => self.super_key = down,
+            // AltGr only picks other characters, it's not part of a shortcut
+            "ISO_Level3_Shift" => {}
+            _ => return false,
+        }
+        true
+    }
+}
+
+// X keysym names shown under a friendlier label
+const KEY_LABELS: [(&str, &str); 19] = [
+    ("Return", "Enter"),
+    ("KP_Enter", "Enter"),
+    ("space", "Space"),
+    ("BackSpace", "Backspace"),
+    ("Escape", "Esc"),
+    ("Prior", "PageUp"),
+    ("Next", "PageDown"),
+    ("ISO_Left_Tab", "Tab"),
+    ("Caps_Lock", "CapsLock"),
+    ("minus", "-"),
+    ("equal", "="),
+    ("comma", ","),
+    ("period", "."),
+    ("slash", "/"),
+    ("semicolon", ";"),
+    ("apostrophe", "'"),
+    ("bracketleft", "["),
+    ("bracketright", "]"),
+    ("backslash", "\\"),
+];
+
+/// The caption for a key press: "a", "A" with Shift, or "Ctrl+Shift+T" for
+/// shortcuts. `keysym` is the unshifted X keysym name.
+pub fn key_label(keysym: &str, modifiers: Modifiers) -> String {
+    let mut key = KEY_LABELS
+        .iter()
+        .find(|(name, _)| *name == keysym)
+        .map(|(_, label)| label.to_string())
+        .unwrap_or_else(|| keysym.to_string());
+
+    let shortcut = modifiers.ctrl || modifiers.alt || modifiers.super_key;
+    if key.chars().count() == 1 {
+        if shortcut || modifiers.shift {
+            key = key.to_uppercase();
+        }
+        // Typed letters read better without "Shift+"
+        if !shortcut && key.chars().all(char::is_alphabetic) {
+            return key;
+        }
+    }
+
+    let held = [
+        (modifiers.ctrl, "Ctrl"),
+        (modifiers.alt, "Alt"),
+        (modifiers.shift, "Shift"),
+        (modifiers.super_key, "Super"),
+    ];
+    let mut label = String::new();
+    for (_, name) in held.iter().filter(|(down, _)| *down) {
+        label.push_str(name);
+        label.push('+');
+    }
+    label.push_str(&key);
+    label
+}
+
+/// Text shown in the key strip from `start` until `end`, in seconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Caption {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
+
+// Keys typed closer together than this share a caption
+const CAPTION_GAP: f64 = 1.0;
+// How long a caption stays up after its last key
+const CAPTION_HOLD: f64 = 1.5;
+const CAPTION_MAX_CHARS: usize = 40;
+
+/// Group key presses into captions that grow as keys are typed: "h", "he", "hel"...
+pub fn key_captions(events: &[InputEvent]) -> Vec<Caption> {
+    let keys: Vec<(f64, &str)> = events
+        .iter()
+        .filter_map(|event| match event {
+            InputEvent::Key { t, key } => Some((*t, key.as_str())),
+            _ => None,
+        })
+        .collect();
+
+    let mut captions = Vec::new();
+    let mut text = String::new();
+    let mut last_plain = false;
+
+    for (i, (time, key)) in keys.iter().enumerate() {
+        let starts_new = i == 0
+            || time - keys[i - 1].0 > CAPTION_GAP
+            || text.chars().count() + key.chars().count() >= CAPTION_MAX_CHARS;
+
+        // Typed characters run together, named keys and shortcuts are separate words
+        let token = match *key {
+            "Space" if !starts_new => " ",
+            key => key,
+        };
+        let plain = token.chars().count() == 1;
+
+        let runs_on = (plain && last_plain) || text.ends_with(' ');
+        if starts_new {
+            text.clear();
+        } else if !runs_on {
+            text.push(' ');
+        }
+        text.push_str(token);
+        last_plain = plain;
+
+        // Shown until the next key changes it, or held for a moment after the last one
+        let end = match keys.get(i + 1) {
+            Some((next, _)) => next.min(time + CAPTION_HOLD),
+            None => time + CAPTION_HOLD,
+        };
+        captions.push(Caption {
+            start: *time,
+            end,
+            text: text.clone(),
+        });
+    }
+
+    captions
+}
+
+// A click ripple is drawn as growing squares: (offset in seconds, half size in pixels)
+const RIPPLE_STEPS: [(f64, i32); 3] = [(0.0, 12), (0.12, 22), (0.24, 32)];
+const RIPPLE_STEP_SECS: f64 = 0.12;
+
+fn between(start: f64, end: f64) -> String {
+    format!("enable='between(t,{:.3},{:.3})'", start, end)
+}
+
+/// The filters that draw click ripples and the key caption strip.
+pub fn annotation_filters(events: &[InputEvent]) -> Vec<String> {
+    let mut filters = Vec::new();
+
+    for event in events {
+        if let InputEvent::Click { t, x, y, button } = event {
+            // Left clicks in yellow, any other button in cyan
+            let color = if *button == 1 { "yellow" } else { "cyan" };
+            for (offset, radius) in RIPPLE_STEPS {
+                let start = t + offset;
+                filters.push(format!(
+                    "drawbox=x={}:y={}:w={}:h={}:color={}@0.8:t=3:{}",
+                    x - radius,
+                    y - radius,
+                    radius * 2,
+                    radius * 2,
+                    color,
+                    between(start, start + RIPPLE_STEP_SECS)
+                ));
+            }
+        }
+    }
+
+    for caption in key_captions(events) {
+        filters.push(format!(
+            "drawtext=text={}:expansion=none:fontsize=32:fontcolor=white:box=1:boxcolor=black@0.6:boxborderw=12:x=(w-text_w)/2:y=h-text_h-48:{}",
+            escape_filter_value(&caption.text),
+            between(caption.start, caption.end)
+        ));
+    }
+
+    filters
+}
+
+/// The ffmpeg command that burns the events into a copy of `input`.
+pub fn annotate_command(
+    input: &Path,
+    output: &Path,
+    events: &[InputEvent],
+    watermark: Option<&Watermark>,
+) -> Result<FfmpegCommand, Box<dyn std::error::Error>> {
+    if !has_annotations(events) {
+        return Err("The event log has no clicks or key presses to draw.".into());
+    }
+
+    let command = FfmpegCommand::new()
+        .overwrite()
+        .input(Input::file(input))
+        .filter(FilterGraph::Video(annotation_filters(events)));
+
+    Ok(apply_watermark(command, watermark)
+        .video_encoder(VideoCodec::for_path(output).encoder())
+        .audio_encoder(Encoder::new("copy"))
+        .output(output))
+}
+
+pub fn annotate(
+    runner: &dyn CommandRunner,
+    input: &Path,
+    output: &Path,
+    events: &[InputEvent],
+    watermark: Option<&Watermark>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let args = annotate_command(input, output, events, watermark)?.args()?;
+    let result = runner.output("ffmpeg", &args)?;
+    if !result.success {
+        let error = String::from_utf8_lossy(&result.stderr);
+        return Err(format!("Annotating failed: {}", error).into());
+    }
+    Ok(())
+}
+
+/// Write the event log next to `output` and, if asked, burn the events into an
+/// annotated copy. Progress goes to `log_sender`.
+pub fn save_events_gui(
+    runner: &dyn CommandRunner,
+    events: &[InputEvent],
+    output: &Path,
+    annotate_copy: bool,
+    log_sender: Sender<String>,
+) -> Result<Option<PathBuf>, Box<dyn std::error::Error>> {
+    let log = event_log_path(output);
+    write_event_log(&log, events)?;
+    log_sender.send(format!(
+        "Logged {} events to {}",
+        events.len(),
+        log.display()
+    ))?;
+
+    if annotate_copy && has_annotations(events) {
+        let annotated = annotated_path(output);
+        log_sender.send(format!("Annotating to {}...", annotated.display()))?;
+        if let Err(e) = annotate(runner, output, &annotated, events, None) {
+            log_sender.send(e.to_string())?;
+            return Err(e);
+        }
+        log_sender.send(format!("Saved annotated copy to {}", annotated.display()))?;
+        return Ok(Some(annotated));
+    }
+
+    Ok(None)
+}
+
+/// Clicks and key presses being logged. Stops when dropped.
+pub struct EventCapture {
+    running: Arc<AtomicBool>,
+    started: Instant,
+    thread: Option<JoinHandle<Vec<InputEvent>>>,
+}
+
+impl EventCapture {
+    /// Stop logging and return the events in the order they happened, timed from
+    /// `capture_start`, when the recording's first frame was captured.
+    pub fn finish(mut self, capture_start: Instant) -> Vec<InputEvent> {
+        self.running.store(false, Ordering::SeqCst);
+        let events = match self.thread.take() {
+            Some(thread) => thread.join().unwrap_or_default(),
+            None => Vec::new(),
+        };
+        let offset = capture_start.saturating_duration_since(self.started);
+        since_capture_start(events, offset.as_secs_f64())
+    }
+}
+
+impl Drop for EventCapture {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// Events counted from `offset` seconds into the log, dropping those before it
+fn since_capture_start(events: Vec<InputEvent>, offset: f64) -> Vec<InputEvent> {
+    events
+        .into_iter()
+        .filter_map(|mut event| {
+            let t = event.t_mut();
+            *t -= offset;
+            (*t >= 0.0).then_some(event)
+        })
+        .collect()
+}
+
+/// Start logging mouse clicks and key presses anywhere on the desktop, and pointer
+/// movement with `track_pointer`. Time is counted from the capture start given to
+/// `finish` and stands still while `paused` is set, to match a recording made of
+/// segments.
+pub fn capture(
+    paused: Arc<AtomicBool>,
+    track_pointer: bool,
+) -> Result<EventCapture, Box<dyn std::error::Error>> {
+    let running = Arc::new(AtomicBool::new(true));
+    let running_clone = running.clone();
+
+    // The capture reports whether it could listen before the clock starts
+    let (ready_tx, ready_rx) = channel();
+    let thread = thread::spawn(move || {
+        #[cfg(target_os = "linux")]
+        let events = x11::run(&running_clone, &paused, track_pointer, &ready_tx);
+
+        #[cfg(not(target_os = "linux"))]
+        let events = {
+            let _ = (running_clone, paused, track_pointer);
+            let _ = ready_tx.send(Err(
+                "Click and key logging is only supported on Linux with X11.".to_string(),
+            ));
+            Vec::new()
+        };
+
+        events
+    });
+
+    match ready_rx.recv() {
+        Ok(Ok(started)) => Ok(EventCapture {
+            running,
+            started,
+            thread: Some(thread),
+        }),
+        Ok(Err(e)) => {
+            let _ = thread.join();
+            Err(e.into())
+        }
+        Err(_) => Err("The event logger stopped unexpectedly.".into()),
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod x11 {
+    use super::{key_label, InputEvent, Modifiers};
+    use std::ffi::{CStr, CString};
+    use std::os::raw::{c_int, c_uint};
+    use std::ptr;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc::Sender;
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use x11_dl::xinput2::{self, XInput2};
+    use x11_dl::xlib;
+
+    // Buttons 4 to 7 are the scroll wheel
+    const SCROLL_BUTTONS: std::ops::RangeInclusive<c_int> = 4..=7;
+    // How often the pointer position is sampled while tracking it
+    const POINTER_INTERVAL: Duration = Duration::from_millis(40);
+
+    pub fn run(
+        running: &AtomicBool,
+        paused: &AtomicBool,
+        track_pointer: bool,
+        ready: &Sender<Result<Instant, String>>,
+    ) -> Vec<InputEvent> {
+        let libraries = xlib::Xlib::open()
+            .map_err(|e| format!("Could not load libX11: {}", e))
+            .and_then(|xlib| {
+                XInput2::open()
+                    .map(|xinput| (xlib, xinput))
+                    .map_err(|e| format!("Could not load libXi: {}", e))
+            });
+        let (xlib, xinput) = match libraries {
+            Ok(libraries) => libraries,
+            Err(e) => {
+                let _ = ready.send(Err(e));
+                return Vec::new();
+            }
+        };
+
+        let mut events = Vec::new();
+
+        unsafe {
+            let display = (xlib.XOpenDisplay)(ptr::null());
+            if display.is_null() {
+                let _ = ready.send(Err(
+                    "Could not open the X display. Click and key logging needs an X11 session."
+                        .to_string(),
+                ));
+                return events;
+            }
+
+            let opcode = match select_raw_events(&xlib, &xinput, display) {
+                Ok(opcode) => opcode,
+                Err(e) => {
+                    let _ = ready.send(Err(e));
+                    (xlib.XCloseDisplay)(display);
+                    return events;
+                }
+            };
+            let started = Instant::now();
+            let _ = ready.send(Ok(started));
+
+            let root = (xlib.XDefaultRootWindow)(display);
+            let mut paused_since: Option<Instant> = None;
+            let mut paused_total = Duration::ZERO;
+            let mut modifiers = Modifiers::default();
+            let mut pointer = None;
+            let mut pointer_sampled = started;
+            let mut event: xlib::XEvent = std::mem::zeroed();
+
+            while running.load(Ordering::SeqCst) {
+                match (paused.load(Ordering::SeqCst), paused_since) {
+                    (true, None) => paused_since = Some(Instant::now()),
+                    (false, Some(since)) => {
+                        paused_total += since.elapsed();
+                        paused_since = None;
+                    }
+                    _ => {}
+                }
+                let t = (started.elapsed() - paused_total).as_secs_f64();
+
+                // Raw motion events would flood the log, so only sample the position
+                if track_pointer
+                    && paused_since.is_none()
+                    && pointer_sampled.elapsed() >= POINTER_INTERVAL
+                {
+                    pointer_sampled = Instant::now();
+                    let (x, y) = pointer_position(&xlib, display, root);
+                    if pointer != Some((x, y)) {
+                        pointer = Some((x, y));
+                        events.push(InputEvent::Move { t, x, y });
+                    }
+                }
+
+                while (xlib.XPending)(display) > 0 {
+                    (xlib.XNextEvent)(display, &mut event);
+                    let mut cookie = event.generic_event_cookie;
+                    if event.get_type() != xlib::GenericEvent
+                        || cookie.extension != opcode
+                        || (xlib.XGetEventData)(display, &mut cookie) == 0
+                    {
+                        continue;
+                    }
+
+                    let raw = &*(cookie.data as *const xinput2::XIRawEvent);
+                    match cookie.evtype {
+                        xinput2::XI_RawButtonPress if !SCROLL_BUTTONS.contains(&raw.detail) => {
+                            let (x, y) = pointer_position(&xlib, display, root);
+                            if paused_since.is_none() {
+                                events.push(InputEvent::Click {
+                                    t,
+                                    x,
+                                    y,
+                                    button: raw.detail as u32,
+                                });
+                            }
+                        }
+                        xinput2::XI_RawKeyPress | xinput2::XI_RawKeyRelease => {
+                            let down = cookie.evtype == xinput2::XI_RawKeyPress;
+                            if let Some(keysym) = keysym_name(&xlib, display, raw.detail) {
+                                let modifier = modifiers.update(&keysym, down);
+                                if down && !modifier && paused_since.is_none() {
+                                    events.push(InputEvent::Key {
+                                        t,
+                                        key: key_label(&keysym, modifiers),
+                                    });
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                    (xlib.XFreeEventData)(display, &mut cookie);
+                }
+
+                // Short sleeps keep the timestamps within a frame or so
+                thread::sleep(Duration::from_millis(10));
+            }
+
+            (xlib.XCloseDisplay)(display);
+        }
+
+        events
+    }
+
+    // Ask for raw key and button events from every device. Returns the XInput opcode.
+    unsafe fn select_raw_events(
+        xlib: &xlib::Xlib,
+        xinput: &XInput2,
+        display: *mut xlib::Display,
+    ) -> Result<c_int, String> {
+        let name = CString::new("XInputExtension").expect("no NUL in extension name");
+        let (mut opcode, mut first_event, mut first_error) = (0, 0, 0);
+        if (xlib.XQueryExtension)(
+            display,
+            name.as_ptr(),
+            &mut opcode,
+            &mut first_event,
+            &mut first_error,
+        ) == 0
+        {
+            return Err("The X server has no XInput extension.".to_string());
+        }
+
+        let (mut major, mut minor) = (2, 0);
+        if (xinput.XIQueryVersion)(display, &mut major, &mut minor) != xlib::Success as c_int {
+            return Err("The X server doesn't support XInput 2.".to_string());
+        }
+
+        let mut mask = [0; 3];
+        for event in [
+            xinput2::XI_RawKeyPress,
+            xinput2::XI_RawKeyRelease,
+            xinput2::XI_RawButtonPress,
+        ] {
+            xinput2::XISetMask(&mut mask, event);
+        }
+        let mut event_mask = xinput2::XIEventMask {
+            deviceid: xinput2::XIAllMasterDevices,
+            mask_len: mask.len() as c_int,
+            mask: mask.as_mut_ptr(),
+        };
+
+        let root = (xlib.XDefaultRootWindow)(display);
+        (xinput.XISelectEvents)(display, root, &mut event_mask, 1);
+        (xlib.XSync)(display, xlib::False);
+
+        Ok(opcode)
+    }
+
+    // Raw events carry no position, so ask where the pointer is now
+    unsafe fn pointer_position(
+        xlib: &xlib::Xlib,
+        display: *mut xlib::Display,
+        root: xlib::Window,
+    ) -> (i32, i32) {
+        let (mut root_return, mut child_return) = (0, 0);
+        let (mut x, mut y, mut window_x, mut window_y) = (0, 0, 0, 0);
+        let mut mask: c_uint = 0;
+        (xlib.XQueryPointer)(
+            display,
+            root,
+            &mut root_return,
+            &mut child_return,
+            &mut x,
+            &mut y,
+            &mut window_x,
+            &mut window_y,
+            &mut mask,
+        );
+        (x, y)
+    }
+
+    // The unshifted keysym name of a keycode, e.g. "a" or "Return"
+    unsafe fn keysym_name(
+        xlib: &xlib::Xlib,
+        display: *mut xlib::Display,
+        keycode: c_int,
+    ) -> Option<String> {
+        let keysym = (xlib.XkbKeycodeToKeysym)(display, keycode as u8, 0, 0);
+        if keysym == 0 {
+            return None;
+        }
+        let name = (xlib.XKeysymToString)(keysym);
+        if name.is_null() {
+            return None;
+        }
+        Some(CStr::from_ptr(name).to_string_lossy().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(t: f64, key: &str) -> InputEvent {
+        InputEvent::Key {
+            t,
+            key: key.to_string(),
+        }
+    }
+
+    #[test]
+    fn event_log_round_trips_as_json_lines() {
+        let path = std::env::temp_dir().join(format!("rcrdr-events-{}.jsonl", std::process::id()));
+        let events = vec![
+            InputEvent::Click {
+                t: 0.5,
+                x: 10,
+                y: 20,
+                button: 1,
+            },
+            key(1.25, "Ctrl+C"),
+        ];
+
+        write_event_log(&path, &events).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            contents.lines().next().unwrap(),
+            r#"{"type":"click","t":0.5,"x":10,"y":20,"button":1}"#
+        );
+        assert_eq!(read_event_log(&path).unwrap(), events);
+
+        fs::write(&path, "{\"type\":\"click\"}\n").unwrap();
+        let error = read_event_log(&path).unwrap_err().to_string();
+        assert!(error.contains(":1: invalid event"), "{}", error);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn log_and_annotated_paths_sit_next_to_the_video() {
+        assert_eq!(
+            event_log_path(Path::new("/tmp/clip.mp4")),
+            Path::new("/tmp/clip.mp4.events.jsonl")
+        );
+        // Videos that only differ in their extension keep their own logs
+        assert_ne!(
+            event_log_path(Path::new("/tmp/clip.mp4")),
+            event_log_path(Path::new("/tmp/clip.mkv"))
+        );
+        assert_eq!(
+            annotated_path(Path::new("/tmp/clip.webm")),
+            Path::new("/tmp/clip.annotated.webm")
+        );
+    }
+
+    #[test]
+    fn keys_are_labelled_like_shortcuts() {
+        let none = Modifiers::default();
+        let shift = Modifiers {
+            shift: true,
+            ..none
+        };
+        let ctrl_shift = Modifiers {
+            ctrl: true,
+            ..shift
+        };
+
+        assert_eq!(key_label("a", none), "a");
+        assert_eq!(key_label("a", shift), "A");
+        assert_eq!(key_label("t", ctrl_shift), "Ctrl+Shift+T");
+        assert_eq!(key_label("Return", none), "Enter");
+        assert_eq!(key_label("1", shift), "Shift+1");
+        assert_eq!(key_label("comma", none), ",");
+    }
+
+    #[test]
+    fn typed_keys_grow_one_caption_until_a_pause() {
+        let events = [
+            key(1.0, "h"),
+            key(1.2, "i"),
+            key(1.4, "Space"),
+            key(1.6, "Enter"),
+            key(5.0, "Ctrl+S"),
+        ];
+
+        let captions = key_captions(&events);
+        let texts: Vec<&str> = captions.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, ["h", "hi", "hi ", "hi Enter", "Ctrl+S"]);
+        assert_eq!(captions[1].end, 1.4);
+        assert_eq!(captions[3].end, 1.6 + CAPTION_HOLD);
+        assert_eq!(captions[4].end, 5.0 + CAPTION_HOLD);
+    }
+
+    #[test]
+    fn annotate_command_draws_ripples_and_captions() {
+        let events = [
+            InputEvent::Click {
+                t: 2.0,
+                x: 100,
+                y: 50,
+                button: 1,
+            },
+            key(3.0, "Ctrl+:"),
+        ];
+
+        let args = annotate_command(Path::new("in.mp4"), Path::new("out.mp4"), &events, None)
+            .unwrap()
+            .args()
+            .unwrap();
+        let filters = &args[args.iter().position(|arg| arg == "-vf").unwrap() + 1];
+
+        assert!(filters.starts_with(
+            "drawbox=x=88:y=38:w=24:h=24:color=yellow@0.8:t=3:enable='between(t,2.000,2.120)',"
+        ));
+        assert!(filters.contains("drawbox=x=68:y=18:w=64:h=64"));
+        assert!(filters.contains(r"drawtext=text=Ctrl+\\:"));
+        assert!(filters.ends_with("enable='between(t,3.000,4.500)'"));
+        assert_eq!(&args[args.len() - 3..], ["-c:a", "copy", "out.mp4"]);
+
+        assert!(annotate_command(Path::new("in.mp4"), Path::new("out.mp4"), &[], None).is_err());
+    }
+
+    #[test]
+    fn timelapse_events_are_drawn_where_they_play() {
+        // Ten and fifteen minutes in, one frame every 2 seconds played at 30 fps
+        let events = vec![
+            InputEvent::Click {
+                t: 600.0,
+                x: 100,
+                y: 50,
+                button: 1,
+            },
+            key(900.0, "a"),
+        ];
+
+        let events = timelapse_events(events, 2.0);
+        assert_eq!(
+            events,
+            [
+                InputEvent::Click {
+                    t: 10.0,
+                    x: 100,
+                    y: 50,
+                    button: 1,
+                },
+                key(15.0, "a"),
+            ]
+        );
+
+        let args = annotate_command(Path::new("in.mp4"), Path::new("out.mp4"), &events, None)
+            .unwrap()
+            .args()
+            .unwrap();
+        let filters = &args[args.iter().position(|arg| arg == "-vf").unwrap() + 1];
+        assert!(filters.starts_with(
+            "drawbox=x=88:y=38:w=24:h=24:color=yellow@0.8:t=3:enable='between(t,10.000,10.120)',"
+        ));
+        assert!(filters.ends_with("enable='between(t,15.000,16.500)'"));
+    }
+
+    #[test]
+    fn events_are_timed_from_the_capture_start() {
+        let events = vec![key(0.2, "a"), key(1.5, "b"), key(3.0, "c")];
+        assert_eq!(
+            since_capture_start(events, 1.0),
+            [key(0.5, "b"), key(2.0, "c")]
+        );
+    }
+}
@@ -0,0 +1,731 @@
+use std::path::Path;
+
+/// Where an ffmpeg input reads from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputSource {
+    /// A capture device through an input format such as x11grab or avfoundation
+    Device { format: String, target: String },
+    /// A lavfi source filter graph, e.g. "testsrc2=size=1280x720"
+    Lavfi(String),
+    /// A media file
+    File(String),
+}
+
+/// One `-i` input together with its input options.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Input {
+    pub source: InputSource,
+    options: Vec<(String, String)>,
+}
+
+impl Input {
+    pub fn device(format: &str, target: &str) -> Self {
+        Self::new(InputSource::Device {
+            format: format.to_string(),
+            target: target.to_string(),
+        })
+    }
+
+    pub fn lavfi(graph: &str) -> Self {
+        Self::new(InputSource::Lavfi(graph.to_string()))
+    }
+
+    pub fn file(path: impl AsRef<Path>) -> Self {
+        Self::new(InputSource::File(
+            path.as_ref().to_string_lossy().to_string(),
+        ))
+    }
+
+    fn new(source: InputSource) -> Self {
+        Self {
+            source,
+            options: Vec::new(),
+        }
+    }
+
+    /// Add an input option, written as `-<key> <value>` before `-i`.
+    pub fn option(mut self, key: &str, value: impl ToString) -> Self {
+        self.options.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Seek to `seconds` before decoding.
+    pub fn seek(self, seconds: f64) -> Self {
+        self.option("ss", format!("{:.3}", seconds))
+    }
+
+    fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+
+        match &self.source {
+            InputSource::Device { format, .. } => args.extend(["-f".to_string(), format.clone()]),
+            InputSource::Lavfi(_) => args.extend(["-f".to_string(), "lavfi".to_string()]),
+            InputSource::File(_) => {}
+        }
+
+        for (key, value) in &self.options {
+            args.push(format!("-{}", key));
+            args.push(value.clone());
+        }
+
+        args.push("-i".to_string());
+        args.push(match &self.source {
+            InputSource::Device { target, .. } => target.clone(),
+            InputSource::Lavfi(graph) => graph.clone(),
+            InputSource::File(path) => path.clone(),
+        });
+
+        args
+    }
+}
+
+/// A filter graph applied to the inputs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterGraph {
+    /// A chain of filters for the single video stream (`-vf`), joined with ","
+    Video(Vec<String>),
+    /// Labelled chains across several inputs (`-filter_complex`), joined with ";"
+    Complex(Vec<String>),
+}
+
+impl FilterGraph {
+    fn args(&self) -> [String; 2] {
+        match self {
+            FilterGraph::Video(filters) => ["-vf".to_string(), filters.join(",")],
+            FilterGraph::Complex(chains) => ["-filter_complex".to_string(), chains.join(";")],
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            FilterGraph::Video(filters) | FilterGraph::Complex(filters) => {
+                filters.iter().all(|filter| filter.trim().is_empty())
+            }
+        }
+    }
+
+    fn has_label(&self, label: &str) -> bool {
+        match self {
+            FilterGraph::Video(_) => false,
+            FilterGraph::Complex(chains) => chains.iter().any(|chain| chain.contains(label)),
+        }
+    }
+}
+
+/// An encoder and its options for one stream type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Encoder {
+    pub codec: String,
+    options: Vec<(String, String)>,
+}
+
+impl Encoder {
+    pub fn new(codec: &str) -> Self {
+        Self {
+            codec: codec.to_string(),
+            options: Vec::new(),
+        }
+    }
+
+    /// Add an encoder option, written as `-<key> <value>` after the codec.
+    pub fn option(mut self, key: &str, value: impl ToString) -> Self {
+        self.options.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    fn is_copy(&self) -> bool {
+        self.codec == "copy"
+    }
+
+    fn args(&self, stream: &str) -> Vec<String> {
+        let mut args = vec![format!("-c:{}", stream), self.codec.clone()];
+        for (key, value) in &self.options {
+            args.push(format!("-{}", key));
+            args.push(value.clone());
+        }
+        args
+    }
+}
+
+/// A further output of the same command, with its own streams, filters and format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtraOutput {
+    maps: Vec<String>,
+    video_filters: Vec<String>,
+    video: Option<Encoder>,
+    options: Vec<(String, String)>,
+    target: String,
+}
+
+impl ExtraOutput {
+    pub fn new(target: &str) -> Self {
+        Self {
+            maps: Vec::new(),
+            video_filters: Vec::new(),
+            video: None,
+            options: Vec::new(),
+            target: target.to_string(),
+        }
+    }
+
+    /// Select a stream for this output, e.g. "0:v".
+    pub fn map(mut self, spec: &str) -> Self {
+        self.maps.push(spec.to_string());
+        self
+    }
+
+    /// Append a filter to this output's video chain (`-vf`).
+    pub fn video_filter(mut self, filter: &str) -> Self {
+        self.video_filters.push(filter.to_string());
+        self
+    }
+
+    pub fn video_encoder(mut self, encoder: Encoder) -> Self {
+        self.video = Some(encoder);
+        self
+    }
+
+    /// Add an output option, written as `-<key> <value>` before the target.
+    pub fn option(mut self, key: &str, value: impl ToString) -> Self {
+        self.options.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        for map in &self.maps {
+            args.push("-map".to_string());
+            args.push(map.clone());
+        }
+        if !self.video_filters.is_empty() {
+            args.push("-vf".to_string());
+            args.push(self.video_filters.join(","));
+        }
+        if let Some(video) = &self.video {
+            args.extend(video.args("v"));
+        }
+        for (key, value) in &self.options {
+            args.push(format!("-{}", key));
+            args.push(value.clone());
+        }
+        args.push(self.target.clone());
+        args
+    }
+}
+
+/// A complete ffmpeg invocation: inputs, filters, stream maps, encoders and outputs.
+///
+/// `args()` checks the combination before anything is spawned, so mistakes show
+/// up as a readable error rather than as ffmpeg's stderr.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FfmpegCommand {
+    overwrite: bool,
+    inputs: Vec<Input>,
+    filter: Option<FilterGraph>,
+    maps: Vec<String>,
+    video: Option<Encoder>,
+    audio: Option<Encoder>,
+    output_options: Vec<(String, String)>,
+    output: Option<String>,
+    extra_outputs: Vec<ExtraOutput>,
+}
+
+impl FfmpegCommand {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the output file if it exists (`-y`).
+    pub fn overwrite(mut self) -> Self {
+        self.overwrite = true;
+        self
+    }
+
+    pub fn input(mut self, input: Input) -> Self {
+        self.inputs.push(input);
+        self
+    }
+
+    pub fn filter(mut self, filter: FilterGraph) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Pass the video this command writes through further filter chains, such as
+    /// drawing over the finished picture. `chains` gets the label of that video
+    /// ("[0:v]" when nothing filters it yet) and the index of the first of
+    /// `inputs`, and must end in the label `output`, which is mapped instead.
+    pub fn then_filter(
+        mut self,
+        inputs: Vec<Input>,
+        output: &str,
+        chains: impl FnOnce(&str, usize) -> Vec<String>,
+    ) -> Self {
+        let first_input = self.inputs.len();
+        self.inputs.extend(inputs);
+
+        // The video written now: a filter graph label, a mapped stream or input 0
+        let mapped = self
+            .maps
+            .iter()
+            .position(|map| map.starts_with('[') || map.ends_with(":v"));
+        let source = match mapped {
+            Some(i) => match self.maps[i].starts_with('[') {
+                true => self.maps[i].clone(),
+                false => format!("[{}]", self.maps[i]),
+            },
+            None => "[0:v]".to_string(),
+        };
+        let (mut graph, video) = match self.filter.take() {
+            Some(FilterGraph::Complex(graph)) => (graph, source),
+            Some(FilterGraph::Video(filters)) if !filters.is_empty() => (
+                vec![format!("{}{}[filtered]", source, filters.join(","))],
+                "[filtered]".to_string(),
+            ),
+            _ => (Vec::new(), source),
+        };
+
+        graph.extend(chains(&video, first_input));
+        self.filter = Some(FilterGraph::Complex(graph));
+        match mapped {
+            Some(i) => self.maps[i] = output.to_string(),
+            // A mapped graph output turns off picking the audio automatically
+            None => {
+                self.maps.insert(0, output.to_string());
+                self.maps.push("0:a?".to_string());
+            }
+        }
+        self
+    }
+
+    /// Select a stream for the output, e.g. "0:v" or a filter graph label "[v]".
+    pub fn map(mut self, spec: &str) -> Self {
+        self.maps.push(spec.to_string());
+        self
+    }
+
+    pub fn video_encoder(mut self, encoder: Encoder) -> Self {
+        self.video = Some(encoder);
+        self
+    }
+
+    pub fn audio_encoder(mut self, encoder: Encoder) -> Self {
+        self.audio = Some(encoder);
+        self
+    }
+
+    /// Stop writing after `seconds` (`-t`).
+    pub fn duration(self, seconds: impl ToString) -> Self {
+        self.output_option("t", seconds)
+    }
+
+    /// Add an output option, written as `-<key> <value>` before the output file.
+    pub fn output_option(mut self, key: &str, value: impl ToString) -> Self {
+        self.output_options
+            .push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn output(mut self, path: impl AsRef<Path>) -> Self {
+        self.output = Some(path.as_ref().to_string_lossy().to_string());
+        self
+    }
+
+    /// Write a further output after the main one.
+    pub fn extra_output(mut self, output: ExtraOutput) -> Self {
+        self.extra_outputs.push(output);
+        self
+    }
+
+    /// Check the command and produce the argument list (without the program name).
+    pub fn args(&self) -> Result<Vec<String>, String> {
+        self.validate()?;
+
+        let mut args = Vec::new();
+        if self.overwrite {
+            args.push("-y".to_string());
+        }
+
+        for input in &self.inputs {
+            args.extend(input.args());
+        }
+
+        if let Some(filter) = &self.filter {
+            args.extend(filter.args());
+        }
+
+        for map in &self.maps {
+            args.push("-map".to_string());
+            args.push(map.clone());
+        }
+
+        if let Some(video) = &self.video {
+            args.extend(video.args("v"));
+        }
+
+        if let Some(audio) = &self.audio {
+            args.extend(audio.args("a"));
+        }
+
+        for (key, value) in &self.output_options {
+            args.push(format!("-{}", key));
+            args.push(value.clone());
+        }
+
+        // validate() made sure there is an output
+        args.extend(self.output.clone());
+
+        for output in &self.extra_outputs {
+            args.extend(output.args());
+        }
+
+        Ok(args)
+    }
+
+    /// The command as a single line that can be pasted into a POSIX shell.
+    pub fn to_shell_string(&self) -> Result<String, String> {
+        let args = self.args()?;
+        let mut words = vec!["ffmpeg".to_string()];
+        words.extend(args.iter().map(|arg| shell_quote(arg)));
+        Ok(words.join(" "))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.inputs.is_empty() {
+            return Err("The ffmpeg command has no inputs.".to_string());
+        }
+
+        let output = match self.output.as_deref() {
+            Some(output) if !output.trim().is_empty() => output,
+            _ => return Err("The ffmpeg command has no output file.".to_string()),
+        };
+
+        for input in &self.inputs {
+            match &input.source {
+                InputSource::Device { format, target }
+                    if format.is_empty() || target.is_empty() =>
+                {
+                    return Err("Capture devices need an input format and a device.".into());
+                }
+                InputSource::Lavfi(graph) if graph.trim().is_empty() => {
+                    return Err("The lavfi input has an empty filter graph.".to_string());
+                }
+                InputSource::File(path) if path == output => {
+                    return Err(format!("The output file {} is also an input.", output));
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(filter) = &self.filter {
+            if filter.is_empty() {
+                return Err("The filter graph is empty.".to_string());
+            }
+            if self.video.as_ref().is_some_and(Encoder::is_copy) {
+                return Err("Video cannot be filtered while it is stream copied.".to_string());
+            }
+        }
+
+        for map in &self.maps {
+            self.validate_map(map)?;
+        }
+
+        for extra in &self.extra_outputs {
+            if extra.target.trim().is_empty() {
+                return Err("An extra ffmpeg output has no target.".to_string());
+            }
+            for map in &extra.maps {
+                self.validate_map(map)?;
+            }
+        }
+
+        let extension = Path::new(output)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase());
+        if matches!(extension.as_deref(), Some("gif" | "png" | "jpg")) && self.audio.is_some() {
+            return Err(format!("{} cannot contain audio.", output));
+        }
+
+        Ok(())
+    }
+
+    fn validate_map(&self, map: &str) -> Result<(), String> {
+        if map.starts_with('[') {
+            if !self
+                .filter
+                .as_ref()
+                .is_some_and(|filter| filter.has_label(map))
+            {
+                return Err(format!(
+                    "Stream map {} does not match a filter graph output.",
+                    map
+                ));
+            }
+            return Ok(());
+        }
+
+        let index = map
+            .trim_start_matches('-')
+            .split(':')
+            .next()
+            .and_then(|index| index.parse::<usize>().ok())
+            .ok_or_else(|| format!("Invalid stream map '{}'.", map))?;
+        if index >= self.inputs.len() {
+            return Err(format!(
+                "Stream map {} refers to input {}, but there are only {} inputs.",
+                map,
+                index,
+                self.inputs.len()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Escape `text` for use as a filter option value inside a filter graph, such as
+/// drawtext's `text`. Both the option parser and the graph parser unescape once.
+pub fn escape_filter_value(text: &str) -> String {
+    let escape = |text: &str, special: &str| {
+        let mut escaped = String::new();
+        for c in text.chars() {
+            if special.contains(c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    };
+
+    escape(&escape(text, "\\':"), "\\',;[]")
+}
+
+// Quote `arg` for a POSIX shell if it contains anything but safe characters
+fn shell_quote(arg: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./:=+,@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(safe) {
+        return arg.to_string();
+    }
+
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simple() -> FfmpegCommand {
+        FfmpegCommand::new()
+            .overwrite()
+            .input(Input::device("x11grab", ":1").option("framerate", 30))
+            .input(Input::device("pulse", "default"))
+            .video_encoder(Encoder::new("libx264").option("crf", 23))
+            .audio_encoder(Encoder::new("aac"))
+            .duration(5)
+            .output("out.mp4")
+    }
+
+    #[test]
+    fn builds_arguments_in_ffmpeg_order() {
+        assert_eq!(
+            simple().args().unwrap(),
+            [
+                "-y",
+                "-f",
+                "x11grab",
+                "-framerate",
+                "30",
+                "-i",
+                ":1",
+                "-f",
+                "pulse",
+                "-i",
+                "default",
+                "-c:v",
+                "libx264",
+                "-crf",
+                "23",
+                "-c:a",
+                "aac",
+                "-t",
+                "5",
+                "out.mp4",
+            ]
+        );
+    }
+
+    #[test]
+    fn then_filter_continues_from_the_written_video() {
+        let draw = |video: &str, _| vec![format!("{}drawbox[out]", video)];
+
+        // Nothing mapped: the graph output and any audio get mapped
+        let plain = FfmpegCommand::new()
+            .input(Input::file("in.mp4"))
+            .output("out.mp4")
+            .then_filter(vec![], "[out]", draw);
+        assert_eq!(
+            plain.args().unwrap(),
+            [
+                "-i",
+                "in.mp4",
+                "-filter_complex",
+                "[0:v]drawbox[out]",
+                "-map",
+                "[out]",
+                "-map",
+                "0:a?",
+                "out.mp4"
+            ]
+        );
+
+        // A -vf chain becomes the start of the graph, the mapped stream is replaced
+        let chained = FfmpegCommand::new()
+            .input(Input::file("in.mp4"))
+            .filter(FilterGraph::Video(vec!["crop=10:10".to_string()]))
+            .map("0:v")
+            .map("0:a?")
+            .then_filter(vec![Input::file("logo.png")], "[out]", |video, logo| {
+                vec![format!("{}[{}:v]overlay[out]", video, logo)]
+            })
+            .output("out.mp4");
+        assert_eq!(
+            chained.args().unwrap()[4..10],
+            [
+                "-filter_complex",
+                "[0:v]crop=10:10[filtered];[filtered][1:v]overlay[out]",
+                "-map",
+                "[out]",
+                "-map",
+                "0:a?"
+            ]
+        );
+    }
+
+    #[test]
+    fn complex_filter_and_maps() {
+        let command = FfmpegCommand::new()
+            .input(Input::file("a.mp4"))
+            .input(Input::file("b.mp4").seek(1.5))
+            .filter(FilterGraph::Complex(vec![
+                "[0:v][1:v]hstack[v]".to_string(),
+                "[0:a][1:a]amix[a]".to_string(),
+            ]))
+            .map("[v]")
+            .map("[a]")
+            .output("both.mp4");
+
+        assert_eq!(
+            command.args().unwrap(),
+            [
+                "-i",
+                "a.mp4",
+                "-ss",
+                "1.500",
+                "-i",
+                "b.mp4",
+                "-filter_complex",
+                "[0:v][1:v]hstack[v];[0:a][1:a]amix[a]",
+                "-map",
+                "[v]",
+                "-map",
+                "[a]",
+                "both.mp4",
+            ]
+        );
+    }
+
+    #[test]
+    fn extra_outputs_follow_the_main_output() {
+        let command = simple().extra_output(
+            ExtraOutput::new("pipe:1")
+                .map("0:v")
+                .video_filter("fps=2")
+                .video_filter("scale=320:180")
+                .option("f", "rawvideo"),
+        );
+
+        let args = command.args().unwrap();
+        let main = args.iter().position(|arg| arg == "out.mp4").unwrap();
+        assert_eq!(
+            args[main + 1..],
+            [
+                "-map",
+                "0:v",
+                "-vf",
+                "fps=2,scale=320:180",
+                "-f",
+                "rawvideo",
+                "pipe:1"
+            ]
+        );
+
+        let bad_map = simple().extra_output(ExtraOutput::new("pipe:1").map("5:v"));
+        assert!(bad_map.args().is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_combinations() {
+        let no_output = FfmpegCommand::new().input(Input::file("in.mp4"));
+        assert!(no_output.args().is_err());
+
+        let no_input = FfmpegCommand::new().output("out.mp4");
+        assert!(no_input.args().is_err());
+
+        let bad_map = FfmpegCommand::new()
+            .input(Input::file("in.mp4"))
+            .map("1:v")
+            .output("out.mp4");
+        assert!(bad_map.args().unwrap_err().contains("input 1"));
+
+        let unknown_label = FfmpegCommand::new()
+            .input(Input::file("in.mp4"))
+            .filter(FilterGraph::Video(vec!["scale=320:-1".to_string()]))
+            .map("[v]")
+            .output("out.mp4");
+        assert!(unknown_label.args().is_err());
+
+        let filtered_copy = FfmpegCommand::new()
+            .input(Input::file("in.mp4"))
+            .filter(FilterGraph::Video(vec!["scale=320:-1".to_string()]))
+            .video_encoder(Encoder::new("copy"))
+            .output("out.mp4");
+        assert!(filtered_copy.args().is_err());
+
+        let gif_audio = FfmpegCommand::new()
+            .input(Input::file("in.mp4"))
+            .audio_encoder(Encoder::new("aac"))
+            .output("out.gif");
+        assert!(gif_audio.args().is_err());
+
+        let same_file = FfmpegCommand::new()
+            .input(Input::file("in.mp4"))
+            .output("in.mp4");
+        assert!(same_file.args().is_err());
+    }
+
+    #[test]
+    fn filter_values_are_escaped_for_both_parsers() {
+        // The example from the ffmpeg-filters documentation
+        assert_eq!(
+            escape_filter_value("this is a 'string': may contain one, or more, special characters"),
+            r"this is a \\\'string\\\'\\: may contain one\, or more\, special characters"
+        );
+        assert_eq!(escape_filter_value(r"a\b[c]"), r"a\\\\b\[c\]");
+    }
+
+    #[test]
+    fn shell_string_quotes_only_when_needed() {
+        let command = FfmpegCommand::new()
+            .input(Input::file("my clip's.mp4"))
+            .filter(FilterGraph::Video(vec![
+                "fps=10".to_string(),
+                "split[s0][s1]".to_string(),
+            ]))
+            .output("out.gif");
+
+        assert_eq!(
+            command.to_shell_string().unwrap(),
+            r"ffmpeg -i 'my clip'\''s.mp4' -vf 'fps=10,split[s0][s1]' out.gif"
+        );
+    }
+}
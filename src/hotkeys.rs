@@ -0,0 +1,532 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// What a global hotkey does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotkeyAction {
+    Start,
+    Stop,
+    Pause,
+    Mark,
+}
+
+impl HotkeyAction {
+    pub const ALL: [HotkeyAction; 4] = [
+        HotkeyAction::Start,
+        HotkeyAction::Stop,
+        HotkeyAction::Pause,
+        HotkeyAction::Mark,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            HotkeyAction::Start => "Start recording",
+            HotkeyAction::Stop => "Stop recording",
+            HotkeyAction::Pause => "Pause / resume",
+            HotkeyAction::Mark => "Mark",
+        }
+    }
+}
+
+// Named keys accepted in bindings, with the X keysym each one maps to
+const NAMED_KEYS: [(&str, &str); 13] = [
+    ("Space", "space"),
+    ("Enter", "Return"),
+    ("Escape", "Escape"),
+    ("Tab", "Tab"),
+    ("Home", "Home"),
+    ("End", "End"),
+    ("Insert", "Insert"),
+    ("Delete", "Delete"),
+    ("PageUp", "Prior"),
+    ("PageDown", "Next"),
+    ("Pause", "Pause"),
+    ("Print", "Print"),
+    ("ScrollLock", "Scroll_Lock"),
+];
+
+/// A key combination such as "Ctrl+Alt+R", stored in the config as that string.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Hotkey {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub super_key: bool,
+    /// "R", "5", "F9" or one of the named keys such as "PageUp"
+    pub key: String,
+}
+
+impl Hotkey {
+    // The X keysym name of the key
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn keysym_name(&self) -> &str {
+        NAMED_KEYS
+            .iter()
+            .find(|(name, _)| *name == self.key)
+            .map(|(_, keysym)| *keysym)
+            .unwrap_or(&self.key)
+    }
+}
+
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let modifiers = [
+            (self.ctrl, "Ctrl"),
+            (self.alt, "Alt"),
+            (self.shift, "Shift"),
+            (self.super_key, "Super"),
+        ];
+        for (_, name) in modifiers.iter().filter(|(held, _)| *held) {
+            write!(f, "{}+", name)?;
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+impl FromStr for Hotkey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut hotkey = Hotkey {
+            ctrl: false,
+            alt: false,
+            shift: false,
+            super_key: false,
+            key: String::new(),
+        };
+
+        let parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let (key, modifiers) = parts.split_last().expect("split always yields a part");
+
+        for modifier in modifiers {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => hotkey.ctrl = true,
+                "alt" => hotkey.alt = true,
+                "shift" => hotkey.shift = true,
+                "super" | "win" | "meta" => hotkey.super_key = true,
+                other => return Err(format!("Unknown modifier '{}' in hotkey '{}'", other, s)),
+            }
+        }
+
+        hotkey.key = if key.len() == 1 && key.chars().all(|c| c.is_ascii_alphanumeric()) {
+            key.to_uppercase()
+        } else if let Some(number) = key
+            .strip_prefix(['F', 'f'])
+            .and_then(|n| n.parse::<u32>().ok())
+            .filter(|n| (1..=24).contains(n))
+        {
+            format!("F{}", number)
+        } else if let Some((name, _)) = NAMED_KEYS
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+        {
+            name.to_string()
+        } else {
+            return Err(format!(
+                "Unknown key '{}' in hotkey '{}'. Use a letter, digit, F1-F24 or one of: {}",
+                key,
+                s,
+                NAMED_KEYS.map(|(name, _)| name).join(", ")
+            ));
+        };
+
+        if !(hotkey.ctrl || hotkey.alt || hotkey.super_key) {
+            return Err(format!(
+                "Hotkey '{}' needs Ctrl, Alt or Super, or it would swallow normal typing",
+                s
+            ));
+        }
+
+        Ok(hotkey)
+    }
+}
+
+impl TryFrom<String> for Hotkey {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Hotkey> for String {
+    fn from(hotkey: Hotkey) -> Self {
+        hotkey.to_string()
+    }
+}
+
+/// The `[hotkeys]` section of the config file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HotkeyBindings {
+    pub enabled: bool,
+    pub start: Hotkey,
+    pub stop: Hotkey,
+    pub pause: Hotkey,
+    pub mark: Hotkey,
+}
+
+impl Default for HotkeyBindings {
+    fn default() -> Self {
+        let parse = |s: &str| s.parse::<Hotkey>().expect("default hotkeys are valid");
+
+        Self {
+            enabled: true,
+            start: parse("Ctrl+Alt+R"),
+            stop: parse("Ctrl+Alt+S"),
+            pause: parse("Ctrl+Alt+P"),
+            mark: parse("Ctrl+Alt+M"),
+        }
+    }
+}
+
+impl HotkeyBindings {
+    pub fn get(&self, action: HotkeyAction) -> &Hotkey {
+        match action {
+            HotkeyAction::Start => &self.start,
+            HotkeyAction::Stop => &self.stop,
+            HotkeyAction::Pause => &self.pause,
+            HotkeyAction::Mark => &self.mark,
+        }
+    }
+
+    pub fn set(&mut self, action: HotkeyAction, hotkey: Hotkey) {
+        match action {
+            HotkeyAction::Start => self.start = hotkey,
+            HotkeyAction::Stop => self.stop = hotkey,
+            HotkeyAction::Pause => self.pause = hotkey,
+            HotkeyAction::Mark => self.mark = hotkey,
+        }
+    }
+
+    /// Refuse bindings where one key combination triggers two actions.
+    pub fn validate(&self) -> Result<(), String> {
+        for (i, first) in HotkeyAction::ALL.iter().enumerate() {
+            for second in &HotkeyAction::ALL[i + 1..] {
+                if self.get(*first) == self.get(*second) {
+                    return Err(format!(
+                        "{} is bound to both '{}' and '{}'",
+                        self.get(*first),
+                        first.label(),
+                        second.label()
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Grabbed global hotkeys. The keys are released when this is dropped.
+pub struct HotkeyListener {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for HotkeyListener {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Grab the bound keys for the whole desktop and send their actions to `sender`,
+/// calling `wake` after each one so a minimized window still reacts.
+pub fn listen(
+    bindings: &HotkeyBindings,
+    sender: Sender<HotkeyAction>,
+    wake: impl Fn() + Send + 'static,
+) -> Result<HotkeyListener, Box<dyn std::error::Error>> {
+    bindings.validate()?;
+
+    let keys: Vec<(HotkeyAction, Hotkey)> = HotkeyAction::ALL
+        .iter()
+        .map(|action| (*action, bindings.get(*action).clone()))
+        .collect();
+    let running = Arc::new(AtomicBool::new(true));
+    let running_clone = running.clone();
+
+    // The listener reports whether every key could be grabbed before it starts waiting
+    let (ready_tx, ready_rx) = channel();
+    let thread = thread::spawn(move || {
+        #[cfg(target_os = "linux")]
+        x11::run(&keys, &sender, &wake, &running_clone, &ready_tx);
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = (keys, sender, wake, running_clone);
+            let _ = ready_tx.send(Err(
+                "Global hotkeys are only supported on Linux with X11.".to_string()
+            ));
+        }
+    });
+
+    match ready_rx.recv() {
+        Ok(Ok(())) => Ok(HotkeyListener {
+            running,
+            thread: Some(thread),
+        }),
+        Ok(Err(e)) => {
+            let _ = thread.join();
+            Err(e.into())
+        }
+        Err(_) => Err("The hotkey listener stopped unexpectedly.".into()),
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod x11 {
+    use super::{Hotkey, HotkeyAction};
+    use std::ffi::CString;
+    use std::os::raw::{c_int, c_uint};
+    use std::ptr;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc::Sender;
+    use std::thread;
+    use std::time::Duration;
+    use x11_dl::xlib;
+
+    // Set by the error handler when another client already grabbed a key
+    static GRAB_FAILED: AtomicBool = AtomicBool::new(false);
+
+    unsafe extern "C" fn on_error(_: *mut xlib::Display, event: *mut xlib::XErrorEvent) -> c_int {
+        if (*event).error_code == xlib::BadAccess {
+            GRAB_FAILED.store(true, Ordering::SeqCst);
+        }
+        0
+    }
+
+    // Caps Lock and Num Lock show up as extra modifiers, so each key is grabbed with
+    // every combination of them
+    const LOCK_MASKS: [c_uint; 4] = [
+        0,
+        xlib::LockMask,
+        xlib::Mod2Mask,
+        xlib::LockMask | xlib::Mod2Mask,
+    ];
+
+    fn modifiers(hotkey: &Hotkey) -> c_uint {
+        let mut mask = 0;
+        if hotkey.ctrl {
+            mask |= xlib::ControlMask;
+        }
+        if hotkey.alt {
+            mask |= xlib::Mod1Mask;
+        }
+        if hotkey.shift {
+            mask |= xlib::ShiftMask;
+        }
+        if hotkey.super_key {
+            mask |= xlib::Mod4Mask;
+        }
+        mask
+    }
+
+    struct Grab {
+        keycode: c_int,
+        modifiers: c_uint,
+        action: HotkeyAction,
+    }
+
+    pub fn run(
+        keys: &[(HotkeyAction, Hotkey)],
+        sender: &Sender<HotkeyAction>,
+        wake: &dyn Fn(),
+        running: &AtomicBool,
+        ready: &Sender<Result<(), String>>,
+    ) {
+        let xlib = match xlib::Xlib::open() {
+            Ok(xlib) => xlib,
+            Err(e) => {
+                let _ = ready.send(Err(format!("Could not load libX11: {}", e)));
+                return;
+            }
+        };
+
+        unsafe {
+            // A connection of our own, so the GUI's event loop never sees these events
+            let display = (xlib.XOpenDisplay)(ptr::null());
+            if display.is_null() {
+                let _ = ready.send(Err(
+                    "Could not open the X display. Global hotkeys need an X11 session.".to_string(),
+                ));
+                return;
+            }
+            let root = (xlib.XDefaultRootWindow)(display);
+
+            // Without this, holding a key down repeats it and would toggle pause on and off
+            (xlib.XkbSetDetectableAutoRepeat)(display, xlib::True, ptr::null_mut());
+
+            let grabs = grab_keys(&xlib, display, root, keys);
+            let grabs = match grabs {
+                Ok(grabs) => {
+                    let _ = ready.send(Ok(()));
+                    grabs
+                }
+                Err(e) => {
+                    let _ = ready.send(Err(e));
+                    (xlib.XCloseDisplay)(display);
+                    return;
+                }
+            };
+
+            let mut event: xlib::XEvent = std::mem::zeroed();
+            let relevant = xlib::ControlMask | xlib::Mod1Mask | xlib::ShiftMask | xlib::Mod4Mask;
+
+            'listen: while running.load(Ordering::SeqCst) {
+                while (xlib.XPending)(display) > 0 {
+                    (xlib.XNextEvent)(display, &mut event);
+                    if event.get_type() != xlib::KeyPress {
+                        continue;
+                    }
+
+                    let key = event.key;
+                    let grab = grabs.iter().find(|grab| {
+                        grab.keycode as c_uint == key.keycode
+                            && grab.modifiers == key.state & relevant
+                    });
+                    if let Some(grab) = grab {
+                        if sender.send(grab.action).is_err() {
+                            break 'listen;
+                        }
+                        wake();
+                    }
+                }
+
+                thread::sleep(Duration::from_millis(50));
+            }
+
+            ungrab_keys(&xlib, display, root, &grabs);
+            (xlib.XCloseDisplay)(display);
+        }
+    }
+
+    unsafe fn grab_keys(
+        xlib: &xlib::Xlib,
+        display: *mut xlib::Display,
+        root: xlib::Window,
+        keys: &[(HotkeyAction, Hotkey)],
+    ) -> Result<Vec<Grab>, String> {
+        // Grab errors arrive asynchronously through the process-wide error handler,
+        // so ours is only installed while grabbing
+        let previous = (xlib.XSetErrorHandler)(Some(on_error));
+        let mut grabs = Vec::new();
+        let mut result = Ok(());
+
+        for (action, hotkey) in keys {
+            let name = CString::new(hotkey.keysym_name()).expect("key names have no NUL");
+            let keysym = (xlib.XStringToKeysym)(name.as_ptr());
+            let keycode = (xlib.XKeysymToKeycode)(display, keysym) as c_int;
+            if keysym == 0 || keycode == 0 {
+                result = Err(format!("No key on this keyboard produces {}", hotkey.key));
+                break;
+            }
+
+            let grab = Grab {
+                keycode,
+                modifiers: modifiers(hotkey),
+                action: *action,
+            };
+
+            GRAB_FAILED.store(false, Ordering::SeqCst);
+            for lock in LOCK_MASKS {
+                (xlib.XGrabKey)(
+                    display,
+                    grab.keycode,
+                    grab.modifiers | lock,
+                    root,
+                    xlib::False,
+                    xlib::GrabModeAsync,
+                    xlib::GrabModeAsync,
+                );
+            }
+            (xlib.XSync)(display, xlib::False);
+            grabs.push(grab);
+
+            if GRAB_FAILED.load(Ordering::SeqCst) {
+                result = Err(format!("{} is already used by another application", hotkey));
+                break;
+            }
+        }
+
+        if result.is_err() {
+            ungrab_keys(xlib, display, root, &grabs);
+        }
+        (xlib.XSetErrorHandler)(previous);
+
+        result.map(|_| grabs)
+    }
+
+    unsafe fn ungrab_keys(
+        xlib: &xlib::Xlib,
+        display: *mut xlib::Display,
+        root: xlib::Window,
+        grabs: &[Grab],
+    ) {
+        for grab in grabs {
+            for lock in LOCK_MASKS {
+                (xlib.XUngrabKey)(display, grab.keycode, grab.modifiers | lock, root);
+            }
+        }
+        (xlib.XSync)(display, xlib::False);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_normalizes_hotkeys() {
+        let hotkey: Hotkey = "control + alt + r".parse().unwrap();
+        assert!(hotkey.ctrl && hotkey.alt && !hotkey.shift && !hotkey.super_key);
+        assert_eq!(hotkey.key, "R");
+        assert_eq!(hotkey.to_string(), "Ctrl+Alt+R");
+
+        assert_eq!(
+            "Super+Shift+f9".parse::<Hotkey>().unwrap().to_string(),
+            "Shift+Super+F9"
+        );
+        assert_eq!(
+            "Ctrl+pageup".parse::<Hotkey>().unwrap().keysym_name(),
+            "Prior"
+        );
+    }
+
+    #[test]
+    fn rejects_bad_hotkeys() {
+        assert!("Ctrl+Hyper+R".parse::<Hotkey>().is_err());
+        assert!("Ctrl+F25".parse::<Hotkey>().is_err());
+        assert!("Ctrl+Alt+".parse::<Hotkey>().is_err());
+        assert!("Shift+R".parse::<Hotkey>().is_err());
+        assert!("R".parse::<Hotkey>().is_err());
+    }
+
+    #[test]
+    fn duplicate_bindings_are_refused() {
+        let mut bindings = HotkeyBindings::default();
+        assert!(bindings.validate().is_ok());
+
+        bindings.set(HotkeyAction::Mark, bindings.start.clone());
+        let error = bindings.validate().unwrap_err();
+        assert!(error.contains("Start recording") && error.contains("Mark"));
+    }
+
+    #[test]
+    fn bindings_round_trip_through_toml() {
+        let bindings = HotkeyBindings::default();
+        let text = toml::to_string(&bindings).unwrap();
+        assert!(text.contains("start = \"Ctrl+Alt+R\""), "{}", text);
+        assert_eq!(toml::from_str::<HotkeyBindings>(&text).unwrap(), bindings);
+
+        assert!(toml::from_str::<HotkeyBindings>("stop = \"Q\"").is_err());
+    }
+}
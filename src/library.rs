@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use crate::chapters::chapters_path;
+use crate::events::event_log_path;
+use crate::media::probe;
+use crate::redact::redact_spec_path;
+use crate::runner::CommandRunner;
+use crate::sidecar::sidecar_path;
+use crate::thumbnail::extract_poster;
+
+/// A finished recording remembered by the Library tab.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LibraryEntry {
+    pub path: PathBuf,
+    /// Unix timestamp of when the recording was added
+    pub created_at: i64,
+    pub duration_secs: Option<f64>,
+    pub size_bytes: u64,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub thumbnail: Option<PathBuf>,
+}
+
+impl LibraryEntry {
+    /// Gather size, duration, resolution and a thumbnail for a recording.
+    pub fn from_recording(
+        runner: &dyn CommandRunner,
+        path: &Path,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let size_bytes = fs::metadata(path)?.len();
+        let info = probe(runner, path).ok();
+        let resolution = info.as_ref().and_then(|info| info.resolution());
+
+        let thumbnail = Library::thumbnail_path(path).filter(|thumbnail| {
+            if let Some(dir) = thumbnail.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            extract_poster(runner, path, thumbnail, None, 160).is_ok()
+        });
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            created_at: chrono::Local::now().timestamp(),
+            duration_secs: info.as_ref().map(|info| info.duration_secs),
+            size_bytes,
+            width: resolution.map(|(width, _)| width),
+            height: resolution.map(|(_, height)| height),
+            thumbnail,
+        })
+    }
+
+    pub fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.path.to_string_lossy().to_string())
+    }
+}
+
+/// The recording history, stored as JSON in the platform data directory.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Library {
+    pub entries: Vec<LibraryEntry>,
+}
+
+impl Library {
+    fn data_dir() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("rcrdr"))
+    }
+
+    pub fn index_path() -> Option<PathBuf> {
+        Self::data_dir().map(|dir| dir.join("library.json"))
+    }
+
+    /// Where the thumbnail for a recording is cached.
+    pub fn thumbnail_path(video: &Path) -> Option<PathBuf> {
+        let mut hasher = DefaultHasher::new();
+        video.hash(&mut hasher);
+
+        Self::data_dir().map(|dir| {
+            dir.join("thumbnails")
+                .join(format!("{:016x}.png", hasher.finish()))
+        })
+    }
+
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        let path = match Self::index_path() {
+            Some(path) if path.exists() => path,
+            _ => return Ok(Self::default()),
+        };
+
+        let contents = fs::read_to_string(&path)?;
+        serde_json::from_str(&contents)
+            .map_err(|e| format!("Invalid library index {}: {}", path.display(), e).into())
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::index_path().ok_or("Could not determine the data directory.")?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    /// Add a recording, replacing any older entry for the same file. Newest first.
+    pub fn add(&mut self, entry: LibraryEntry) {
+        self.entries.retain(|e| e.path != entry.path);
+        self.entries.insert(0, entry);
+    }
+
+    /// Delete a recording and the files kept next to it, and forget it.
+    pub fn delete(&mut self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        for companion in companion_paths(path) {
+            let _ = fs::remove_file(companion);
+        }
+        self.remove(path);
+        Ok(())
+    }
+
+    /// Forget a recording and its cached thumbnail. The video itself is left alone.
+    pub fn remove(&mut self, path: &Path) {
+        if let Some(index) = self.entries.iter().position(|e| e.path == path) {
+            let entry = self.entries.remove(index);
+            if let Some(thumbnail) = entry.thumbnail {
+                let _ = fs::remove_file(thumbnail);
+            }
+        }
+    }
+
+    /// Rename a recording on disk and update its entry.
+    pub fn rename(
+        &mut self,
+        path: &Path,
+        new_name: &str,
+    ) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let new_name = new_name.trim();
+        if new_name.is_empty() || new_name.contains(['/', '\\']) {
+            return Err(format!("Invalid file name: '{}'", new_name).into());
+        }
+
+        let new_path = path.with_file_name(new_name);
+        if new_path.exists() {
+            return Err(format!("A file named {} already exists.", new_path.display()).into());
+        }
+        fs::rename(path, &new_path)?;
+        for (old, new) in companion_paths(path)
+            .into_iter()
+            .zip(companion_paths(&new_path))
+        {
+            if old.exists() {
+                let _ = fs::rename(old, new);
+            }
+        }
+
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.path == path) {
+            entry.path = new_path.clone();
+
+            // Keep the thumbnail cache keyed by the current path
+            if let (Some(old), Some(new)) =
+                (entry.thumbnail.take(), Library::thumbnail_path(&new_path))
+            {
+                if fs::rename(&old, &new).is_ok() {
+                    entry.thumbnail = Some(new);
+                }
+            }
+        }
+
+        Ok(new_path)
+    }
+}
+
+// The metadata, chapters, event log and redaction boxes kept next to a recording
+// and named after it
+fn companion_paths(video: &Path) -> [PathBuf; 4] {
+    [
+        sidecar_path(video),
+        chapters_path(video),
+        event_log_path(video),
+        redact_spec_path(video),
+    ]
+}
@@ -1,16 +1,72 @@
 use crate::app::RcrdrApp;
-use crate::cli::{Cli, Commands};
+use crate::chapters::{add_chapters, chapters_path, read_marks_from_stdin, timelapse_marks};
+use crate::cli::{Cli, Commands, WatermarkArgs};
+use crate::concat::concat;
+use crate::condense::{condense, condensed_path};
+use crate::config::Config;
+use crate::crop::{crop, crop_command, cropped_path};
+use crate::cursor::highlight_cursor;
+use crate::events::{
+    annotate, annotate_command, annotated_path, event_log_path, has_annotations, read_event_log,
+    timelapse_events,
+};
+use crate::media::probe;
+use crate::naming::{apply_overwrite_policy, resolve_output_path, TemplateContext};
+use crate::overlay::show_countdown_overlay;
 use crate::recorder::{
-    convert_to_gif, is_command_available, record_screen, test_recording, verify_video_file,
+    convert_to_gif, gif_command, is_command_available, record_command, record_screen,
+    test_recording, verify_video_file, CursorMode, GifOptions, Platform,
+};
+use crate::redact::{redact, redact_command, redacted_path, RedactSpec};
+use crate::runner::SystemRunner;
+use crate::screenshot::{
+    copy_to_clipboard, screenshot_command, take_screenshot, ScreenshotArea, SCREENSHOT_TEMPLATE,
 };
+use crate::sidecar::{read_sidecar, write_sidecar, RecordingMetadata};
+use crate::subtitle::{add_subtitles, subtitle_command, subtitled_path, Captions};
+use crate::thumbnail::{contact_sheet, contact_sheet_command, extract_poster, poster_command};
+use crate::trim::{plan_trim, trim, trim_command, trimmed_path, TrimMode};
+use crate::watermark::Watermark;
+use crate::webcam::WebcamOverlay;
 use clap::Parser;
 use eframe::{run_native, NativeOptions};
 use std::error::Error;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::channel;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
 mod app;
+mod chapters;
 mod cli;
+mod concat;
+mod condense;
+mod config;
+mod control;
+mod crop;
+mod cursor;
+mod events;
+mod ffmpeg;
+mod hotkeys;
+mod library;
+mod media;
+mod naming;
+mod overlay;
+mod preview;
 mod recorder;
+mod redact;
+mod runner;
+mod screenshot;
+mod sidecar;
+mod subtitle;
+mod thumbnail;
+mod trim;
 mod ui;
+mod watermark;
+mod webcam;
 
 #[cfg(target_os = "macos")]
 fn print_macos_permission_guide() {
@@ -26,6 +82,39 @@ fn print_macos_permission_guide() {
     println!();
 }
 
+// Count down in the terminal. Returns false if Ctrl+C cancelled the recording.
+fn run_countdown(seconds: u64, running: &AtomicBool) -> Result<bool, Box<dyn Error>> {
+    for remaining in (1..=seconds).rev() {
+        print!("\rRecording starts in {}... ", remaining);
+        io::stdout().flush()?;
+
+        // Sleep in small steps so Ctrl+C cancels right away
+        for _ in 0..10 {
+            if !running.load(Ordering::SeqCst) {
+                println!();
+                return Ok(false);
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    if seconds > 0 {
+        println!();
+    }
+    Ok(running.load(Ordering::SeqCst))
+}
+
+/// The watermark an export draws: none unless a watermark flag or
+/// `--watermark-profile` asks for one, so exports of watermarked recordings
+/// don't get a second one.
+fn export_watermark(args: &WatermarkArgs) -> Result<Option<Watermark>, Box<dyn Error>> {
+    let saved = match &args.watermark_profile {
+        Some(name) => Config::load()?.profile(Some(name))?.watermark,
+        None => None,
+    };
+    Ok(args.resolve(saved)?)
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
     // Parse CLI args first to maintain backward compatibility
     let cli = Cli::parse();
@@ -33,16 +122,109 @@ fn main() -> Result<(), Box<dyn Error>> {
     // If there are CLI subcommands, run in CLI mode
     if let Some(command) = &cli.command {
         // Check if ffmpeg is installed
-        if !is_command_available("ffmpeg") {
+        if command.needs_ffmpeg() && !is_command_available("ffmpeg") {
             return Err("FFmpeg is not installed. Please install FFmpeg first.".into());
         }
 
         match command {
             Commands::Record {
                 output,
+                template,
+                overwrite,
                 duration,
+                countdown,
                 fps,
+                profile,
+                source,
+                codec,
+                cursor,
+                audio,
+                no_audio,
+                timelapse,
+                webcam,
+                webcam_position,
+                webcam_width,
+                webcam_shape,
+                webcam_border,
+                webcam_border_color,
+                webcam_file,
+                log_events,
+                annotate: annotate_after,
+                marks_from_stdin,
+                control_socket,
+                condense: condense_after,
+                condense_options,
+                watermark,
+                print_command,
             } => {
+                // Explicit flags win over the profile, which wins over the defaults
+                let config = Config::load()?;
+                let profile_name = profile.clone().or_else(|| config.default_profile.clone());
+                let profile = config.profile(profile_name.as_deref())?;
+                let mut settings = profile.record_settings();
+                if let Some(fps) = fps {
+                    settings.fps = *fps;
+                }
+                if let Some(source) = source {
+                    settings.source = source.clone();
+                }
+                if let Some(codec) = codec {
+                    settings.codec = *codec;
+                }
+                if let Some(cursor) = cursor {
+                    settings.cursor = *cursor;
+                }
+                if timelapse.is_some() {
+                    settings.timelapse = *timelapse;
+                    settings.audio = false;
+                }
+                if let Some(device) = webcam {
+                    settings.webcam = Some(WebcamOverlay {
+                        device: device.clone(),
+                        corner: *webcam_position,
+                        width: *webcam_width,
+                        shape: *webcam_shape,
+                        border: *webcam_border,
+                        border_color: *webcam_border_color,
+                        separate_file: *webcam_file,
+                    });
+                }
+                if *audio {
+                    settings.audio = true;
+                } else if *no_audio {
+                    settings.audio = false;
+                }
+                let saved_watermark = match &watermark.watermark_profile {
+                    Some(name) => config.profile(Some(name))?.watermark,
+                    None => settings.watermark.take(),
+                };
+                settings.watermark = watermark.resolve(saved_watermark)?;
+
+                let policy = overwrite.unwrap_or(config.overwrite);
+                let output = match output {
+                    Some(output) => apply_overwrite_policy(Path::new(output), policy)?,
+                    None => {
+                        let (dir, default_template) = config.output_target(&profile);
+                        std::fs::create_dir_all(&dir)?;
+
+                        let context = TemplateContext {
+                            profile: profile_name.clone(),
+                            source: settings.source.clone(),
+                        };
+                        let template = template.as_deref().unwrap_or(&default_template);
+                        resolve_output_path(&dir, template, &context, policy)?
+                    }
+                };
+                let output = output.to_string_lossy().to_string();
+                let output = &output;
+
+                let command = record_command(Platform::current(), &settings, output, *duration)?
+                    .to_shell_string()?;
+                if *print_command {
+                    println!("{}", command);
+                    return Ok(());
+                }
+
                 println!("Recording screen to {}...", output);
 
                 #[cfg(target_os = "macos")]
@@ -56,30 +238,568 @@ fn main() -> Result<(), Box<dyn Error>> {
                     r.store(false, std::sync::atomic::Ordering::SeqCst);
                 })?;
 
-                record_screen(output, *duration, *fps, running)?;
+                if !run_countdown(*countdown, &running)? {
+                    println!("Recording cancelled.");
+                    return Ok(());
+                }
+
+                // The cursor highlight is drawn afterwards from the logged pointer positions
+                let highlight = settings.cursor == CursorMode::Highlight;
+                let capture = if *log_events || *annotate_after || highlight {
+                    Some(events::capture(
+                        Arc::new(AtomicBool::new(false)),
+                        highlight,
+                    )?)
+                } else {
+                    None
+                };
+
+                // Every line typed while recording drops a chapter mark. Piped input
+                // is only read when asked for
+                let (mark_sender, mark_receiver) = channel();
+                if *marks_from_stdin || io::stdin().is_terminal() {
+                    println!("Type a label and press Enter to mark a chapter.");
+                    read_marks_from_stdin(mark_sender.clone());
+                }
+                let _control = match control_socket {
+                    Some(path) => {
+                        let socket =
+                            control::listen(Path::new(path), mark_sender, running.clone())?;
+                        println!("Listening for mark and stop commands on {}", path);
+                        Some(socket)
+                    }
+                    None => None,
+                };
+
+                // Marks are timed from when ffmpeg really starts capturing
+                let (capture_sender, capture_receiver) = channel();
+                let requested = Instant::now();
+                let started = chrono::Local::now();
+                record_screen(
+                    &SystemRunner,
+                    output,
+                    *duration,
+                    &settings,
+                    running,
+                    capture_sender,
+                )?;
+                let capture_start = capture_receiver.try_recv().unwrap_or(requested);
 
                 // Verify the output file is valid
-                if !verify_video_file(output) {
-                    return Err(format!("Failed to create a valid video file: {}. Try running the 'test' command to diagnose issues.", output).into());
+                if let Err(e) = verify_video_file(&SystemRunner, output) {
+                    return Err(format!("Failed to create a valid video file: {} ({}). Try running the 'test' command to diagnose issues.", output, e).into());
+                }
+
+                let metadata = RecordingMetadata::new(
+                    profile_name,
+                    &settings,
+                    command,
+                    started,
+                    chrono::Local::now(),
+                );
+                write_sidecar(Path::new(output), &metadata)?;
+
+                let events = capture
+                    .map(|capture| capture.finish(capture_start))
+                    .unwrap_or_default();
+                // Drawn and logged where they play in the video
+                let events = match settings.timelapse {
+                    Some(interval) => timelapse_events(events, interval),
+                    None => events,
+                };
+
+                if highlight {
+                    println!("Highlighting the cursor...");
+                    highlight_cursor(&SystemRunner, Path::new(output), settings.codec, &events)?;
+                }
+
+                let marks: Vec<_> = mark_receiver
+                    .try_iter()
+                    .map(|request| request.place(capture_start))
+                    .collect();
+                if !marks.is_empty() {
+                    let marks = match settings.timelapse {
+                        Some(interval) => timelapse_marks(marks, interval),
+                        None => marks,
+                    };
+                    println!("Adding {} chapter marks...", marks.len());
+                    let chapters = add_chapters(&SystemRunner, Path::new(output), &marks)?;
+                    println!(
+                        "Wrote {} chapters, listed in {}",
+                        chapters.len(),
+                        chapters_path(Path::new(output)).display()
+                    );
+                }
+
+                if *log_events || *annotate_after {
+                    let log = event_log_path(Path::new(output));
+                    events::write_event_log(&log, &events)?;
+                    println!("Logged {} events to {}", events.len(), log.display());
+
+                    if *annotate_after && has_annotations(&events) {
+                        let annotated = annotated_path(Path::new(output));
+                        println!("Annotating to {}...", annotated.display());
+                        annotate(&SystemRunner, Path::new(output), &annotated, &events, None)?;
+                    }
+                }
+
+                if *condense_after {
+                    let condensed = condensed_path(Path::new(output));
+                    println!("Condensing idle stretches to {}...", condensed.display());
+                    let report = condense(
+                        &SystemRunner,
+                        Path::new(output),
+                        &condensed,
+                        &condense_options.options(),
+                        None,
+                    )?;
+                    println!("{}", report);
                 }
             }
-            Commands::ConvertToGif { input, output } => {
+            Commands::ConvertToGif {
+                input,
+                output,
+                fps,
+                width,
+                srt,
+                captions,
+                watermark,
+                print_command,
+            } => {
                 // First, verify that the input file exists and is a valid video
                 if !std::path::Path::new(input).exists() {
                     return Err(format!("Input file does not exist: {}", input).into());
                 }
 
-                if !verify_video_file(input) {
-                    return Err(format!("Input file is not a valid video file: {}", input).into());
+                let options = GifOptions {
+                    fps: *fps,
+                    width: *width,
+                    watermark: export_watermark(watermark)?,
+                    captions: srt.as_ref().map(|srt| Captions {
+                        srt: srt.into(),
+                        style: captions.style(),
+                    }),
+                };
+                if let Some(captions) = &options.captions {
+                    if !captions.srt.exists() {
+                        return Err(format!(
+                            "Subtitle file does not exist: {}",
+                            captions.srt.display()
+                        )
+                        .into());
+                    }
+                }
+                if *print_command {
+                    println!(
+                        "{}",
+                        gif_command(input, output, &options).to_shell_string()?
+                    );
+                    return Ok(());
+                }
+
+                if let Err(e) = verify_video_file(&SystemRunner, input) {
+                    return Err(
+                        format!("Input file is not a valid video file: {} ({})", input, e).into(),
+                    );
                 }
 
                 println!("Converting {} to GIF {}...", input, output);
-                convert_to_gif(input, output)?;
+                convert_to_gif(&SystemRunner, input, output, &options)?;
                 println!("Conversion complete!");
             }
+            Commands::Thumbnail {
+                input,
+                output,
+                at,
+                grid,
+                width,
+                print_command,
+            } => {
+                let input = Path::new(input);
+                if !input.exists() {
+                    return Err(format!("Input file does not exist: {}", input.display()).into());
+                }
+
+                let suffix = if grid.is_some() { "_sheet" } else { "" };
+                let output = match output {
+                    Some(output) => output.into(),
+                    None => input.with_file_name(format!(
+                        "{}{}.png",
+                        input.file_stem().unwrap_or_default().to_string_lossy(),
+                        suffix
+                    )),
+                };
+
+                if *print_command {
+                    let command = match grid {
+                        Some((columns, rows)) => contact_sheet_command(
+                            &SystemRunner,
+                            input,
+                            &output,
+                            *columns,
+                            *rows,
+                            *width,
+                        )?,
+                        None => poster_command(&SystemRunner, input, &output, *at, *width)?,
+                    };
+                    println!("{}", command.to_shell_string()?);
+                    return Ok(());
+                }
+
+                match grid {
+                    Some((columns, rows)) => {
+                        println!(
+                            "Creating {}x{} contact sheet {}...",
+                            columns,
+                            rows,
+                            output.display()
+                        );
+                        contact_sheet(&SystemRunner, input, &output, *columns, *rows, *width)?;
+                    }
+                    None => {
+                        println!("Extracting poster frame to {}...", output.display());
+                        extract_poster(&SystemRunner, input, &output, *at, *width)?;
+                    }
+                }
+                println!("Done!");
+            }
+            Commands::Trim {
+                input,
+                from,
+                to,
+                output,
+                reencode,
+                watermark,
+                print_command,
+            } => {
+                let input = Path::new(input);
+                if !input.exists() {
+                    return Err(format!("Input file does not exist: {}", input.display()).into());
+                }
+
+                let output = match output {
+                    Some(output) => output.into(),
+                    None => trimmed_path(input),
+                };
+                let (to, mut mode) = plan_trim(&SystemRunner, input, *from, *to)?;
+                let watermark = export_watermark(watermark)?;
+                // Drawing the watermark needs decoded frames
+                if *reencode || watermark.is_some() {
+                    mode = TrimMode::Reencode;
+                }
+
+                if *print_command {
+                    let command = trim_command(input, &output, *from, to, mode, watermark.as_ref());
+                    println!("{}", command.to_shell_string()?);
+                    return Ok(());
+                }
+
+                println!(
+                    "Trimming {} to {} with {}...",
+                    input.display(),
+                    output.display(),
+                    mode.label()
+                );
+                trim(
+                    &SystemRunner,
+                    input,
+                    &output,
+                    *from,
+                    to,
+                    mode,
+                    watermark.as_ref(),
+                )?;
+                println!("Done!");
+            }
+            Commands::Crop {
+                input,
+                rect,
+                scale,
+                output,
+                watermark,
+                print_command,
+            } => {
+                let input = Path::new(input);
+                if !input.exists() {
+                    return Err(format!("Input file does not exist: {}", input.display()).into());
+                }
+
+                let output = match output {
+                    Some(output) => output.into(),
+                    None => cropped_path(input),
+                };
+                let watermark = export_watermark(watermark)?;
+
+                if *print_command {
+                    let command = crop_command(input, &output, *rect, *scale, watermark.as_ref());
+                    println!("{}", command.to_shell_string()?);
+                    return Ok(());
+                }
+
+                println!("Cropping {} to {}...", input.display(), output.display());
+                crop(
+                    &SystemRunner,
+                    input,
+                    &output,
+                    *rect,
+                    *scale,
+                    watermark.as_ref(),
+                )?;
+                println!("Done!");
+            }
+            Commands::Redact {
+                input,
+                spec,
+                output,
+                watermark,
+                print_command,
+            } => {
+                let input = Path::new(input);
+                if !input.exists() {
+                    return Err(format!("Input file does not exist: {}", input.display()).into());
+                }
+                let spec = RedactSpec::load(Path::new(spec))?;
+
+                let output = match output {
+                    Some(output) => output.into(),
+                    None => redacted_path(input),
+                };
+                let watermark = export_watermark(watermark)?;
+
+                if *print_command {
+                    let command = redact_command(input, &output, &spec, watermark.as_ref())?;
+                    println!("{}", command.to_shell_string()?);
+                    return Ok(());
+                }
+
+                println!(
+                    "Redacting {} regions of {} to {}...",
+                    spec.regions.len(),
+                    input.display(),
+                    output.display()
+                );
+                redact(&SystemRunner, input, &output, &spec, watermark.as_ref())?;
+                println!("Done!");
+            }
+            Commands::Subtitle {
+                input,
+                srt,
+                burn,
+                output,
+                captions,
+                print_command,
+            } => {
+                let input = Path::new(input);
+                if !input.exists() {
+                    return Err(format!("Input file does not exist: {}", input.display()).into());
+                }
+
+                let output = match output {
+                    Some(output) => output.into(),
+                    None => subtitled_path(input),
+                };
+                let style = captions.style();
+                let burn = burn.then_some(&style);
+
+                if *print_command {
+                    let command = subtitle_command(input, &output, Path::new(srt), burn)?;
+                    println!("{}", command.to_shell_string()?);
+                    return Ok(());
+                }
+
+                println!(
+                    "{} {} into {}...",
+                    if burn.is_some() {
+                        "Burning captions from"
+                    } else {
+                        "Adding a subtitle track from"
+                    },
+                    srt,
+                    output.display()
+                );
+                add_subtitles(&SystemRunner, input, &output, Path::new(srt), burn)?;
+                println!("Done!");
+            }
+            Commands::Condense {
+                input,
+                output,
+                options,
+                watermark,
+            } => {
+                let input = Path::new(input);
+                if !input.exists() {
+                    return Err(format!("Input file does not exist: {}", input.display()).into());
+                }
+
+                let output = match output {
+                    Some(output) => output.into(),
+                    None => condensed_path(input),
+                };
+                let watermark = export_watermark(watermark)?;
+
+                println!("Looking for idle stretches in {}...", input.display());
+                let report = condense(
+                    &SystemRunner,
+                    input,
+                    &output,
+                    &options.options(),
+                    watermark.as_ref(),
+                )?;
+                if report.stretches == 0 {
+                    println!("No idle stretches to condense, nothing written.");
+                } else {
+                    println!("{}, saved to {}", report, output.display());
+                }
+            }
+            Commands::Concat {
+                inputs,
+                output,
+                crossfade,
+                watermark,
+            } => {
+                let inputs: Vec<PathBuf> = inputs.iter().map(PathBuf::from).collect();
+                if let Some(missing) = inputs.iter().find(|input| !input.exists()) {
+                    return Err(format!("Input file does not exist: {}", missing.display()).into());
+                }
+
+                let watermark = export_watermark(watermark)?;
+
+                println!("Concatenating {} recordings to {}...", inputs.len(), output);
+                let plan = concat(
+                    &SystemRunner,
+                    &inputs,
+                    Path::new(output),
+                    *crossfade,
+                    watermark.as_ref(),
+                )?;
+                println!("Done! ({})", plan.label());
+            }
+            Commands::Screenshot {
+                output,
+                profile,
+                source,
+                region,
+                window,
+                monitor,
+                cursor,
+                clipboard,
+                print_command,
+            } => {
+                let config = Config::load()?;
+                let profile_name = profile.clone().or_else(|| config.default_profile.clone());
+                let profile = config.profile(profile_name.as_deref())?;
+                let mut settings = profile.record_settings();
+                if let Some(source) = source {
+                    settings.source = source.clone();
+                }
+                if let Some(cursor) = cursor {
+                    settings.cursor = *cursor;
+                }
+
+                let area = match (region, window, monitor) {
+                    (Some(region), _, _) => ScreenshotArea::Region(*region),
+                    (_, Some(window), _) if window == "active" => ScreenshotArea::Window(None),
+                    (_, Some(window), _) => ScreenshotArea::Window(Some(window.clone())),
+                    (_, _, Some(monitor)) => ScreenshotArea::Monitor(monitor.clone()),
+                    _ => ScreenshotArea::Full,
+                };
+
+                let output = match output {
+                    Some(output) => output.into(),
+                    None => {
+                        let (dir, _) = config.output_target(&profile);
+                        std::fs::create_dir_all(&dir)?;
+
+                        let context = TemplateContext {
+                            profile: profile_name,
+                            source: settings.source.clone(),
+                        };
+                        resolve_output_path(&dir, SCREENSHOT_TEMPLATE, &context, config.overwrite)?
+                    }
+                };
+
+                if *print_command {
+                    let geometry = area.geometry(&SystemRunner)?;
+                    let command =
+                        screenshot_command(Platform::current(), &settings, geometry, &output)?;
+                    println!("{}", command.to_shell_string()?);
+                    return Ok(());
+                }
+
+                take_screenshot(&SystemRunner, &settings, &area, &output)?;
+                println!("Screenshot saved to {}", output.display());
+
+                if *clipboard {
+                    copy_to_clipboard(&SystemRunner, &output)?;
+                    println!("Copied to the clipboard.");
+                }
+            }
+            Commands::Annotate {
+                input,
+                events,
+                output,
+                watermark,
+                print_command,
+            } => {
+                let input = Path::new(input);
+                if !input.exists() {
+                    return Err(format!("Input file does not exist: {}", input.display()).into());
+                }
+
+                let log = match events {
+                    Some(events) => events.into(),
+                    None => event_log_path(input),
+                };
+                let output = match output {
+                    Some(output) => output.into(),
+                    None => annotated_path(input),
+                };
+                let events = read_event_log(&log)?;
+                let watermark = export_watermark(watermark)?;
+
+                if *print_command {
+                    let command = annotate_command(input, &output, &events, watermark.as_ref())?;
+                    println!("{}", command.to_shell_string()?);
+                    return Ok(());
+                }
+
+                println!(
+                    "Annotating {} with {} events to {}...",
+                    input.display(),
+                    events.len(),
+                    output.display()
+                );
+                annotate(&SystemRunner, input, &output, &events, watermark.as_ref())?;
+                println!("Done!");
+            }
+            Commands::CountdownOverlay { seconds } => {
+                show_countdown_overlay(*seconds)?;
+            }
+            Commands::Info { input, json } => {
+                let info = probe(&SystemRunner, Path::new(input))?;
+                // A broken sidecar shouldn't hide what ffprobe found. The warning goes
+                // to stderr to keep --json output parseable
+                let recording = read_sidecar(Path::new(input)).unwrap_or_else(|e| {
+                    eprintln!("Warning: {}", e);
+                    None
+                });
+                if *json {
+                    let mut json = serde_json::to_value(&info)?;
+                    if let (Some(object), Some(recording)) = (json.as_object_mut(), recording) {
+                        object.insert("recording".to_string(), serde_json::to_value(recording)?);
+                    }
+                    println!("{}", serde_json::to_string_pretty(&json)?);
+                } else {
+                    print!("{}", info);
+                    if let Some(recording) = recording {
+                        println!();
+                        print!("{}", recording);
+                    }
+                }
+            }
             Commands::Test { output } => {
                 println!("Running test recording to {}...", output);
-                test_recording(output)?;
+                test_recording(&SystemRunner, output)?;
             }
         }
     } else {
@@ -0,0 +1,284 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::runner::CommandRunner;
+
+/// What ffprobe knows about a media file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MediaInfo {
+    pub path: PathBuf,
+    /// Container format, e.g. "mov,mp4,m4a,3gp,3g2,mj2"
+    pub container: String,
+    pub duration_secs: f64,
+    pub size_bytes: Option<u64>,
+    /// Overall bitrate in bits per second
+    pub bitrate: Option<u64>,
+    pub streams: Vec<StreamInfo>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamKind {
+    Video,
+    Audio,
+    Subtitle,
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamInfo {
+    pub index: u32,
+    pub kind: StreamKind,
+    pub codec: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fps: Option<f64>,
+    pub pixel_format: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    pub bitrate: Option<u64>,
+}
+
+impl MediaInfo {
+    pub fn video_stream(&self) -> Option<&StreamInfo> {
+        self.streams.iter().find(|s| s.kind == StreamKind::Video)
+    }
+
+    /// Width and height of the first video stream.
+    pub fn resolution(&self) -> Option<(u32, u32)> {
+        let video = self.video_stream()?;
+        Some((video.width?, video.height?))
+    }
+}
+
+impl fmt::Display for MediaInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<12}{}", "File", self.path.display())?;
+        writeln!(f, "{:<12}{}", "Container", self.container)?;
+        writeln!(f, "{:<12}{:.3}s", "Duration", self.duration_secs)?;
+        if let Some(size) = self.size_bytes {
+            writeln!(f, "{:<12}{} bytes", "Size", size)?;
+        }
+        if let Some(bitrate) = self.bitrate {
+            writeln!(f, "{:<12}{} kb/s", "Bitrate", bitrate / 1000)?;
+        }
+
+        for stream in &self.streams {
+            writeln!(f)?;
+            writeln!(f, "Stream #{} ({:?})", stream.index, stream.kind)?;
+            writeln!(f, "  {:<14}{}", "Codec", stream.codec)?;
+            if let (Some(width), Some(height)) = (stream.width, stream.height) {
+                writeln!(f, "  {:<14}{}x{}", "Resolution", width, height)?;
+            }
+            if let Some(fps) = stream.fps {
+                writeln!(f, "  {:<14}{:.2}", "Frame rate", fps)?;
+            }
+            if let Some(pixel_format) = &stream.pixel_format {
+                writeln!(f, "  {:<14}{}", "Pixel format", pixel_format)?;
+            }
+            if let Some(sample_rate) = stream.sample_rate {
+                writeln!(f, "  {:<14}{} Hz", "Sample rate", sample_rate)?;
+            }
+            if let Some(channels) = stream.channels {
+                writeln!(f, "  {:<14}{}", "Channels", channels)?;
+            }
+            if let Some(bitrate) = stream.bitrate {
+                writeln!(f, "  {:<14}{} kb/s", "Bitrate", bitrate / 1000)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// The subset of `ffprobe -print_format json -show_format -show_streams` we read.
+// ffprobe prints most numbers as strings, so they are parsed afterwards.
+#[derive(Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    format: ProbeFormat,
+}
+
+#[derive(Deserialize)]
+struct ProbeFormat {
+    format_name: String,
+    duration: Option<String>,
+    size: Option<String>,
+    bit_rate: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    index: u32,
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    avg_frame_rate: Option<String>,
+    r_frame_rate: Option<String>,
+    pix_fmt: Option<String>,
+    sample_rate: Option<String>,
+    channels: Option<u32>,
+    bit_rate: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+/// Run ffprobe on `path` and collect its format and stream information.
+pub fn probe(
+    runner: &dyn CommandRunner,
+    path: &Path,
+) -> Result<MediaInfo, Box<dyn std::error::Error>> {
+    let args = [
+        "-v",
+        "error",
+        "-print_format",
+        "json",
+        "-show_format",
+        "-show_streams",
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .chain([path.to_string_lossy().to_string()])
+    .collect::<Vec<_>>();
+
+    let output = runner
+        .output("ffprobe", &args)
+        .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+
+    if !output.success {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "{} does not appear to be a valid media file: {}",
+            path.display(),
+            error.trim()
+        )
+        .into());
+    }
+
+    parse_probe_output(path, &output.stdout)
+}
+
+fn parse_probe_output(path: &Path, json: &[u8]) -> Result<MediaInfo, Box<dyn std::error::Error>> {
+    let raw: ProbeOutput =
+        serde_json::from_slice(json).map_err(|e| format!("Unexpected ffprobe output: {}", e))?;
+
+    let streams = raw
+        .streams
+        .into_iter()
+        .map(|stream| {
+            let kind = match stream.codec_type.as_deref() {
+                Some("video") => StreamKind::Video,
+                Some("audio") => StreamKind::Audio,
+                Some("subtitle") => StreamKind::Subtitle,
+                _ => StreamKind::Other,
+            };
+
+            // avg_frame_rate is "0/0" for some containers, so fall back to r_frame_rate
+            let fps = [&stream.avg_frame_rate, &stream.r_frame_rate]
+                .into_iter()
+                .flatten()
+                .find_map(|rate| parse_rate(rate))
+                .filter(|_| kind == StreamKind::Video);
+
+            StreamInfo {
+                index: stream.index,
+                kind,
+                codec: stream.codec_name.unwrap_or_else(|| "unknown".to_string()),
+                width: stream.width,
+                height: stream.height,
+                fps,
+                pixel_format: stream.pix_fmt,
+                sample_rate: stream.sample_rate.and_then(|r| r.parse().ok()),
+                channels: stream.channels,
+                bitrate: stream
+                    .bit_rate
+                    .or_else(|| stream.tags.get("BPS").cloned())
+                    .and_then(|b| b.parse().ok()),
+            }
+        })
+        .collect();
+
+    Ok(MediaInfo {
+        path: path.to_path_buf(),
+        container: raw.format.format_name,
+        duration_secs: raw
+            .format
+            .duration
+            .and_then(|d| d.parse().ok())
+            .unwrap_or(0.0),
+        size_bytes: raw.format.size.and_then(|s| s.parse().ok()),
+        bitrate: raw.format.bit_rate.and_then(|b| b.parse().ok()),
+        streams,
+    })
+}
+
+// Parse an ffprobe rational such as "30000/1001"
+fn parse_rate(rate: &str) -> Option<f64> {
+    let (num, den) = rate.split_once('/')?;
+    let num: f64 = num.parse().ok()?;
+    let den: f64 = den.parse().ok()?;
+
+    (num > 0.0 && den > 0.0).then(|| num / den)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::ScriptedRunner;
+
+    const PROBE_JSON: &str = r#"{
+        "streams": [
+            {"index": 0, "codec_type": "video", "codec_name": "h264", "width": 1280,
+             "height": 720, "avg_frame_rate": "0/0", "r_frame_rate": "30000/1001",
+             "pix_fmt": "yuv420p", "bit_rate": "850000"},
+            {"index": 1, "codec_type": "audio", "codec_name": "aac", "sample_rate": "48000",
+             "channels": 2, "tags": {"BPS": "128000"}}
+        ],
+        "format": {"format_name": "mov,mp4", "duration": "2.500000", "size": "300000",
+                   "bit_rate": "960000"}
+    }"#;
+
+    #[test]
+    fn probe_parses_ffprobe_json() {
+        let runner = ScriptedRunner::new().respond(true, PROBE_JSON, "");
+        let info = probe(&runner, Path::new("clip.mp4")).unwrap();
+
+        assert_eq!(
+            runner.calls()[0],
+            [
+                "ffprobe",
+                "-v",
+                "error",
+                "-print_format",
+                "json",
+                "-show_format",
+                "-show_streams",
+                "clip.mp4"
+            ]
+        );
+        assert_eq!(info.container, "mov,mp4");
+        assert_eq!(info.duration_secs, 2.5);
+        assert_eq!(info.size_bytes, Some(300_000));
+        assert_eq!(info.resolution(), Some((1280, 720)));
+
+        let video = info.video_stream().unwrap();
+        assert!((video.fps.unwrap() - 29.97).abs() < 0.01);
+        assert_eq!(info.streams[1].kind, StreamKind::Audio);
+        assert_eq!(info.streams[1].bitrate, Some(128_000));
+        assert_eq!(info.streams[1].fps, None);
+    }
+
+    #[test]
+    fn probe_reports_invalid_files() {
+        let runner = ScriptedRunner::new().respond(false, "", "moov atom not found");
+        let error = probe(&runner, Path::new("broken.mp4"))
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("moov atom not found"), "{}", error);
+    }
+}
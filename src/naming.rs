@@ -0,0 +1,200 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::recorder::CaptureSource;
+
+pub const DEFAULT_TEMPLATE: &str = "recording_{date}_{time}.mp4";
+
+/// What to do when the resolved output file already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OverwritePolicy {
+    /// Fail instead of touching the existing file
+    Refuse,
+    /// Append _2, _3, ... until the name is free
+    #[default]
+    Increment,
+    /// Replace the existing file
+    Overwrite,
+}
+
+impl OverwritePolicy {
+    pub const ALL: [OverwritePolicy; 3] = [
+        OverwritePolicy::Refuse,
+        OverwritePolicy::Increment,
+        OverwritePolicy::Overwrite,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            OverwritePolicy::Refuse => "Refuse",
+            OverwritePolicy::Increment => "Auto-increment",
+            OverwritePolicy::Overwrite => "Overwrite",
+        }
+    }
+}
+
+/// Values available to filename template tokens.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateContext {
+    pub profile: Option<String>,
+    pub source: CaptureSource,
+}
+
+/// The directory recordings go to when nothing else is configured.
+pub fn default_output_dir() -> PathBuf {
+    dirs::video_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Expand `{date}`, `{time}`, `{profile}`, `{window_title}`, `{monitor}`,
+/// `{counter}` and `{hostname}` in `template`.
+pub fn render_template(
+    template: &str,
+    context: &TemplateContext,
+    counter: u32,
+) -> Result<String, String> {
+    let now = chrono::Local::now();
+    let mut rendered = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("Unclosed '{{' in filename template '{}'", template))?;
+        let token = &rest[start + 1..start + end];
+
+        let value = match token {
+            "date" => now.format("%Y%m%d").to_string(),
+            "time" => now.format("%H%M%S").to_string(),
+            "profile" => context
+                .profile
+                .clone()
+                .unwrap_or_else(|| "default".to_string()),
+            "window_title" => active_window_title().unwrap_or_else(|| "window".to_string()),
+            "monitor" => match &context.source {
+                CaptureSource::Screen => "screen".to_string(),
+                CaptureSource::Display(input) => input.clone(),
+                CaptureSource::Synthetic => "synthetic".to_string(),
+            },
+            "counter" => counter.to_string(),
+            "hostname" => hostname().unwrap_or_else(|| "localhost".to_string()),
+            other => return Err(format!("Unknown filename template token '{{{}}}'", other)),
+        };
+
+        rendered.push_str(&sanitize(&value));
+        rest = &rest[start + end + 1..];
+    }
+
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+/// Render `template` inside `dir` and apply the overwrite policy.
+pub fn resolve_output_path(
+    dir: &Path,
+    template: &str,
+    context: &TemplateContext,
+    policy: OverwritePolicy,
+) -> Result<PathBuf, String> {
+    // With an explicit {counter} the template picks the first free number itself
+    if template.contains("{counter}") {
+        for counter in 1..10_000 {
+            let path = dir.join(render_template(template, context, counter)?);
+            if !path.exists() {
+                return Ok(path);
+            }
+        }
+        return Err(format!("No free file name for template '{}'", template));
+    }
+
+    let path = dir.join(render_template(template, context, 1)?);
+    apply_overwrite_policy(&path, policy)
+}
+
+/// Check an output path against the overwrite policy, returning the path to write.
+pub fn apply_overwrite_policy(path: &Path, policy: OverwritePolicy) -> Result<PathBuf, String> {
+    if !path.exists() {
+        return Ok(path.to_path_buf());
+    }
+
+    match policy {
+        OverwritePolicy::Overwrite => Ok(path.to_path_buf()),
+        OverwritePolicy::Refuse => Err(format!(
+            "Output file already exists: {} (use --overwrite to change this)",
+            path.display()
+        )),
+        OverwritePolicy::Increment => {
+            let stem = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let extension = path
+                .extension()
+                .map(|e| format!(".{}", e.to_string_lossy()))
+                .unwrap_or_default();
+
+            for n in 2..10_000 {
+                let candidate = path.with_file_name(format!("{}_{}{}", stem, n, extension));
+                if !candidate.exists() {
+                    return Ok(candidate);
+                }
+            }
+            Err(format!("No free file name for {}", path.display()))
+        }
+    }
+}
+
+// Keep token values from introducing path separators or shell-hostile characters
+fn sanitize(value: &str) -> String {
+    value
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '-',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+fn active_window_title() -> Option<String> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+
+    let output = Command::new("xdotool")
+        .args(["getactivewindow", "getwindowname"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let title = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!title.is_empty()).then_some(title)
+}
+
+pub fn hostname() -> Option<String> {
+    #[cfg(unix)]
+    {
+        let mut buffer = [0u8; 256];
+        let result =
+            unsafe { libc::gethostname(buffer.as_mut_ptr() as *mut libc::c_char, buffer.len()) };
+        if result != 0 {
+            return None;
+        }
+        let len = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+        Some(String::from_utf8_lossy(&buffer[..len]).to_string())
+    }
+
+    #[cfg(not(unix))]
+    {
+        std::env::var("COMPUTERNAME").ok()
+    }
+}
@@ -0,0 +1,84 @@
+use eframe::egui::{self, Align2, Color32, FontId};
+use eframe::{run_native, App, Frame, NativeOptions};
+use std::io;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+const OVERLAY_SIZE: f32 = 240.0;
+
+/// A borderless, click-through window showing the seconds left before recording.
+struct CountdownOverlay {
+    deadline: Instant,
+}
+
+impl App for CountdownOverlay {
+    fn clear_color(&self, _visuals: &egui::Visuals) -> [f32; 4] {
+        // Only the disc below is visible, the rest of the window is transparent
+        [0.0; 4]
+    }
+
+    fn update(&mut self, ctx: &egui::Context, frame: &mut Frame) {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            frame.close();
+            return;
+        }
+
+        egui::CentralPanel::default()
+            .frame(egui::Frame::none())
+            .show(ctx, |ui| {
+                let rect = ui.max_rect();
+                let painter = ui.painter();
+
+                painter.circle_filled(
+                    rect.center(),
+                    rect.width().min(rect.height()) / 2.0 - 4.0,
+                    Color32::from_black_alpha(190),
+                );
+                painter.text(
+                    rect.center(),
+                    Align2::CENTER_CENTER,
+                    (remaining.as_secs_f32().ceil() as u64).to_string(),
+                    FontId::proportional(OVERLAY_SIZE * 0.55),
+                    Color32::WHITE,
+                );
+            });
+
+        ctx.request_repaint_after(Duration::from_millis(50));
+    }
+}
+
+/// Run the countdown overlay window until `seconds` have passed. Blocks, and has to
+/// run in its own process since only one native window can exist per process.
+pub fn show_countdown_overlay(seconds: u64) -> Result<(), Box<dyn std::error::Error>> {
+    let native_options = NativeOptions {
+        initial_window_size: Some(egui::vec2(OVERLAY_SIZE, OVERLAY_SIZE)),
+        resizable: false,
+        decorated: false,
+        transparent: true,
+        always_on_top: true,
+        centered: true,
+        // Don't steal focus or clicks from the window about to be recorded
+        active: false,
+        mouse_passthrough: true,
+        ..Default::default()
+    };
+
+    let deadline = Instant::now() + Duration::from_secs(seconds);
+    run_native(
+        "rcrdr countdown",
+        native_options,
+        Box::new(move |_cc| Box::new(CountdownOverlay { deadline })),
+    )?;
+
+    Ok(())
+}
+
+/// Start `rcrdr countdown-overlay` as a child process.
+pub fn spawn_countdown_overlay(seconds: u64) -> io::Result<Child> {
+    Command::new(std::env::current_exe()?)
+        .args(["countdown-overlay", &seconds.to_string()])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .spawn()
+}
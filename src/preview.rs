@@ -0,0 +1,197 @@
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::ffmpeg::{ExtraOutput, FfmpegCommand, FilterGraph};
+use crate::recorder::{capture_command, Platform, RecordSettings};
+use crate::runner::{CommandRunner, RunningProcess};
+
+/// Preview frames are letterboxed to this size so every frame has the same length.
+pub const PREVIEW_WIDTH: usize = 320;
+pub const PREVIEW_HEIGHT: usize = 180;
+/// Frames per second of the preview, kept low to stay cheap next to the recording
+pub const PREVIEW_FPS: u32 = 2;
+
+const FRAME_BYTES: usize = PREVIEW_WIDTH * PREVIEW_HEIGHT * 4;
+
+/// The newest preview frame as RGBA bytes, shared between the thread reading
+/// ffmpeg's output and the window drawing it.
+#[derive(Clone, Default)]
+pub struct PreviewFrames {
+    latest: Arc<Mutex<Option<Vec<u8>>>>,
+}
+
+impl PreviewFrames {
+    /// The frame that arrived since the last call, if any.
+    pub fn take(&self) -> Option<Vec<u8>> {
+        self.latest.lock().ok()?.take()
+    }
+
+    // Keep reading frames from `stdout` until ffmpeg closes it
+    fn read_from(&self, mut stdout: Box<dyn Read + Send>) {
+        let latest = self.latest.clone();
+        thread::spawn(move || {
+            let mut frame = vec![0; FRAME_BYTES];
+            while stdout.read_exact(&mut frame).is_ok() {
+                if let Ok(mut latest) = latest.lock() {
+                    *latest = Some(frame.clone());
+                }
+            }
+        });
+    }
+
+    /// Feed this preview from `process`, which must write `preview_output()` to stdout.
+    pub fn attach(&self, process: &mut dyn RunningProcess) {
+        if let Some(stdout) = process.take_stdout() {
+            self.read_from(stdout);
+        }
+    }
+}
+
+// Scale into the preview size and pad the rest black
+fn preview_filters() -> [String; 3] {
+    [
+        format!("fps={}", PREVIEW_FPS),
+        format!(
+            "scale={}:{}:force_original_aspect_ratio=decrease",
+            PREVIEW_WIDTH, PREVIEW_HEIGHT
+        ),
+        format!(
+            "pad={}:{}:(ow-iw)/2:(oh-ih)/2",
+            PREVIEW_WIDTH, PREVIEW_HEIGHT
+        ),
+    ]
+}
+
+/// A second output for a recording command that writes preview frames to stdout.
+pub fn preview_output() -> ExtraOutput {
+    let [fps, scale, pad] = preview_filters();
+    ExtraOutput::new("pipe:1")
+        .map("0:v")
+        .video_filter(&fps)
+        .video_filter(&scale)
+        .video_filter(&pad)
+        .option("f", "rawvideo")
+        .option("pix_fmt", "rgba")
+}
+
+/// The ffmpeg command that only previews the capture source, before recording.
+pub fn preview_command(
+    platform: Platform,
+    settings: &RecordSettings,
+) -> Result<FfmpegCommand, Box<dyn std::error::Error>> {
+    // Grabbing only as many frames as the preview shows keeps the capture cheap.
+    // The webcam isn't opened, the recording may need it.
+    let settings = RecordSettings {
+        fps: PREVIEW_FPS,
+        audio: false,
+        webcam: None,
+        timelapse: None,
+        watermark: None,
+        ..settings.clone()
+    };
+
+    Ok(capture_command(platform, &settings)?
+        .filter(FilterGraph::Video(preview_filters().to_vec()))
+        .output_option("f", "rawvideo")
+        .output_option("pix_fmt", "rgba")
+        .output("pipe:1"))
+}
+
+/// A running preview of the capture source. Stops when dropped.
+pub struct SourcePreview {
+    process: Box<dyn RunningProcess>,
+    pub frames: PreviewFrames,
+}
+
+impl SourcePreview {
+    pub fn start(
+        runner: &dyn CommandRunner,
+        settings: &RecordSettings,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let args = preview_command(Platform::current(), settings)?.args()?;
+        let mut process = runner.spawn("ffmpeg", &args)?;
+
+        let frames = PreviewFrames::default();
+        frames.attach(process.as_mut());
+
+        Ok(Self { process, frames })
+    }
+
+    /// Whether ffmpeg stopped, e.g. because the source could not be opened.
+    pub fn has_exited(&mut self) -> bool {
+        !matches!(self.process.try_wait(), Ok(None))
+    }
+}
+
+impl Drop for SourcePreview {
+    fn drop(&mut self) {
+        self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recorder::CaptureSource;
+    use crate::runner::ScriptedRunner;
+
+    #[test]
+    fn preview_command_only_writes_raw_frames_to_stdout() {
+        let settings = RecordSettings {
+            source: CaptureSource::Display(":1".to_string()),
+            fps: 60,
+            audio: true,
+            ..RecordSettings::default()
+        };
+
+        assert_eq!(
+            preview_command(Platform::Linux, &settings)
+                .unwrap()
+                .args()
+                .unwrap(),
+            [
+                "-f",
+                "x11grab",
+                "-framerate",
+                "2",
+                "-i",
+                ":1",
+                "-vf",
+                "fps=2,scale=320:180:force_original_aspect_ratio=decrease,pad=320:180:(ow-iw)/2:(oh-ih)/2",
+                "-f",
+                "rawvideo",
+                "-pix_fmt",
+                "rgba",
+                "pipe:1",
+            ]
+        );
+    }
+
+    #[test]
+    fn frames_are_read_whole_and_only_the_latest_is_kept() {
+        // Two whole frames, then a partial one that gets dropped
+        let stdout = "\x01".repeat(FRAME_BYTES) + &"\x02".repeat(FRAME_BYTES) + &"\x03".repeat(100);
+
+        let runner = ScriptedRunner::new().respond(true, &stdout, "");
+        let mut process = runner.spawn("ffmpeg", &[]).unwrap();
+        let frames = PreviewFrames::default();
+        frames.attach(process.as_mut());
+
+        let mut latest = None;
+        for _ in 0..100 {
+            if let Some(frame) = frames.take() {
+                latest = Some(frame);
+                if latest.as_ref().unwrap()[0] == 2 {
+                    break;
+                }
+            }
+            thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        let latest = latest.unwrap();
+        assert_eq!(latest.len(), FRAME_BYTES);
+        assert_eq!(latest[0], 2);
+    }
+}
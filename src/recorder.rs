@@ -1,170 +1,638 @@
+use crate::ffmpeg::{Encoder, ExtraOutput, FfmpegCommand, FilterGraph, Input};
+use crate::media::{probe, MediaInfo};
+use crate::preview::{preview_output, PreviewFrames};
+use crate::runner::CommandRunner;
+use crate::subtitle::Captions;
+use crate::watermark::{apply_watermark, Watermark};
+use crate::webcam::{webcam_path, WebcamOverlay};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::fs;
 use std::io::{self, Read, Write};
-use std::process::{Command, Stdio};
+use std::path::Path;
+use std::process::Command;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
-use std::thread;
-use std::time::Duration;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Where the video frames come from.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum CaptureSource {
+    /// The primary screen of the current platform
+    #[default]
+    Screen,
+    /// A raw ffmpeg input for the platform grabber, e.g. ":1.0" for x11grab
+    Display(String),
+    /// ffmpeg's lavfi test pattern and sine tone, for headless testing
+    Synthetic,
+}
 
-pub fn is_command_available(command: &str) -> bool {
-    let output = if cfg!(target_os = "windows") {
-        Command::new("where").arg(command).output()
-    } else {
-        Command::new("which").arg(command).output()
+impl fmt::Display for CaptureSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureSource::Screen => write!(f, "screen"),
+            CaptureSource::Display(input) => write!(f, "display:{}", input),
+            CaptureSource::Synthetic => write!(f, "synthetic"),
+        }
+    }
+}
+
+impl std::str::FromStr for CaptureSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "screen" | "" => Ok(CaptureSource::Screen),
+            "synthetic" => Ok(CaptureSource::Synthetic),
+            other => match other.strip_prefix("display:") {
+                Some(input) if !input.is_empty() => Ok(CaptureSource::Display(input.to_string())),
+                _ => Err(format!(
+                    "Unknown capture source '{}'. Expected 'screen', 'synthetic' or 'display:<input>'.",
+                    other
+                )),
+            },
+        }
+    }
+}
+
+impl TryFrom<String> for CaptureSource {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<CaptureSource> for String {
+    fn from(source: CaptureSource) -> Self {
+        source.to_string()
+    }
+}
+
+/// Video encoder used for recordings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoCodec {
+    #[default]
+    H264,
+    H265,
+    Vp9,
+}
+
+impl VideoCodec {
+    pub const ALL: [VideoCodec; 3] = [VideoCodec::H264, VideoCodec::H265, VideoCodec::Vp9];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "H.264",
+            VideoCodec::H265 => "H.265",
+            VideoCodec::Vp9 => "VP9",
+        }
+    }
+
+    /// The codec to re-encode into when writing `path`: VP9 for WebM, H.264 otherwise.
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("webm") => VideoCodec::Vp9,
+            _ => VideoCodec::H264,
+        }
+    }
+
+    /// The video encoder and its quality settings for this codec.
+    pub fn encoder(&self) -> Encoder {
+        match self {
+            VideoCodec::H264 => Encoder::new("libx264")
+                .option("pix_fmt", "yuv420p")
+                .option("preset", "medium")
+                .option("crf", 23),
+            VideoCodec::H265 => Encoder::new("libx265")
+                .option("pix_fmt", "yuv420p")
+                .option("preset", "medium")
+                .option("crf", 28),
+            VideoCodec::Vp9 => Encoder::new("libvpx-vp9")
+                .option("pix_fmt", "yuv420p")
+                .option("crf", 32)
+                .option("b:v", 0),
+        }
+    }
+
+    /// The audio encoder that goes with this codec: Opus next to VP9, which WebM
+    /// needs, AAC otherwise.
+    pub fn audio_encoder(&self) -> Encoder {
+        match self {
+            VideoCodec::Vp9 => Encoder::new("libopus").option("b:a", "128k"),
+            VideoCodec::H264 | VideoCodec::H265 => Encoder::new("aac").option("b:a", "128k"),
+        }
+    }
+}
+
+/// The audio encoder for an export of `input` to `output`: a copy while the
+/// container stays the same, otherwise one the output container takes.
+pub fn export_audio_encoder(input: &Path, output: &Path) -> Encoder {
+    let extension = |path: &Path| {
+        path.extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
     };
+    match extension(input) == extension(output) {
+        true => Encoder::new("copy"),
+        false => VideoCodec::for_path(output).audio_encoder(),
+    }
+}
 
-    match output {
-        Ok(o) => o.status.success(),
-        Err(_) => false,
+/// What happens to the mouse pointer in recordings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum CursorMode {
+    #[default]
+    Show,
+    Hide,
+    /// Show it with a translucent halo, drawn in after recording
+    Highlight,
+}
+
+impl CursorMode {
+    pub const ALL: [CursorMode; 3] = [CursorMode::Show, CursorMode::Hide, CursorMode::Highlight];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CursorMode::Show => "Show",
+            CursorMode::Hide => "Hide",
+            CursorMode::Highlight => "Highlight",
+        }
+    }
+
+    /// Whether the screen grabber draws the pointer.
+    pub fn draws_pointer(&self) -> bool {
+        *self != CursorMode::Hide
     }
 }
 
-pub fn verify_video_file(file_path: &str) -> bool {
-    // Check if file exists and is not empty
-    match fs::metadata(file_path) {
-        Ok(metadata) => {
-            if metadata.len() == 0 {
-                println!("Warning: The video file is empty.");
-                return false;
-            }
+/// Everything that decides how a recording is captured and encoded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordSettings {
+    pub source: CaptureSource,
+    pub codec: VideoCodec,
+    pub fps: u32,
+    pub audio: bool,
+    pub cursor: CursorMode,
+    pub webcam: Option<WebcamOverlay>,
+    /// Seconds between captured frames, played back at `TIMELAPSE_FPS`
+    pub timelapse: Option<f64>,
+    pub watermark: Option<Watermark>,
+}
+
+impl Default for RecordSettings {
+    fn default() -> Self {
+        Self {
+            source: CaptureSource::Screen,
+            codec: VideoCodec::H264,
+            fps: 30,
+            audio: false,
+            cursor: CursorMode::Show,
+            webcam: None,
+            timelapse: None,
+            watermark: None,
         }
-        Err(_) => {
-            println!("Warning: Could not access the video file.");
-            return false;
+    }
+}
+
+/// Frame rate timelapse recordings play back at.
+pub const TIMELAPSE_FPS: u32 = 30;
+
+/// How long `elapsed` of capturing a frame every `interval` seconds plays back for.
+pub fn timelapse_length(elapsed: Duration, interval: f64) -> Duration {
+    Duration::from_secs_f64(elapsed.as_secs_f64() / interval / TIMELAPSE_FPS as f64)
+}
+
+/// The platform decides which grabber ffmpeg captures the screen with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Linux,
+    MacOs,
+    Windows,
+}
+
+impl Platform {
+    pub fn current() -> Self {
+        if cfg!(target_os = "windows") {
+            Platform::Windows
+        } else if cfg!(target_os = "macos") {
+            Platform::MacOs
+        } else {
+            Platform::Linux
         }
     }
+}
 
-    // Use ffprobe to verify the file is a valid video container
-    let output = Command::new("ffprobe")
-        .args([
-            "-v",
-            "error",
-            "-show_entries",
-            "format=duration",
-            "-of",
-            "default=noprint_wrappers=1:nokey=1",
-            file_path,
-        ])
-        .output();
+/// Frame rate, size and watermark of converted GIFs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GifOptions {
+    pub fps: u32,
+    pub width: u32,
+    pub watermark: Option<Watermark>,
+    /// Captions burnt in before scaling, styled as for videos
+    pub captions: Option<Captions>,
+}
 
-    match output {
-        Ok(output) => {
-            if !output.status.success() {
-                println!("Warning: File does not appear to be a valid video file.");
-                return false;
-            }
+impl Default for GifOptions {
+    fn default() -> Self {
+        Self {
+            fps: 10,
+            width: 640,
+            watermark: None,
+            captions: None,
+        }
+    }
+}
 
-            // Try to parse the duration
-            let duration_str = String::from_utf8_lossy(&output.stdout);
-            match duration_str.trim().parse::<f64>() {
-                Ok(duration) => {
-                    if duration <= 0.0 {
-                        println!("Warning: Video file has zero duration.");
-                        return false;
-                    }
-                }
-                Err(_) => {
-                    println!("Warning: Could not determine video duration.");
-                    return false;
-                }
-            }
+// The X display to grab when recording the screen on Linux
+fn linux_display() -> String {
+    std::env::var("DISPLAY").unwrap_or_else(|_| ":0.0".to_string())
+}
 
-            true
+// The platform screen grabber, capturing `framerate` frames per second
+fn screen_input(
+    platform: Platform,
+    display: Option<String>,
+    framerate: &str,
+    audio: bool,
+    cursor: CursorMode,
+) -> Input {
+    let input = match platform {
+        Platform::Windows => Input::device("gdigrab", &display.unwrap_or_else(|| "desktop".into())),
+        Platform::Linux => Input::device("x11grab", &display.unwrap_or_else(linux_display)),
+        Platform::MacOs => {
+            // avfoundation takes "<video>:<audio>" device indices in a single input
+            let video = display.unwrap_or_else(|| "1".to_string());
+            let audio = if audio { "0" } else { "none" };
+            Input::device("avfoundation", &format!("{}:{}", video, audio))
         }
-        Err(_) => {
-            println!("Warning: Failed to verify video file.");
-            false
+    }
+    .option("framerate", framerate);
+
+    // x11grab and gdigrab draw the pointer by default, avfoundation doesn't
+    match (platform, cursor.draws_pointer()) {
+        (Platform::MacOs, true) => input.option("capture_cursor", 1),
+        (Platform::Linux | Platform::Windows, false) => input.option("draw_mouse", 0),
+        _ => input,
+    }
+}
+
+// Frames per second to capture, as an ffmpeg rate such as "30" or "1/2"
+fn capture_rate(settings: &RecordSettings) -> String {
+    match settings.timelapse {
+        Some(interval) => format!("1/{}", interval),
+        None => settings.fps.to_string(),
+    }
+}
+
+// Whether audio comes from an input of its own, rather than with the screen
+fn has_separate_audio(platform: Platform, settings: &RecordSettings) -> bool {
+    settings.audio && (settings.source == CaptureSource::Synthetic || platform == Platform::Linux)
+}
+
+// The webcam is the input after the screen and the audio
+fn webcam_input_index(platform: Platform, settings: &RecordSettings) -> usize {
+    1 + has_separate_audio(platform, settings) as usize
+}
+
+/// An ffmpeg command with the inputs of the capture source, and the audio and
+/// webcam inputs if requested, but no encoders or output yet.
+pub fn capture_command(
+    platform: Platform,
+    settings: &RecordSettings,
+) -> Result<FfmpegCommand, Box<dyn std::error::Error>> {
+    if let Some(interval) = settings.timelapse {
+        if interval <= 0.0 {
+            return Err("The timelapse interval must be more than 0 seconds.".into());
+        }
+        if settings.audio {
+            return Err("Timelapse recordings can't have audio.".into());
         }
     }
+
+    let mut command = FfmpegCommand::new();
+
+    // The synthetic source is the same on every platform and needs no display
+    if settings.source == CaptureSource::Synthetic {
+        command = command.input(Input::lavfi(&format!(
+            "testsrc2=size=1280x720:rate={}",
+            capture_rate(settings)
+        )));
+
+        if settings.audio {
+            command = command.input(Input::lavfi("sine=frequency=440:sample_rate=48000"));
+        }
+    } else {
+        if platform == Platform::Windows && settings.audio {
+            return Err("Audio capture is not supported on Windows yet.".into());
+        }
+
+        let display = match &settings.source {
+            CaptureSource::Display(input) => Some(input.clone()),
+            _ => None,
+        };
+
+        let mut screen = screen_input(
+            platform,
+            display,
+            &capture_rate(settings),
+            settings.audio,
+            settings.cursor,
+        );
+        if platform == Platform::MacOs {
+            screen = screen.option("pixel_format", "uyvy422"); // Needed for macOS avfoundation
+        }
+        command = command.input(screen);
+
+        // avfoundation already captured the audio device with the screen
+        if platform == Platform::Linux && settings.audio {
+            command = command.input(Input::device("pulse", "default"));
+        }
+    }
+
+    let separate_audio = has_separate_audio(platform, settings);
+    let mut video = "0:v";
+    // Timelapse frames get consecutive timestamps at the playback rate
+    let timelapse = format!("setpts=N/({}*TB)", TIMELAPSE_FPS);
+    if let Some(webcam) = &settings.webcam {
+        if platform != Platform::Linux {
+            return Err("The webcam overlay is only supported on Linux (V4L2).".into());
+        }
+
+        let mut chains = webcam.filter_chains(webcam_input_index(platform, settings));
+        video = "[v]";
+        if settings.timelapse.is_some() {
+            chains.push(format!("[v]{}[timelapse]", timelapse));
+            video = "[timelapse]";
+        }
+        command = command
+            .input(webcam.input())
+            .filter(FilterGraph::Complex(chains));
+    } else if settings.timelapse.is_some() {
+        command = command.filter(FilterGraph::Video(vec![timelapse]));
+    }
+
+    // With more than one input, pick the streams explicitly
+    if separate_audio || settings.webcam.is_some() {
+        command = command.map(video);
+        if settings.audio {
+            command = command.map(if separate_audio { "1:a" } else { "0:a" });
+        }
+    }
+
+    // Drawn over everything else, the logo is the last input
+    if let Some(watermark) = &settings.watermark {
+        watermark.check()?;
+    }
+    Ok(apply_watermark(command, settings.watermark.as_ref()))
 }
 
-pub fn record_screen_gui(
+/// The ffmpeg command for a screen recording.
+pub fn record_command(
+    platform: Platform,
+    settings: &RecordSettings,
     output: &str,
     duration: u64,
-    fps: u32,
-    running: Arc<AtomicBool>,
-    log_sender: Sender<String>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    log_sender.send("Initializing recording...".to_string())?;
+) -> Result<FfmpegCommand, Box<dyn std::error::Error>> {
+    // The overwrite policy was already applied to the output path
+    let mut command = capture_command(platform, settings)?
+        .overwrite()
+        .video_encoder(settings.codec.encoder());
+
+    if settings.audio {
+        command = command.audio_encoder(settings.codec.audio_encoder());
+    }
 
-    // Set up the FFmpeg command based on the platform
-    let mut cmd = Command::new("ffmpeg");
-    cmd.arg("-y"); // Overwrite output file if it exists
+    match settings.timelapse {
+        Some(interval) => {
+            // The output is shorter than the time spent recording
+            command = command.output_option("r", TIMELAPSE_FPS);
+            if duration > 0 {
+                let length = timelapse_length(Duration::from_secs(duration), interval);
+                command = command.duration(format!("{:.3}", length.as_secs_f64()));
+            }
+        }
+        None if duration > 0 => command = command.duration(duration),
+        None => {}
+    }
+    command = command.output(output);
 
-    #[cfg(target_os = "windows")]
+    if settings
+        .webcam
+        .as_ref()
+        .is_some_and(|webcam| webcam.separate_file)
     {
-        cmd.args([
-            "-f",
-            "gdigrab",
-            "-framerate",
-            &fps.to_string(),
-            "-i",
-            "desktop",
-        ]);
+        let mut camera = ExtraOutput::new(&webcam_path(output))
+            .map(&format!("{}:v", webcam_input_index(platform, settings)))
+            .video_encoder(settings.codec.encoder());
+        if duration > 0 {
+            camera = camera.option("t", duration);
+        }
+        command = command.extra_output(camera);
     }
 
-    #[cfg(target_os = "linux")]
-    {
-        cmd.args([
-            "-f",
-            "x11grab",
-            "-framerate",
-            &fps.to_string(),
-            "-i",
-            ":0.0",
-        ]);
+    Ok(command)
+}
+
+/// The ffmpeg command for the 3 second test recording.
+pub fn test_recording_command(platform: Platform, output: &str) -> FfmpegCommand {
+    let mut screen = screen_input(platform, None, "30", false, CursorMode::Show);
+    if platform == Platform::MacOs {
+        screen = screen.option("pixel_format", "uyvy422"); // Needed for macOS avfoundation
     }
 
-    #[cfg(target_os = "macos")]
-    {
-        cmd.args([
-            "-f",
-            "avfoundation",
-            "-framerate",
-            &fps.to_string(),
-            "-i",
-            "1:none", // Capture screen 1, no audio
-            "-pix_fmt",
-            "uyvy422", // Needed for macOS avfoundation
-        ]);
-    }
-
-    // Common output options
-    cmd.args([
-        "-c:v", "libx264", "-pix_fmt", "yuv420p", "-preset", "medium", "-crf", "23",
-    ]);
+    FfmpegCommand::new()
+        .overwrite() // Overwrite output file if it exists
+        .input(screen)
+        .video_encoder(
+            Encoder::new("libx264")
+                .option("pix_fmt", "yuv420p")
+                .option("preset", "ultrafast") // Use ultrafast for test
+                .option("crf", 28), // Lower quality for test
+        )
+        .duration(3)
+        .output(output)
+}
 
-    if duration > 0 {
-        cmd.args(["-t", &duration.to_string()]);
+/// The ffmpeg command for converting a video to a looping GIF.
+pub fn gif_command(input: &str, output: &str, options: &GifOptions) -> FfmpegCommand {
+    let mut command = FfmpegCommand::new().input(Input::file(input));
+    let mut scale = format!(
+        "fps={},scale={}:-1:flags=lanczos",
+        options.fps, options.width
+    );
+    if let Some(captions) = &options.captions {
+        scale = format!("{},{}", captions.filter(), scale);
     }
 
-    // Add output file
-    cmd.arg(output);
+    // The watermark goes on before the palette is built, so its colors are in it
+    let mut chains = match &options.watermark {
+        Some(watermark) => {
+            if let Some(logo) = watermark.input() {
+                command = command.input(logo);
+            }
+            let mut chains = vec![format!("[0:v]{}[scaled]", scale)];
+            chains.extend(watermark.filter_chains("[scaled]", 1));
+            chains.push("[watermarked]split[s0][s1]".to_string());
+            chains
+        }
+        None => vec![format!("[0:v]{},split[s0][s1]", scale)],
+    };
+    // Build a palette from the video itself for much better colors than the default one
+    chains.push("[s0]palettegen[p]".to_string());
+    chains.push("[s1][p]paletteuse[gif]".to_string());
+
+    command
+        .filter(FilterGraph::Complex(chains))
+        .map("[gif]")
+        .output_option("loop", 0)
+        .output(output)
+}
 
-    // Capture stderr to provide better error messages
-    cmd.stderr(Stdio::piped());
+pub fn is_command_available(command: &str) -> bool {
+    let output = if cfg!(target_os = "windows") {
+        Command::new("where").arg(command).output()
+    } else {
+        Command::new("which").arg(command).output()
+    };
+
+    match output {
+        Ok(o) => o.status.success(),
+        Err(_) => false,
+    }
+}
+
+/// Check that `file_path` is a non-empty video with a positive duration.
+pub fn verify_video_file(
+    runner: &dyn CommandRunner,
+    file_path: &str,
+) -> Result<MediaInfo, Box<dyn std::error::Error>> {
+    // Check if file exists and is not empty
+    match fs::metadata(file_path) {
+        Ok(metadata) if metadata.len() == 0 => return Err("The video file is empty.".into()),
+        Ok(_) => {}
+        Err(_) => return Err("Could not access the video file.".into()),
+    }
+
+    // Use ffprobe to verify the file is a valid video container
+    let info = probe(runner, Path::new(file_path))?;
+
+    if info.video_stream().is_none() {
+        return Err("The file has no video stream.".into());
+    }
+    if info.duration_secs <= 0.0 {
+        return Err("Video file has zero duration.".into());
+    }
+
+    Ok(info)
+}
+
+// Print the avfoundation devices to help diagnose macOS permission and index problems
+fn list_macos_devices(runner: &dyn CommandRunner) -> Result<Vec<String>, std::io::Error> {
+    let output = runner.output(
+        "ffmpeg",
+        &["-f", "avfoundation", "-list_devices", "true", "-i", ""].map(String::from),
+    )?;
+
+    let output_str = String::from_utf8_lossy(&output.stderr);
+    Ok(output_str
+        .lines()
+        .filter(|line| line.contains("AVFoundation") || line.contains("capture"))
+        .map(str::to_string)
+        .collect())
+}
+
+/// How the GUI steers a recording running on another thread.
+#[derive(Clone, Default)]
+pub struct RecordingControl {
+    /// Cleared to stop the recording
+    pub running: Arc<AtomicBool>,
+    /// Set to end the current segment, cleared to start the next one
+    pub paused: Arc<AtomicBool>,
+    /// Where to send preview frames of the capture, if wanted
+    pub preview: Option<PreviewFrames>,
+}
+
+pub fn record_screen_gui(
+    runner: &dyn CommandRunner,
+    output: &str,
+    duration: u64,
+    settings: &RecordSettings,
+    control: RecordingControl,
+    log_sender: Sender<String>,
+    started: Sender<Instant>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let RecordingControl {
+        running,
+        paused,
+        preview,
+    } = control;
+    log_sender.send("Initializing recording...".to_string())?;
+
+    // Preview frames come from a second output of the same ffmpeg process
+    let command =
+        |output: &str, duration: u64| -> Result<Vec<String>, Box<dyn std::error::Error>> {
+            let mut command = record_command(Platform::current(), settings, output, duration)?;
+            if preview.is_some() {
+                let mut frames = preview_output();
+                if duration > 0 {
+                    frames = frames.option("t", duration);
+                }
+                command = command.extra_output(frames);
+            }
+            Ok(command.args()?)
+        };
 
     if duration > 0 {
         // For fixed duration, just run and wait
+        let args = command(output, duration)?;
         log_sender.send(format!("Recording for {} seconds...", duration))?;
 
-        let output_result = cmd.output()?;
-        if !output_result.status.success() {
-            let error = String::from_utf8_lossy(&output_result.stderr);
+        let mut child = runner.spawn("ffmpeg", &args)?;
+        if let Some(frames) = &preview {
+            frames.attach(child.as_mut());
+        }
+
+        // stderr closes when ffmpeg exits
+        let stderr = child
+            .take_stderr()
+            .map(|pipe| watch_capture_start(pipe, started));
+        if !child.wait()? {
+            let error = stderr
+                .map(|thread| thread.join().unwrap_or_default())
+                .unwrap_or_default();
             log_sender.send(format!("FFmpeg recording failed: {}", error))?;
             return Err(format!("FFmpeg recording failed: {}", error).into());
         }
-    } else {
-        // For manual stop, run in background and wait for stop signal
-        let mut child = cmd.spawn()?;
-        let stderr = child.stderr.take().expect("Failed to capture stderr");
-        let mut stderr_reader = io::BufReader::new(stderr);
+        return Ok(());
+    }
+
+    // For manual stop, run in background and wait for the stop signal. Pausing
+    // ends the current segment and resuming starts the next one.
+    log_sender.send("Recording started. Press Stop button when ready.".to_string())?;
+    let mut segments = Vec::new();
+
+    loop {
+        let segment = segment_path(output, segments.len() + 1);
+        let args = command(&segment, 0)?;
+        let mut child = runner.spawn("ffmpeg", &args)?;
+        if let Some(frames) = &preview {
+            frames.attach(child.as_mut());
+        }
+        segments.push(segment);
 
-        log_sender.send("Recording started. Press Stop button when ready.".to_string())?;
+        let stderr = child.take_stderr().expect("Failed to capture stderr");
+        let mut stderr_reader = io::BufReader::new(stderr);
 
-        // Thread to monitor stderr and send logs
+        // Thread to monitor stderr and send logs. Only the first segment's start
+        // counts, later ones continue the same timeline.
         let log_sender_clone = log_sender.clone();
+        let started = (segments.len() == 1).then(|| started.clone());
         thread::spawn(move || {
             let mut buffer = [0; 1024];
 
@@ -174,21 +642,28 @@ pub fn record_screen_gui(
                 }
 
                 let output = String::from_utf8_lossy(&buffer[..n]).to_string();
+                if output.contains("Press [q]") || output.contains("frame=") {
+                    if let Some(started) = &started {
+                        let _ = started.send(Instant::now());
+                    }
+                }
                 log_sender_clone.send(output).unwrap_or_default();
             }
         });
 
-        // Wait for the stop signal
-        while running.load(Ordering::SeqCst) {
+        // Wait for the stop or pause signal
+        let mut exited = false;
+        while running.load(Ordering::SeqCst) && !paused.load(Ordering::SeqCst) {
             thread::sleep(Duration::from_millis(100));
 
             // Check if ffmpeg exited on its own
             match child.try_wait() {
-                Ok(Some(status)) => {
-                    if !status.success() {
+                Ok(Some(success)) => {
+                    if !success {
                         log_sender.send("FFmpeg recording failed unexpectedly.".to_string())?;
                         return Err("FFmpeg recording failed unexpectedly.".into());
                     }
+                    exited = true;
                     break;
                 }
                 Ok(None) => continue,
@@ -196,55 +671,134 @@ pub fn record_screen_gui(
             }
         }
 
-        log_sender.send("Stopping recording...".to_string())?;
-
         // Gracefully terminate FFmpeg with SIGINT for proper file finalization
-        #[cfg(unix)]
-        {
-            unsafe {
-                libc::kill(child.id() as i32, libc::SIGINT);
-            }
-            // Give FFmpeg a moment to clean up
-            thread::sleep(Duration::from_millis(500));
-        }
+        child.interrupt();
+        // Give FFmpeg a moment to clean up
+        thread::sleep(Duration::from_millis(500));
 
         // Then kill if still running
-        let _ = child.kill();
+        child.kill();
         let _ = child.wait();
 
-        log_sender.send("Recording stopped.".to_string())?;
-        log_sender.send(format!("Saved to {}", output))?;
+        if exited || !running.load(Ordering::SeqCst) {
+            break;
+        }
+
+        log_sender.send("Recording paused.".to_string())?;
+        while paused.load(Ordering::SeqCst) && running.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(100));
+        }
+        if !running.load(Ordering::SeqCst) {
+            break;
+        }
+        log_sender.send("Recording resumed.".to_string())?;
+    }
+
+    log_sender.send("Stopping recording...".to_string())?;
+
+    if segments.len() > 1 {
+        log_sender.send(format!("Joining {} segments...", segments.len()))?;
+    }
+    join_segments(runner, &segments, output)?;
+
+    // The webcam file was split into segments alongside the recording
+    if settings
+        .webcam
+        .as_ref()
+        .is_some_and(|webcam| webcam.separate_file)
+    {
+        let cameras: Vec<String> = segments
+            .iter()
+            .map(|segment| webcam_path(segment))
+            .collect();
+        join_segments(runner, &cameras, &webcam_path(output))?;
+    }
+
+    log_sender.send("Recording stopped.".to_string())?;
+    log_sender.send(format!("Saved to {}", output))?;
+
+    Ok(())
+}
+
+// "clip.mp4" -> "clip.part1.mp4", next to the final output
+fn segment_path(output: &str, number: usize) -> String {
+    let path = Path::new(output);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{}.part{}.{}", stem, number, extension.to_string_lossy()),
+        None => format!("{}.part{}", stem, number),
+    };
+    path.with_file_name(name).to_string_lossy().to_string()
+}
+
+// A single segment only needs renaming
+fn join_segments(
+    runner: &dyn CommandRunner,
+    segments: &[String],
+    output: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let [segment] = segments {
+        fs::rename(segment, output)?;
+        Ok(())
+    } else {
+        concat_segments(runner, segments, output)
+    }
+}
+
+/// Join recording segments into `output` with the concat demuxer, without
+/// re-encoding, and remove the segments afterwards.
+pub fn concat_segments(
+    runner: &dyn CommandRunner,
+    segments: &[String],
+    output: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // The list sits next to the segments, and the demuxer resolves names relative to it
+    let list = format!("{}.segments.txt", output);
+    let entries: String = segments
+        .iter()
+        .map(|segment| {
+            let name = Path::new(segment).file_name().unwrap_or_default();
+            format!("file '{}'\n", name.to_string_lossy().replace('\'', r"'\''"))
+        })
+        .collect();
+    fs::write(&list, entries)?;
+
+    let command = FfmpegCommand::new()
+        .overwrite()
+        .input(Input::file(&list).option("f", "concat").option("safe", 0))
+        .output_option("c", "copy")
+        .output(output);
+    let result = runner.output("ffmpeg", &command.args()?);
+    let _ = fs::remove_file(&list);
+
+    let result = result?;
+    if !result.success {
+        let error = String::from_utf8_lossy(&result.stderr);
+        return Err(format!("Joining the recording segments failed: {}", error).into());
+    }
+
+    for segment in segments {
+        let _ = fs::remove_file(segment);
     }
 
     Ok(())
 }
 
 pub fn convert_to_gif_gui(
+    runner: &dyn CommandRunner,
     input: &str,
     output: &str,
+    options: &GifOptions,
     log_sender: Sender<String>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     log_sender.send("Starting video to GIF conversion...".to_string())?;
     log_sender.send("This may take a while depending on video length.".to_string())?;
 
-    // Use FFmpeg to convert video to GIF with reasonable quality
-    let mut cmd = Command::new("ffmpeg");
-    cmd.args([
-        "-i",
-        input,
-        "-vf",
-        "fps=10,scale=640:-1:flags=lanczos,split[s0][s1];[s0]palettegen[p];[s1][p]paletteuse",
-        "-loop",
-        "0",
-        output,
-    ]);
-
+    // Use FFmpeg to convert video to GIF with reasonable quality.
     // We want to capture stderr for progress updates
-    cmd.stderr(Stdio::piped());
+    let mut child = runner.spawn("ffmpeg", &gif_command(input, output, options).args()?)?;
 
-    let mut child = cmd.spawn()?;
-
-    if let Some(stderr) = child.stderr.take() {
+    if let Some(stderr) = child.take_stderr() {
         let mut reader = io::BufReader::new(stderr);
         let mut buffer = [0; 1024];
 
@@ -258,9 +812,9 @@ pub fn convert_to_gif_gui(
         }
     }
 
-    let status = child.wait()?;
+    let success = child.wait()?;
 
-    if !status.success() {
+    if !success {
         log_sender.send("GIF conversion failed!".to_string())?;
         return Err("GIF conversion failed".into());
     }
@@ -284,6 +838,7 @@ pub fn convert_to_gif_gui(
 }
 
 pub fn test_recording_gui(
+    runner: &dyn CommandRunner,
     output: &str,
     log_sender: Sender<String>,
 ) -> Result<(), Box<dyn std::error::Error>> {
@@ -292,78 +847,29 @@ pub fn test_recording_gui(
         "This will record your screen for 3 seconds to verify everything works.".to_string(),
     )?;
 
-    #[cfg(target_os = "macos")]
-    log_sender
-        .send("Note: On macOS, you may need to grant screen recording permissions".to_string())?;
-
-    // Set up the FFmpeg command for a very short recording
-    let mut cmd = Command::new("ffmpeg");
-    cmd.arg("-y"); // Overwrite output file if it exists
+    let platform = Platform::current();
+    if platform == Platform::MacOs {
+        log_sender.send(
+            "Note: On macOS, you may need to grant screen recording permissions".to_string(),
+        )?;
 
-    #[cfg(target_os = "windows")]
-    {
-        cmd.args(["-f", "gdigrab", "-framerate", "30", "-i", "desktop"]);
-    }
-
-    #[cfg(target_os = "linux")]
-    {
-        cmd.args(["-f", "x11grab", "-framerate", "30", "-i", ":0.0"]);
-    }
-
-    #[cfg(target_os = "macos")]
-    {
         // List available devices first to help diagnose issues
-        let devices = Command::new("ffmpeg")
-            .args(["-f", "avfoundation", "-list_devices", "true", "-i", ""])
-            .output();
-
-        match devices {
-            Ok(output) => {
-                let output_str = String::from_utf8_lossy(&output.stderr);
+        match list_macos_devices(runner) {
+            Ok(devices) => {
                 log_sender.send("Available capture devices:".to_string())?;
-                for line in output_str.lines() {
-                    if line.contains("AVFoundation") || line.contains("capture") {
-                        log_sender.send(line.to_string())?;
-                    }
+                for device in devices {
+                    log_sender.send(device)?;
                 }
             }
             Err(_) => log_sender.send("Failed to list capture devices.".to_string())?,
         }
-
-        cmd.args([
-            "-f",
-            "avfoundation",
-            "-framerate",
-            "30",
-            "-i",
-            "1:none", // Default to screen 1, but this might need adjustment
-            "-pix_fmt",
-            "uyvy422", // Needed for macOS avfoundation
-        ]);
-    }
-
-    // Common output options
-    cmd.args([
-        "-c:v",
-        "libx264",
-        "-pix_fmt",
-        "yuv420p",
-        "-preset",
-        "ultrafast", // Use ultrafast for test
-        "-crf",
-        "28", // Lower quality for test
-        "-t",
-        "3", // 3 seconds
-        output,
-    ]);
-
-    // Capture stderr to provide better error messages
-    cmd.stderr(Stdio::piped());
+    }
 
     log_sender.send("Test recording in progress (3 seconds)...".to_string())?;
 
-    let mut child = cmd.spawn()?;
-    let stderr = child.stderr.take().expect("Failed to capture stderr");
+    // Set up the FFmpeg command for a very short recording
+    let mut child = runner.spawn("ffmpeg", &test_recording_command(platform, output).args()?)?;
+    let stderr = child.take_stderr().expect("Failed to capture stderr");
     let mut stderr_reader = io::BufReader::new(stderr);
     let stderr_thread = std::thread::spawn(move || {
         let mut buffer = [0; 1024];
@@ -380,120 +886,104 @@ pub fn test_recording_gui(
     });
 
     // Wait for ffmpeg to finish
-    let status = child.wait()?;
+    let success = child.wait()?;
     let stderr_output = stderr_thread.join().unwrap_or_default();
 
-    if !status.success() {
+    if !success {
         log_sender.send(format!("Test recording failed: {}", stderr_output))?;
         return Err(format!("Test recording failed: {}", stderr_output).into());
     }
 
-    if verify_video_file(output) {
-        log_sender.send("Test recording completed successfully!".to_string())?;
-        log_sender.send("Your system is configured correctly for screen recording.".to_string())?;
-    } else {
-        log_sender
-            .send("Test recording completed but did not produce a valid video file.".to_string())?;
-        log_sender.send("Please check your system configuration.".to_string())?;
-        return Err("Test recording failed to produce a valid video file.".into());
+    match verify_video_file(runner, output) {
+        Ok(_) => {
+            log_sender.send("Test recording completed successfully!".to_string())?;
+            log_sender
+                .send("Your system is configured correctly for screen recording.".to_string())?;
+        }
+        Err(e) => {
+            log_sender.send(format!(
+                "Test recording completed but did not produce a valid video file: {}",
+                e
+            ))?;
+            log_sender.send("Please check your system configuration.".to_string())?;
+            return Err("Test recording failed to produce a valid video file.".into());
+        }
     }
 
     Ok(())
 }
 
+// Collect ffmpeg's stderr in the background, sending the time it first reports
+// progress to `started`. That is when frames are really being captured, after
+// ffmpeg has started up and opened the capture device.
+fn watch_capture_start(
+    stderr: Box<dyn Read + Send>,
+    started: Sender<Instant>,
+) -> JoinHandle<String> {
+    thread::spawn(move || {
+        let mut reader = io::BufReader::new(stderr);
+        let mut buffer = [0; 1024];
+        let mut output = String::new();
+        let mut reported = false;
+
+        while let Ok(n) = reader.read(&mut buffer) {
+            if n == 0 {
+                break;
+            }
+            output.push_str(&String::from_utf8_lossy(&buffer[..n]));
+            if !reported && (output.contains("Press [q]") || output.contains("frame=")) {
+                reported = true;
+                let _ = started.send(Instant::now());
+            }
+        }
+
+        output
+    })
+}
+
+/// Record until `duration` seconds have passed, or until `running` is cleared when
+/// `duration` is 0. The moment capture really starts is sent to `started`.
 pub fn record_screen(
+    runner: &dyn CommandRunner,
     output: &str,
     duration: u64,
-    fps: u32,
+    settings: &RecordSettings,
     running: Arc<AtomicBool>,
+    started: Sender<Instant>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     println!("Press Ctrl+C to stop recording.");
 
     // Set up the FFmpeg command based on the platform
-    let mut cmd = Command::new("ffmpeg");
-    cmd.arg("-y"); // Overwrite output file if it exists
-
-    #[cfg(target_os = "windows")]
-    {
-        cmd.args([
-            "-f",
-            "gdigrab",
-            "-framerate",
-            &fps.to_string(),
-            "-i",
-            "desktop",
-        ]);
-    }
-
-    #[cfg(target_os = "linux")]
-    {
-        cmd.args([
-            "-f",
-            "x11grab",
-            "-framerate",
-            &fps.to_string(),
-            "-i",
-            ":0.0",
-        ]);
-    }
-
-    #[cfg(target_os = "macos")]
-    {
-        cmd.args([
-            "-f",
-            "avfoundation",
-            "-framerate",
-            &fps.to_string(),
-            "-i",
-            "1:none", // Capture screen 1, no audio
-            "-pix_fmt",
-            "uyvy422", // Needed for macOS avfoundation
-        ]);
-    }
-
-    // Common output options
-    cmd.args([
-        "-c:v", "libx264", "-pix_fmt", "yuv420p", "-preset", "medium", "-crf", "23",
-    ]);
-
-    if duration > 0 {
-        cmd.args(["-t", &duration.to_string()]);
-    }
-
-    // Add output file
-    cmd.arg(output);
-
-    // Capture stderr to provide better error messages
-    cmd.stderr(Stdio::piped());
+    let args = record_command(Platform::current(), settings, output, duration)?.args()?;
 
     if duration > 0 {
-        // For fixed duration, just run and wait
+        // For fixed duration, run until ffmpeg is done or asked to stop early
         print!("Recording for {} seconds... ", duration);
         io::stdout().flush()?;
 
-        let output = cmd.output()?;
-        if !output.status.success() {
-            let error = String::from_utf8_lossy(&output.stderr);
-            return Err(format!("FFmpeg recording failed: {}", error).into());
+        let mut child = runner.spawn("ffmpeg", &args)?;
+        let stderr = child.take_stderr().expect("Failed to capture stderr");
+        let stderr_thread = watch_capture_start(stderr, started);
+
+        let success = loop {
+            if let Some(success) = child.try_wait()? {
+                break success;
+            }
+            if !running.load(Ordering::SeqCst) {
+                child.interrupt();
+                break child.wait()?;
+            }
+            thread::sleep(Duration::from_millis(100));
+        };
+        let error_output = stderr_thread.join().unwrap_or_default();
+        if !success && running.load(Ordering::SeqCst) {
+            return Err(format!("FFmpeg recording failed: {}", error_output).into());
         }
     } else {
         // For manual stop, run in background and wait for Ctrl+C
-        let mut child = cmd.spawn()?;
-        let stderr = child.stderr.take().expect("Failed to capture stderr");
-        let mut stderr_reader = io::BufReader::new(stderr);
-        let stderr_thread = std::thread::spawn(move || {
-            let mut buffer = [0; 1024];
-            let mut error_output = String::new();
-
-            while let Ok(n) = stderr_reader.read(&mut buffer) {
-                if n == 0 {
-                    break;
-                }
-                error_output.push_str(&String::from_utf8_lossy(&buffer[..n]));
-            }
-
-            error_output
-        });
+        let mut child = runner.spawn("ffmpeg", &args)?;
+        let stderr = child.take_stderr().expect("Failed to capture stderr");
+        let stderr_thread = watch_capture_start(stderr, started);
 
         // Give FFmpeg a moment to start up before allowing termination
         println!("Initializing recording...");
@@ -517,8 +1007,8 @@ pub fn record_screen(
 
             // Check if ffmpeg exited on its own
             match child.try_wait() {
-                Ok(Some(status)) => {
-                    if !status.success() {
+                Ok(Some(success)) => {
+                    if !success {
                         let error_output = stderr_thread.join().unwrap_or_default();
                         return Err(format!("FFmpeg recording failed: {}", error_output).into());
                     }
@@ -539,17 +1029,12 @@ pub fn record_screen(
         println!("\rTerminating recording process...");
 
         // Gracefully terminate FFmpeg with SIGINT for proper file finalization
-        #[cfg(unix)]
-        {
-            unsafe {
-                libc::kill(child.id() as i32, libc::SIGINT);
-            }
-            // Give FFmpeg a moment to clean up
-            thread::sleep(Duration::from_millis(500));
-        }
+        child.interrupt();
+        // Give FFmpeg a moment to clean up
+        thread::sleep(Duration::from_millis(500));
 
         // Then kill if still running
-        let _ = child.kill();
+        child.kill();
         let _ = child.wait();
 
         // Check if there were any errors
@@ -563,25 +1048,17 @@ pub fn record_screen(
     Ok(())
 }
 
-pub fn convert_to_gif(input: &str, output: &str) -> Result<(), Box<dyn std::error::Error>> {
+pub fn convert_to_gif(
+    runner: &dyn CommandRunner,
+    input: &str,
+    output: &str,
+    options: &GifOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
     println!("Converting video to GIF (this may take a moment)...");
 
     // Use FFmpeg to convert video to GIF with reasonable quality
-    let mut cmd = Command::new("ffmpeg");
-    cmd.args([
-        "-i",
-        input,
-        "-vf",
-        "fps=10,scale=640:-1:flags=lanczos,split[s0][s1];[s0]palettegen[p];[s1][p]paletteuse",
-        "-loop",
-        "0",
-        output,
-    ]);
-
-    cmd.stderr(Stdio::piped());
-
-    let output_result = cmd.output()?;
-    if !output_result.status.success() {
+    let output_result = runner.output("ffmpeg", &gif_command(input, output, options).args()?)?;
+    if !output_result.success {
         let error = String::from_utf8_lossy(&output_result.stderr);
         return Err(format!("GIF conversion failed: {}", error).into());
     }
@@ -589,91 +1066,784 @@ pub fn convert_to_gif(input: &str, output: &str) -> Result<(), Box<dyn std::erro
     Ok(())
 }
 
-pub fn test_recording(output: &str) -> Result<(), Box<dyn std::error::Error>> {
+pub fn test_recording(
+    runner: &dyn CommandRunner,
+    output: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
     println!("This will record your screen for 3 seconds to test if everything works.");
     println!("If you don't see any errors, then your system is properly configured.");
 
     println!("Starting test recording in 3 seconds...");
     thread::sleep(Duration::from_secs(3));
 
-    // Set up the FFmpeg command for a very short recording
-    let mut cmd = Command::new("ffmpeg");
-    cmd.arg("-y"); // Overwrite output file if it exists
-
-    #[cfg(target_os = "windows")]
-    {
-        cmd.args(["-f", "gdigrab", "-framerate", "30", "-i", "desktop"]);
-    }
-
-    #[cfg(target_os = "linux")]
-    {
-        cmd.args(["-f", "x11grab", "-framerate", "30", "-i", ":0.0"]);
-    }
-
-    #[cfg(target_os = "macos")]
-    {
+    let platform = Platform::current();
+    if platform == Platform::MacOs {
         // List available devices first to help diagnose issues
-        let devices = Command::new("ffmpeg")
-            .args(["-f", "avfoundation", "-list_devices", "true", "-i", ""])
-            .output();
-
-        match devices {
-            Ok(output) => {
-                let output_str = String::from_utf8_lossy(&output.stderr);
+        match list_macos_devices(runner) {
+            Ok(devices) => {
                 println!("Available capture devices:");
-                for line in output_str.lines() {
-                    if line.contains("AVFoundation") || line.contains("capture") {
-                        println!("{}", line);
-                    }
+                for device in devices {
+                    println!("{}", device);
                 }
             }
             Err(_) => println!("Failed to list capture devices."),
         }
+    }
 
-        cmd.args([
-            "-f",
-            "avfoundation",
-            "-framerate",
-            "30",
-            "-i",
-            "1:none", // Default to screen 1, but this might need adjustment
-            "-pix_fmt",
-            "uyvy422", // Needed for macOS avfoundation
-        ]);
-    }
-
-    // Common output options
-    cmd.args([
-        "-c:v",
-        "libx264",
-        "-pix_fmt",
-        "yuv420p",
-        "-preset",
-        "ultrafast", // Use ultrafast for test
-        "-crf",
-        "28", // Lower quality for test
-        "-t",
-        "3", // 3 seconds
-        output,
-    ]);
-
-    // Capture stderr to provide better error messages
-    cmd.stderr(Stdio::piped());
-
+    // Set up the FFmpeg command for a very short recording
     println!("Recording...");
-    let output_result = cmd.output()?;
+    let output_result =
+        runner.output("ffmpeg", &test_recording_command(platform, output).args()?)?;
 
-    if !output_result.status.success() {
+    if !output_result.success {
         let error = String::from_utf8_lossy(&output_result.stderr);
         return Err(format!("Test recording failed: {}", error).into());
     }
 
-    if verify_video_file(output) {
-        println!("Success! Test recording completed without errors.");
-        println!("You can view the test video at: {}", output);
-    } else {
-        return Err("Test recording completed but did not produce a valid video file.".into());
+    match verify_video_file(runner, output) {
+        Ok(_) => {
+            println!("Success! Test recording completed without errors.");
+            println!("You can view the test video at: {}", output);
+        }
+        Err(e) => {
+            return Err(format!(
+                "Test recording completed but did not produce a valid video file: {}",
+                e
+            )
+            .into());
+        }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::ScriptedRunner;
+    use crate::subtitle::CaptionStyle;
+
+    fn args(expected: &[&str]) -> Vec<String> {
+        expected.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn record_args(
+        platform: Platform,
+        settings: &RecordSettings,
+        output: &str,
+        duration: u64,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        Ok(record_command(platform, settings, output, duration)?.args()?)
+    }
+
+    fn test_recording_args(platform: Platform, output: &str) -> Vec<String> {
+        test_recording_command(platform, output).args().unwrap()
+    }
+
+    fn gif_args(input: &str, output: &str, options: &GifOptions) -> Vec<String> {
+        gif_command(input, output, options).args().unwrap()
+    }
+
+    fn settings(codec: VideoCodec, audio: bool) -> RecordSettings {
+        RecordSettings {
+            source: CaptureSource::Display("INPUT".to_string()),
+            codec,
+            fps: 25,
+            audio,
+            cursor: CursorMode::Show,
+            webcam: None,
+            timelapse: None,
+            watermark: None,
+        }
+    }
+
+    #[test]
+    fn linux_record_args() {
+        let result = record_args(
+            Platform::Linux,
+            &settings(VideoCodec::H264, false),
+            "out.mp4",
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(
+            result,
+            args(&[
+                "-y",
+                "-f",
+                "x11grab",
+                "-framerate",
+                "25",
+                "-i",
+                "INPUT",
+                "-c:v",
+                "libx264",
+                "-pix_fmt",
+                "yuv420p",
+                "-preset",
+                "medium",
+                "-crf",
+                "23",
+                "out.mp4",
+            ])
+        );
+    }
+
+    #[test]
+    fn linux_record_args_with_audio_and_duration() {
+        let result = record_args(
+            Platform::Linux,
+            &settings(VideoCodec::H264, true),
+            "out.mp4",
+            12,
+        )
+        .unwrap();
+
+        assert_eq!(
+            result,
+            args(&[
+                "-y",
+                "-f",
+                "x11grab",
+                "-framerate",
+                "25",
+                "-i",
+                "INPUT",
+                "-f",
+                "pulse",
+                "-i",
+                "default",
+                "-map",
+                "0:v",
+                "-map",
+                "1:a",
+                "-c:v",
+                "libx264",
+                "-pix_fmt",
+                "yuv420p",
+                "-preset",
+                "medium",
+                "-crf",
+                "23",
+                "-c:a",
+                "aac",
+                "-b:a",
+                "128k",
+                "-t",
+                "12",
+                "out.mp4",
+            ])
+        );
+    }
+
+    #[test]
+    fn vp9_recordings_take_opus_audio_for_webm() {
+        let result = record_args(
+            Platform::Linux,
+            &settings(VideoCodec::Vp9, true),
+            "out.webm",
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(
+            result,
+            args(&[
+                "-y",
+                "-f",
+                "x11grab",
+                "-framerate",
+                "25",
+                "-i",
+                "INPUT",
+                "-f",
+                "pulse",
+                "-i",
+                "default",
+                "-map",
+                "0:v",
+                "-map",
+                "1:a",
+                "-c:v",
+                "libvpx-vp9",
+                "-pix_fmt",
+                "yuv420p",
+                "-crf",
+                "32",
+                "-b:v",
+                "0",
+                "-c:a",
+                "libopus",
+                "-b:a",
+                "128k",
+                "out.webm",
+            ])
+        );
+    }
+
+    #[test]
+    fn timelapse_captures_slowly_and_plays_back_at_30_fps() {
+        let settings = RecordSettings {
+            timelapse: Some(2.0),
+            ..settings(VideoCodec::H264, false)
+        };
+        let result = record_args(Platform::Linux, &settings, "out.mp4", 120).unwrap();
+
+        assert_eq!(
+            result,
+            args(&[
+                "-y",
+                "-f",
+                "x11grab",
+                "-framerate",
+                "1/2",
+                "-i",
+                "INPUT",
+                "-vf",
+                "setpts=N/(30*TB)",
+                "-c:v",
+                "libx264",
+                "-pix_fmt",
+                "yuv420p",
+                "-preset",
+                "medium",
+                "-crf",
+                "23",
+                "-r",
+                "30",
+                "-t",
+                "2.000",
+                "out.mp4",
+            ])
+        );
+
+        let with_audio = RecordSettings {
+            audio: true,
+            ..settings
+        };
+        assert!(record_args(Platform::Linux, &with_audio, "out.mp4", 0).is_err());
+        assert_eq!(
+            timelapse_length(Duration::from_secs(3600), 2.0),
+            Duration::from_secs(60)
+        );
+    }
+
+    #[test]
+    fn linux_record_args_with_webcam_and_separate_file() {
+        let settings = RecordSettings {
+            webcam: Some(WebcamOverlay {
+                separate_file: true,
+                ..WebcamOverlay::default()
+            }),
+            ..settings(VideoCodec::H264, true)
+        };
+        let result = record_args(Platform::Linux, &settings, "out.mp4", 12).unwrap();
+
+        assert_eq!(
+            result,
+            args(&[
+                "-y",
+                "-f",
+                "x11grab",
+                "-framerate",
+                "25",
+                "-i",
+                "INPUT",
+                "-f",
+                "pulse",
+                "-i",
+                "default",
+                "-f",
+                "v4l2",
+                "-i",
+                "/dev/video0",
+                "-filter_complex",
+                "[2:v]scale=320:-2[camera];[0:v][camera]overlay=x=W-w-20:y=H-h-20[v]",
+                "-map",
+                "[v]",
+                "-map",
+                "1:a",
+                "-c:v",
+                "libx264",
+                "-pix_fmt",
+                "yuv420p",
+                "-preset",
+                "medium",
+                "-crf",
+                "23",
+                "-c:a",
+                "aac",
+                "-b:a",
+                "128k",
+                "-t",
+                "12",
+                "out.mp4",
+                "-map",
+                "2:v",
+                "-c:v",
+                "libx264",
+                "-pix_fmt",
+                "yuv420p",
+                "-preset",
+                "medium",
+                "-crf",
+                "23",
+                "-t",
+                "12",
+                "out.webcam.mp4",
+            ])
+        );
+
+        // Only V4L2 cameras are supported
+        assert!(record_args(Platform::MacOs, &settings, "out.mp4", 0).is_err());
+    }
+
+    #[test]
+    fn linux_screen_uses_display_variable() {
+        let settings = RecordSettings::default();
+        let result = record_args(Platform::Linux, &settings, "out.mp4", 0).unwrap();
+
+        assert_eq!(result[6], linux_display());
+    }
+
+    #[test]
+    fn macos_record_args() {
+        let result = record_args(
+            Platform::MacOs,
+            &settings(VideoCodec::H264, true),
+            "out.mp4",
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(
+            result,
+            args(&[
+                "-y",
+                "-f",
+                "avfoundation",
+                "-framerate",
+                "25",
+                "-capture_cursor",
+                "1",
+                "-pixel_format",
+                "uyvy422",
+                "-i",
+                "INPUT:0",
+                "-c:v",
+                "libx264",
+                "-pix_fmt",
+                "yuv420p",
+                "-preset",
+                "medium",
+                "-crf",
+                "23",
+                "-c:a",
+                "aac",
+                "-b:a",
+                "128k",
+                "out.mp4",
+            ])
+        );
+
+        let screen =
+            record_args(Platform::MacOs, &RecordSettings::default(), "out.mp4", 0).unwrap();
+        assert_eq!(screen[10], "1:none");
+    }
+
+    #[test]
+    fn windows_record_args() {
+        let result =
+            record_args(Platform::Windows, &RecordSettings::default(), "out.mp4", 5).unwrap();
+
+        assert_eq!(
+            result,
+            args(&[
+                "-y",
+                "-f",
+                "gdigrab",
+                "-framerate",
+                "30",
+                "-i",
+                "desktop",
+                "-c:v",
+                "libx264",
+                "-pix_fmt",
+                "yuv420p",
+                "-preset",
+                "medium",
+                "-crf",
+                "23",
+                "-t",
+                "5",
+                "out.mp4",
+            ])
+        );
+    }
+
+    #[test]
+    fn windows_rejects_audio() {
+        let result = record_args(
+            Platform::Windows,
+            &settings(VideoCodec::H264, true),
+            "out.mp4",
+            0,
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn codec_encoder_args() {
+        let expected = [
+            (
+                VideoCodec::H264,
+                args(&[
+                    "-c:v", "libx264", "-pix_fmt", "yuv420p", "-preset", "medium", "-crf", "23",
+                ]),
+            ),
+            (
+                VideoCodec::H265,
+                args(&[
+                    "-c:v", "libx265", "-pix_fmt", "yuv420p", "-preset", "medium", "-crf", "28",
+                ]),
+            ),
+            (
+                VideoCodec::Vp9,
+                args(&[
+                    "-c:v",
+                    "libvpx-vp9",
+                    "-pix_fmt",
+                    "yuv420p",
+                    "-crf",
+                    "32",
+                    "-b:v",
+                    "0",
+                ]),
+            ),
+        ];
+
+        for (codec, encoder) in expected {
+            let result =
+                record_args(Platform::Windows, &settings(codec, false), "out.mp4", 0).unwrap();
+            assert_eq!(result[7..result.len() - 1], encoder[..], "{:?}", codec);
+        }
+    }
+
+    #[test]
+    fn synthetic_record_args_are_platform_independent() {
+        let settings = RecordSettings {
+            source: CaptureSource::Synthetic,
+            audio: true,
+            ..RecordSettings::default()
+        };
+
+        for platform in [Platform::Linux, Platform::MacOs, Platform::Windows] {
+            let result = record_args(platform, &settings, "out.mp4", 2).unwrap();
+            assert_eq!(
+                result[..9],
+                args(&[
+                    "-y",
+                    "-f",
+                    "lavfi",
+                    "-i",
+                    "testsrc2=size=1280x720:rate=30",
+                    "-f",
+                    "lavfi",
+                    "-i",
+                    "sine=frequency=440:sample_rate=48000",
+                ])[..]
+            );
+        }
+    }
+
+    #[test]
+    fn hidden_cursor_is_not_drawn_by_the_grabber() {
+        let settings = RecordSettings {
+            cursor: CursorMode::Hide,
+            ..settings(VideoCodec::H264, false)
+        };
+
+        let linux = record_args(Platform::Linux, &settings, "out.mp4", 0).unwrap();
+        assert_eq!(
+            linux[..7],
+            args(&[
+                "-y",
+                "-f",
+                "x11grab",
+                "-framerate",
+                "25",
+                "-draw_mouse",
+                "0"
+            ])
+        );
+
+        let windows = record_args(Platform::Windows, &settings, "out.mp4", 0).unwrap();
+        assert!(windows.windows(2).any(|pair| pair == ["-draw_mouse", "0"]));
+
+        let macos = record_args(Platform::MacOs, &settings, "out.mp4", 0).unwrap();
+        assert!(!macos.contains(&"-capture_cursor".to_string()));
+
+        // The highlight is drawn over the grabbed pointer
+        let highlight = RecordSettings {
+            cursor: CursorMode::Highlight,
+            ..settings
+        };
+        let linux = record_args(Platform::Linux, &highlight, "out.mp4", 0).unwrap();
+        assert!(!linux.contains(&"-draw_mouse".to_string()));
+    }
+
+    #[test]
+    fn test_recording_args_per_platform() {
+        let windows = test_recording_args(Platform::Windows, "t.mp4");
+        assert_eq!(
+            windows,
+            args(&[
+                "-y",
+                "-f",
+                "gdigrab",
+                "-framerate",
+                "30",
+                "-i",
+                "desktop",
+                "-c:v",
+                "libx264",
+                "-pix_fmt",
+                "yuv420p",
+                "-preset",
+                "ultrafast",
+                "-crf",
+                "28",
+                "-t",
+                "3",
+                "t.mp4",
+            ])
+        );
+
+        let macos = test_recording_args(Platform::MacOs, "t.mp4");
+        assert_eq!(
+            macos[..11],
+            args(&[
+                "-y",
+                "-f",
+                "avfoundation",
+                "-framerate",
+                "30",
+                "-capture_cursor",
+                "1",
+                "-pixel_format",
+                "uyvy422",
+                "-i",
+                "1:none",
+            ])[..]
+        );
+
+        let linux = test_recording_args(Platform::Linux, "t.mp4");
+        assert_eq!(
+            linux[..5],
+            args(&["-y", "-f", "x11grab", "-framerate", "30"])[..]
+        );
+        assert_eq!(linux[6], linux_display());
+    }
+
+    #[test]
+    fn watermark_is_drawn_over_the_recording() {
+        let settings = RecordSettings {
+            watermark: Some(Watermark {
+                text: Some("internal".to_string()),
+                ..Watermark::default()
+            }),
+            ..settings(VideoCodec::H264, true)
+        };
+        let result = record_args(Platform::Linux, &settings, "out.mp4", 0).unwrap();
+
+        assert_eq!(
+            result[11..17],
+            args(&[
+                "-filter_complex",
+                "[0:v]drawtext=text=internal:expansion=none:fontsize=24:fontcolor=0xffffff@0.70:x=w-text_w-20:y=h-text_h-20[watermarked]",
+                "-map",
+                "[watermarked]",
+                "-map",
+                "1:a",
+            ])[..]
+        );
+
+        let empty = RecordSettings {
+            watermark: Some(Watermark::default()),
+            ..settings
+        };
+        assert!(record_args(Platform::Linux, &empty, "out.mp4", 0).is_err());
+    }
+
+    #[test]
+    fn gif_args_default_and_custom() {
+        assert_eq!(
+            gif_args("in.mp4", "out.gif", &GifOptions::default()),
+            args(&[
+                "-i",
+                "in.mp4",
+                "-filter_complex",
+                "[0:v]fps=10,scale=640:-1:flags=lanczos,split[s0][s1];[s0]palettegen[p];[s1][p]paletteuse[gif]",
+                "-map",
+                "[gif]",
+                "-loop",
+                "0",
+                "out.gif",
+            ])
+        );
+
+        let options = GifOptions {
+            fps: 15,
+            width: 320,
+            ..GifOptions::default()
+        };
+        assert_eq!(
+            gif_args("in.mp4", "out.gif", &options)[3],
+            "[0:v]fps=15,scale=320:-1:flags=lanczos,split[s0][s1];[s0]palettegen[p];[s1][p]paletteuse[gif]"
+        );
+
+        let options = GifOptions {
+            watermark: Some(Watermark {
+                image: Some("logo.png".into()),
+                ..Watermark::default()
+            }),
+            ..GifOptions::default()
+        };
+        let args = gif_args("in.mp4", "out.gif", &options);
+        assert_eq!(args[..4], ["-i", "in.mp4", "-i", "logo.png"]);
+        assert_eq!(
+            args[5],
+            "[0:v]fps=10,scale=640:-1:flags=lanczos[scaled];[1:v]format=rgba,colorchannelmixer=aa=0.70[logo];[scaled][logo]overlay=x=W-w-20:y=H-h-20[watermarked];[watermarked]split[s0][s1];[s0]palettegen[p];[s1][p]paletteuse[gif]"
+        );
+
+        let options = GifOptions {
+            captions: Some(Captions {
+                srt: "steps.srt".into(),
+                style: CaptionStyle::default(),
+            }),
+            ..GifOptions::default()
+        };
+        let graph = &gif_args("in.mp4", "out.gif", &options)[3];
+        assert!(graph.starts_with("[0:v]subtitles=filename=steps.srt:force_style="));
+        assert!(graph.ends_with(",fps=10,scale=640:-1:flags=lanczos,split[s0][s1];[s0]palettegen[p];[s1][p]paletteuse[gif]"));
+    }
+
+    #[test]
+    fn convert_to_gif_runs_ffmpeg_and_reports_stderr() {
+        let runner = ScriptedRunner::new().respond(false, "", "Invalid data found");
+        let error = convert_to_gif(&runner, "in.mp4", "out.gif", &GifOptions::default())
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("Invalid data found"), "{}", error);
+        assert_eq!(runner.calls().len(), 1);
+        assert_eq!(runner.calls()[0][0], "ffmpeg");
+        assert_eq!(
+            runner.calls()[0][1..],
+            gif_args("in.mp4", "out.gif", &GifOptions::default())[..]
+        );
+    }
+
+    #[test]
+    fn fixed_duration_recording_reports_ffmpeg_failure() {
+        let runner = ScriptedRunner::new().respond(false, "", "Cannot open display");
+        let settings = RecordSettings::default();
+        let running = Arc::new(AtomicBool::new(true));
+
+        let (started, _) = std::sync::mpsc::channel();
+
+        let error = record_screen(&runner, "out.mp4", 3, &settings, running, started)
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("Cannot open display"), "{}", error);
+        assert_eq!(
+            runner.calls()[0][1..],
+            record_args(Platform::current(), &settings, "out.mp4", 3).unwrap()[..]
+        );
+    }
+
+    #[test]
+    fn recording_reports_when_capture_starts() {
+        let runner = ScriptedRunner::new().respond(
+            true,
+            "",
+            "Input #0, x11grab, from ':0.0':\nPress [q] to stop, [?] for help\nframe=   30 fps= 30",
+        );
+        let running = Arc::new(AtomicBool::new(true));
+        let (started, capture_start) = std::sync::mpsc::channel();
+
+        let before = Instant::now();
+        record_screen(
+            &runner,
+            "out.mp4",
+            1,
+            &RecordSettings::default(),
+            running,
+            started,
+        )
+        .unwrap();
+
+        let at = capture_start.try_recv().unwrap();
+        assert!(at >= before);
+        assert!(capture_start.try_recv().is_err());
+    }
+
+    #[test]
+    fn gui_recording_forwards_ffmpeg_failure_to_the_log() {
+        let runner = ScriptedRunner::new().respond(false, "", "x11grab: cannot open display");
+        let (tx, rx) = std::sync::mpsc::channel();
+        let running = Arc::new(AtomicBool::new(true));
+
+        let result = record_screen_gui(
+            &runner,
+            "out.mp4",
+            3,
+            &RecordSettings::default(),
+            RecordingControl {
+                running,
+                ..RecordingControl::default()
+            },
+            tx,
+            std::sync::mpsc::channel().0,
+        );
+
+        assert!(result.is_err());
+        let logs: Vec<String> = rx.try_iter().collect();
+        assert!(logs
+            .iter()
+            .any(|log| log.contains("x11grab: cannot open display")));
+    }
+
+    #[test]
+    fn segment_paths_sit_next_to_the_output() {
+        assert_eq!(segment_path("/tmp/clip.mp4", 2), "/tmp/clip.part2.mp4");
+        assert_eq!(segment_path("clip", 1), "clip.part1");
+    }
+
+    #[test]
+    fn segments_are_joined_with_the_concat_demuxer() {
+        let dir = std::env::temp_dir().join(format!("rcrdr-concat-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("clip.mp4").to_string_lossy().to_string();
+        let segments = vec![segment_path(&output, 1), segment_path(&output, 2)];
+        for segment in &segments {
+            fs::write(segment, b"segment").unwrap();
+        }
+
+        let runner = ScriptedRunner::new();
+        concat_segments(&runner, &segments, &output).unwrap();
+
+        let list = format!("{}.segments.txt", output);
+        assert_eq!(
+            runner.calls()[0],
+            ["ffmpeg", "-y", "-f", "concat", "-safe", "0", "-i", &list, "-c", "copy", &output,]
+        );
+        assert!(!Path::new(&list).exists());
+        assert!(segments.iter().all(|segment| !Path::new(segment).exists()));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
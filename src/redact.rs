@@ -0,0 +1,400 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+
+use crate::crop::check_rect;
+use crate::ffmpeg::{FfmpegCommand, FilterGraph, Input};
+use crate::media::probe;
+use crate::recorder::{export_audio_encoder, VideoCodec};
+use crate::runner::CommandRunner;
+use crate::screenshot::Geometry;
+use crate::watermark::{apply_watermark, Watermark};
+
+/// How a redacted rectangle is hidden.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactStyle {
+    #[default]
+    Blur,
+    Pixelate,
+}
+
+impl RedactStyle {
+    pub const ALL: [RedactStyle; 2] = [RedactStyle::Blur, RedactStyle::Pixelate];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RedactStyle::Blur => "Blur",
+            RedactStyle::Pixelate => "Pixelate",
+        }
+    }
+}
+
+fn default_strength() -> u32 {
+    12
+}
+
+/// A rectangle hidden from `start` until `end` seconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Redaction {
+    /// WIDTHxHEIGHT+X+Y, like region capture
+    pub rect: Geometry,
+    #[serde(default)]
+    pub start: f64,
+    /// Until the end of the video when missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<f64>,
+    #[serde(default)]
+    pub style: RedactStyle,
+    /// Blur radius, or the size of the pixel blocks, in pixels
+    #[serde(default = "default_strength")]
+    pub strength: u32,
+}
+
+impl Redaction {
+    pub fn new(rect: Geometry, start: f64) -> Self {
+        Self {
+            rect,
+            start,
+            end: None,
+            style: RedactStyle::default(),
+            strength: default_strength(),
+        }
+    }
+
+    pub fn is_active(&self, t: f64) -> bool {
+        t >= self.start && self.end.is_none_or(|end| t <= end)
+    }
+
+    fn effect(&self) -> String {
+        let strength = self.strength.max(1);
+        match self.style {
+            // boxblur refuses radii larger than half the (chroma) plane
+            RedactStyle::Blur => format!(
+                "boxblur=luma_radius='min({s},min(w,h)/2)':chroma_radius='min({s},min(cw,ch)/2)'",
+                s = strength
+            ),
+            RedactStyle::Pixelate => format!(
+                "scale='max(1,iw/{s})':'max(1,ih/{s})',scale={w}:{h}:flags=neighbor",
+                s = strength,
+                w = self.rect.width,
+                h = self.rect.height
+            ),
+        }
+    }
+
+    fn enable(&self) -> String {
+        match self.end {
+            Some(end) => format!("between(t,{:.3},{:.3})", self.start, end),
+            None => format!("gte(t,{:.3})", self.start),
+        }
+    }
+}
+
+/// The rectangles to hide, read from JSON or TOML:
+///
+/// ```toml
+/// [[regions]]
+/// rect = "400x80+20+900"
+/// start = 1.5
+/// end = 4.0
+/// style = "pixelate"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RedactSpec {
+    #[serde(default)]
+    pub regions: Vec<Redaction>,
+}
+
+impl RedactSpec {
+    /// Read a spec, as TOML for a .toml file and as JSON otherwise.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        let spec = if is_toml(path) {
+            toml::from_str(&contents).map_err(|e| e.to_string())
+        } else {
+            serde_json::from_str(&contents).map_err(|e| e.to_string())
+        };
+        spec.map_err(|e| format!("Invalid redaction spec {}: {}", path.display(), e).into())
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let contents = if is_toml(path) {
+            toml::to_string_pretty(self)?
+        } else {
+            serde_json::to_string_pretty(self)?
+        };
+        fs::write(path, contents)?;
+        Ok(())
+    }
+
+    /// Check every region against a `width` x `height` video of `duration` seconds.
+    pub fn check(&self, width: u32, height: u32, duration: f64) -> Result<(), String> {
+        if self.regions.is_empty() {
+            return Err("The redaction spec has no regions".to_string());
+        }
+
+        for (i, region) in self.regions.iter().enumerate() {
+            let describe = |e: String| format!("Region {}: {}", i + 1, e);
+            check_rect(&region.rect, width, height).map_err(describe)?;
+            if region.start < 0.0 || region.start >= duration {
+                return Err(describe(format!(
+                    "starts at {:.2}s, outside the {:.2}s video",
+                    region.start, duration
+                )));
+            }
+            if region.end.is_some_and(|end| end <= region.start) {
+                return Err(describe("ends before it starts".to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_toml(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "toml")
+}
+
+/// "clip.mp4" -> "clip.redacted.mp4", where the redacted copy goes by default.
+pub fn redacted_path(video: &Path) -> PathBuf {
+    match video.extension() {
+        Some(extension) => {
+            video.with_extension(format!("redacted.{}", extension.to_string_lossy()))
+        }
+        None => video.with_extension("redacted"),
+    }
+}
+
+/// "clip.mp4" -> "clip.mp4.redact.json", where the GUI keeps the boxes drawn for a video.
+pub fn redact_spec_path(video: &Path) -> PathBuf {
+    let mut name = video.as_os_str().to_os_string();
+    name.push(".redact.json");
+    PathBuf::from(name)
+}
+
+/// Filter chains that hide each region of the video of input 0 while it is
+/// active, as "[v]": the region is cut out of a copy, blurred or pixelated, and
+/// laid back over the original.
+pub fn redact_filters(regions: &[Redaction]) -> Vec<String> {
+    let mut chains = Vec::new();
+    let mut base = "0:v".to_string();
+
+    for (i, region) in regions.iter().enumerate() {
+        let rect = region.rect;
+        let output = if i + 1 == regions.len() {
+            "v".to_string()
+        } else {
+            format!("redacted{}", i)
+        };
+
+        chains.push(format!("[{}]split[base{i}][source{i}]", base, i = i));
+        chains.push(format!(
+            "[source{i}]crop={}:{}:{}:{},{}[box{i}]",
+            rect.width,
+            rect.height,
+            rect.x,
+            rect.y,
+            region.effect(),
+            i = i
+        ));
+        chains.push(format!(
+            "[base{i}][box{i}]overlay={}:{}:enable='{}'[{}]",
+            rect.x,
+            rect.y,
+            region.enable(),
+            output,
+            i = i
+        ));
+        base = output;
+    }
+
+    chains
+}
+
+/// The ffmpeg command that writes `input` with the regions of `spec` hidden to `output`.
+pub fn redact_command(
+    input: &Path,
+    output: &Path,
+    spec: &RedactSpec,
+    watermark: Option<&Watermark>,
+) -> Result<FfmpegCommand, Box<dyn std::error::Error>> {
+    if spec.regions.is_empty() {
+        return Err("The redaction spec has no regions".into());
+    }
+
+    let command = FfmpegCommand::new()
+        .overwrite()
+        .input(Input::file(input))
+        .filter(FilterGraph::Complex(redact_filters(&spec.regions)))
+        .map("[v]")
+        .map("0:a?");
+
+    Ok(apply_watermark(command, watermark)
+        .video_encoder(VideoCodec::for_path(output).encoder())
+        .audio_encoder(export_audio_encoder(input, output))
+        .output(output))
+}
+
+/// Write `input` with the regions of `spec` hidden to `output`.
+pub fn redact(
+    runner: &dyn CommandRunner,
+    input: &Path,
+    output: &Path,
+    spec: &RedactSpec,
+    watermark: Option<&Watermark>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let info = probe(runner, input)?;
+    let video = info
+        .video_stream()
+        .ok_or_else(|| format!("{} has no video stream", input.display()))?;
+    spec.check(
+        video.width.unwrap_or(0),
+        video.height.unwrap_or(0),
+        info.duration_secs,
+    )?;
+
+    let args = redact_command(input, output, spec, watermark)?.args()?;
+    let result = runner.output("ffmpeg", &args)?;
+    if !result.success {
+        let _ = fs::remove_file(output);
+        let error = String::from_utf8_lossy(&result.stderr);
+        return Err(format!("Redacting failed: {}", error).into());
+    }
+    Ok(())
+}
+
+pub fn redact_gui(
+    runner: &dyn CommandRunner,
+    input: &Path,
+    output: &Path,
+    spec: &RedactSpec,
+    watermark: Option<&Watermark>,
+    log_sender: Sender<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log_sender.send(format!(
+        "Redacting {} regions of {}...",
+        spec.regions.len(),
+        input.display()
+    ))?;
+
+    if let Err(e) = redact(runner, input, output, spec, watermark) {
+        log_sender.send(e.to_string())?;
+        return Err(e);
+    }
+
+    log_sender.send(format!("Redacted copy saved to {}", output.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(rect: &str, start: f64, end: Option<f64>) -> Redaction {
+        Redaction {
+            end,
+            ..Redaction::new(rect.parse().unwrap(), start)
+        }
+    }
+
+    #[test]
+    fn specs_read_as_json_or_toml() {
+        let dir = std::env::temp_dir().join(format!("rcrdr-redact-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let json = dir.join("spec.json");
+        fs::write(
+            &json,
+            r#"{"regions": [{"rect": "400x80+20+900", "start": 1.5, "end": 4, "style": "pixelate"}]}"#,
+        )
+        .unwrap();
+        let toml = dir.join("spec.toml");
+        fs::write(
+            &toml,
+            "[[regions]]\nrect = \"400x80+20+900\"\nstart = 1.5\nend = 4.0\nstyle = \"pixelate\"\n",
+        )
+        .unwrap();
+
+        let expected = RedactSpec {
+            regions: vec![Redaction {
+                style: RedactStyle::Pixelate,
+                ..region("400x80+20+900", 1.5, Some(4.0))
+            }],
+        };
+        assert_eq!(RedactSpec::load(&json).unwrap(), expected);
+        assert_eq!(RedactSpec::load(&toml).unwrap(), expected);
+
+        fs::write(&json, r#"{"regions": [{"rect": "400x80+20"}]}"#).unwrap();
+        assert!(RedactSpec::load(&json).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn regions_are_chained_and_enabled_by_time() {
+        let regions = [
+            region("200x100+10+20", 1.0, Some(2.5)),
+            Redaction {
+                style: RedactStyle::Pixelate,
+                strength: 8,
+                ..region("64x64+0+0", 3.0, None)
+            },
+        ];
+
+        assert_eq!(
+            redact_filters(&regions),
+            [
+                "[0:v]split[base0][source0]",
+                "[source0]crop=200:100:10:20,boxblur=luma_radius='min(12,min(w,h)/2)':chroma_radius='min(12,min(cw,ch)/2)'[box0]",
+                "[base0][box0]overlay=10:20:enable='between(t,1.000,2.500)'[redacted0]",
+                "[redacted0]split[base1][source1]",
+                "[source1]crop=64:64:0:0,scale='max(1,iw/8)':'max(1,ih/8)',scale=64:64:flags=neighbor[box1]",
+                "[base1][box1]overlay=0:0:enable='gte(t,3.000)'[v]",
+            ]
+        );
+    }
+
+    #[test]
+    fn webm_exports_encode_the_audio_for_webm() {
+        let spec = RedactSpec {
+            regions: vec![region("200x100+10+20", 1.0, None)],
+        };
+        let args = |output: &str| {
+            redact_command(Path::new("clip.mp4"), Path::new(output), &spec, None)
+                .unwrap()
+                .args()
+                .unwrap()
+        };
+
+        assert!(args("clip.redacted.mp4")
+            .windows(2)
+            .any(|pair| pair == ["-c:a", "copy"]));
+        let webm = args("clip.webm");
+        assert!(webm.windows(2).any(|pair| pair == ["-c:v", "libvpx-vp9"]));
+        assert!(webm.windows(2).any(|pair| pair == ["-c:a", "libopus"]));
+    }
+
+    #[test]
+    fn regions_must_fit_the_video() {
+        let spec = |region| RedactSpec {
+            regions: vec![region],
+        };
+
+        assert!(spec(region("100x100+0+0", 0.0, None))
+            .check(1920, 1080, 10.0)
+            .is_ok());
+        assert!(RedactSpec::default().check(1920, 1080, 10.0).is_err());
+        assert!(spec(region("100x100+1900+0", 0.0, None))
+            .check(1920, 1080, 10.0)
+            .is_err());
+        assert!(spec(region("100x100+0+0", 12.0, None))
+            .check(1920, 1080, 10.0)
+            .is_err());
+        assert!(spec(region("100x100+0+0", 5.0, Some(5.0)))
+            .check(1920, 1080, 10.0)
+            .is_err());
+    }
+}
@@ -0,0 +1,218 @@
+use std::io::{self, Read};
+use std::process::{Child, Command, Stdio};
+
+/// The result of a process that ran to completion.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProcessOutput {
+    pub success: bool,
+    pub code: Option<i32>,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+/// A process started by a `CommandRunner` that is still running.
+pub trait RunningProcess: Send {
+    /// Take the stderr pipe. Returns `None` after the first call.
+    fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>>;
+
+    /// Take the stdout pipe, for outputs written to `pipe:1`. Returns `None` after
+    /// the first call.
+    fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>>;
+
+    /// `Some(success)` once the process has exited.
+    fn try_wait(&mut self) -> io::Result<Option<bool>>;
+
+    fn wait(&mut self) -> io::Result<bool>;
+
+    /// Ask the process to finish cleanly (SIGINT on Unix), so ffmpeg can
+    /// finalize the output file.
+    fn interrupt(&mut self);
+
+    fn kill(&mut self);
+}
+
+/// Runs external programs. ffmpeg and ffprobe are always started through
+/// one of these so tests can swap in a scripted fake.
+pub trait CommandRunner: Send + Sync {
+    /// Run to completion and capture stdout and stderr.
+    fn output(&self, program: &str, args: &[String]) -> io::Result<ProcessOutput>;
+
+    /// Start in the background with stdout and stderr piped.
+    fn spawn(&self, program: &str, args: &[String]) -> io::Result<Box<dyn RunningProcess>>;
+}
+
+/// Runs commands for real with `std::process::Command`.
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn output(&self, program: &str, args: &[String]) -> io::Result<ProcessOutput> {
+        let output = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .output()?;
+
+        Ok(ProcessOutput {
+            success: output.status.success(),
+            code: output.status.code(),
+            stdout: output.stdout,
+            stderr: output.stderr,
+        })
+    }
+
+    fn spawn(&self, program: &str, args: &[String]) -> io::Result<Box<dyn RunningProcess>> {
+        let child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        Ok(Box::new(SystemProcess { child }))
+    }
+}
+
+struct SystemProcess {
+    child: Child,
+}
+
+impl RunningProcess for SystemProcess {
+    fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.child
+            .stderr
+            .take()
+            .map(|stderr| Box::new(stderr) as Box<dyn Read + Send>)
+    }
+
+    fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.child
+            .stdout
+            .take()
+            .map(|stdout| Box::new(stdout) as Box<dyn Read + Send>)
+    }
+
+    fn try_wait(&mut self) -> io::Result<Option<bool>> {
+        Ok(self.child.try_wait()?.map(|status| status.success()))
+    }
+
+    fn wait(&mut self) -> io::Result<bool> {
+        Ok(self.child.wait()?.success())
+    }
+
+    fn interrupt(&mut self) {
+        #[cfg(unix)]
+        unsafe {
+            libc::kill(self.child.id() as i32, libc::SIGINT);
+        }
+    }
+
+    fn kill(&mut self) {
+        let _ = self.child.kill();
+    }
+}
+
+#[cfg(test)]
+pub use fake::ScriptedRunner;
+
+#[cfg(test)]
+mod fake {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    /// Records every argv it is asked to run and replays canned results in order.
+    /// Once the script runs out, every command succeeds with no output.
+    #[derive(Default)]
+    pub struct ScriptedRunner {
+        calls: Mutex<Vec<Vec<String>>>,
+        responses: Mutex<VecDeque<ProcessOutput>>,
+    }
+
+    impl ScriptedRunner {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Queue the result of the next command.
+        pub fn respond(self, success: bool, stdout: &str, stderr: &str) -> Self {
+            self.responses.lock().unwrap().push_back(ProcessOutput {
+                success,
+                code: Some(if success { 0 } else { 1 }),
+                stdout: stdout.as_bytes().to_vec(),
+                stderr: stderr.as_bytes().to_vec(),
+            });
+            self
+        }
+
+        /// Every command run so far, program name first.
+        pub fn calls(&self) -> Vec<Vec<String>> {
+            self.calls.lock().unwrap().clone()
+        }
+
+        fn next(&self, program: &str, args: &[String]) -> ProcessOutput {
+            let mut argv = vec![program.to_string()];
+            argv.extend(args.iter().cloned());
+            self.calls.lock().unwrap().push(argv);
+
+            self.responses
+                .lock()
+                .unwrap()
+                .pop_front()
+                .unwrap_or(ProcessOutput {
+                    success: true,
+                    code: Some(0),
+                    ..ProcessOutput::default()
+                })
+        }
+    }
+
+    impl CommandRunner for ScriptedRunner {
+        fn output(&self, program: &str, args: &[String]) -> io::Result<ProcessOutput> {
+            Ok(self.next(program, args))
+        }
+
+        fn spawn(&self, program: &str, args: &[String]) -> io::Result<Box<dyn RunningProcess>> {
+            Ok(Box::new(ScriptedProcess {
+                output: self.next(program, args),
+                stdout_taken: false,
+                stderr_taken: false,
+            }))
+        }
+    }
+
+    // A process that has already exited with its canned result
+    struct ScriptedProcess {
+        output: ProcessOutput,
+        stdout_taken: bool,
+        stderr_taken: bool,
+    }
+
+    impl RunningProcess for ScriptedProcess {
+        fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>> {
+            if self.stderr_taken {
+                return None;
+            }
+            self.stderr_taken = true;
+            Some(Box::new(io::Cursor::new(self.output.stderr.clone())))
+        }
+
+        fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>> {
+            if self.stdout_taken {
+                return None;
+            }
+            self.stdout_taken = true;
+            Some(Box::new(io::Cursor::new(self.output.stdout.clone())))
+        }
+
+        fn try_wait(&mut self) -> io::Result<Option<bool>> {
+            Ok(Some(self.output.success))
+        }
+
+        fn wait(&mut self) -> io::Result<bool> {
+            Ok(self.output.success)
+        }
+
+        fn interrupt(&mut self) {}
+
+        fn kill(&mut self) {}
+    }
+}
@@ -0,0 +1,511 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::mpsc::Sender;
+
+use crate::ffmpeg::{FfmpegCommand, FilterGraph};
+use crate::recorder::{capture_command, Platform, RecordSettings};
+use crate::runner::CommandRunner;
+
+/// File name for screenshots without an explicit output path.
+pub const SCREENSHOT_TEMPLATE: &str = "screenshot_{date}_{time}.png";
+
+/// A rectangle of the screen in X11 geometry syntax, e.g. "800x600+100+50".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Geometry {
+    pub width: u32,
+    pub height: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl fmt::Display for Geometry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}+{}+{}", self.width, self.height, self.x, self.y)
+    }
+}
+
+impl FromStr for Geometry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || {
+            format!(
+                "Invalid geometry '{}', expected WIDTHxHEIGHT[+X+Y] such as 800x600+100+50",
+                s
+            )
+        };
+
+        let mut parts = s.trim().split('+');
+        let size = parts.next().ok_or_else(error)?;
+        let (width, height) = size.split_once(['x', 'X']).ok_or_else(error)?;
+        let width: u32 = width.parse().map_err(|_| error())?;
+        let height: u32 = height.parse().map_err(|_| error())?;
+
+        let offsets: Vec<&str> = parts.collect();
+        let (x, y) = match offsets.as_slice() {
+            [] => (0, 0),
+            [x, y] => (
+                x.parse().map_err(|_| error())?,
+                y.parse().map_err(|_| error())?,
+            ),
+            _ => return Err(error()),
+        };
+
+        if width == 0 || height == 0 {
+            return Err(error());
+        }
+
+        Ok(Geometry {
+            width,
+            height,
+            x,
+            y,
+        })
+    }
+}
+
+impl TryFrom<String> for Geometry {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Geometry> for String {
+    fn from(geometry: Geometry) -> Self {
+        geometry.to_string()
+    }
+}
+
+/// Which part of the capture source a screenshot keeps.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ScreenshotArea {
+    /// Everything the capture source sees
+    #[default]
+    Full,
+    Region(Geometry),
+    /// An X11 window id, or the active window
+    Window(Option<String>),
+    /// An xrandr monitor, by name or index
+    Monitor(String),
+}
+
+impl ScreenshotArea {
+    /// The rectangle to crop to, `None` to keep the whole frame. Windows and
+    /// monitors are looked up with xdotool and xrandr.
+    pub fn geometry(
+        &self,
+        runner: &dyn CommandRunner,
+    ) -> Result<Option<Geometry>, Box<dyn std::error::Error>> {
+        match self {
+            ScreenshotArea::Full => Ok(None),
+            ScreenshotArea::Region(geometry) => Ok(Some(*geometry)),
+            ScreenshotArea::Window(window) => window_geometry(runner, window.as_deref()).map(Some),
+            ScreenshotArea::Monitor(monitor) => monitor_geometry(runner, monitor).map(Some),
+        }
+    }
+}
+
+// Run an X11 helper and return its stdout
+fn query(
+    runner: &dyn CommandRunner,
+    program: &str,
+    args: &[&str],
+) -> Result<String, Box<dyn std::error::Error>> {
+    if Platform::current() != Platform::Linux {
+        return Err("Window and monitor screenshots are only supported on Linux (X11).".into());
+    }
+
+    let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    let output = runner
+        .output(program, &args)
+        .map_err(|e| format!("Could not run {}: {}. Is it installed?", program, e))?;
+    if !output.success {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("{} failed: {}", program, error.trim()).into());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Position and size of an X11 window, the active window without an id.
+pub fn window_geometry(
+    runner: &dyn CommandRunner,
+    window: Option<&str>,
+) -> Result<Geometry, Box<dyn std::error::Error>> {
+    let shell = match window {
+        Some(id) => query(runner, "xdotool", &["getwindowgeometry", "--shell", id])?,
+        None => query(
+            runner,
+            "xdotool",
+            &["getactivewindow", "getwindowgeometry", "--shell"],
+        )?,
+    };
+
+    // Lines such as "WIDTH=800"
+    let value = |key: &str| {
+        shell
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .find(|(name, _)| *name == key)
+            .and_then(|(_, value)| value.trim().parse::<i64>().ok())
+            .ok_or_else(|| format!("xdotool did not report the window's {}", key))
+    };
+
+    // "1920 1080"
+    let screen = query(runner, "xdotool", &["getdisplaygeometry"])?;
+    let screen: Vec<i64> = screen
+        .split_whitespace()
+        .filter_map(|value| value.parse().ok())
+        .collect();
+    let [screen_width, screen_height] = screen[..] else {
+        return Err("xdotool did not report the screen size".into());
+    };
+
+    // Windows partly off screen are cut at the screen edges
+    let (x, y) = (value("X")?, value("Y")?);
+    let (left, top) = (x.max(0), y.max(0));
+    let right = (x + value("WIDTH")?).min(screen_width);
+    let bottom = (y + value("HEIGHT")?).min(screen_height);
+    if right <= left || bottom <= top {
+        return Err("The window is off screen".into());
+    }
+    Ok(Geometry {
+        width: (right - left) as u32,
+        height: (bottom - top) as u32,
+        x: left as u32,
+        y: top as u32,
+    })
+}
+
+/// Position and size of a monitor, by xrandr name (e.g. "HDMI-1") or index.
+pub fn monitor_geometry(
+    runner: &dyn CommandRunner,
+    monitor: &str,
+) -> Result<Geometry, Box<dyn std::error::Error>> {
+    let listing = query(runner, "xrandr", &["--listactivemonitors"])?;
+    let monitors = parse_monitors(&listing);
+
+    monitors
+        .iter()
+        .find(|(index, name, _)| name == monitor || index == monitor)
+        .map(|(_, _, geometry)| *geometry)
+        .ok_or_else(|| {
+            let names: Vec<&str> = monitors.iter().map(|(_, name, _)| name.as_str()).collect();
+            format!(
+                "Unknown monitor '{}'. Available monitors: {}",
+                monitor,
+                names.join(", ")
+            )
+            .into()
+        })
+}
+
+// "xrandr --listactivemonitors" lines such as
+// " 0: +*eDP-1 1920/344x1080/194+0+0  eDP-1" as (index, name, geometry)
+fn parse_monitors(listing: &str) -> Vec<(String, String, Geometry)> {
+    listing
+        .lines()
+        .filter_map(|line| {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let index = words.first()?.strip_suffix(':')?;
+            let name = words.last()?;
+
+            // Drop the physical sizes in millimetres after each '/'
+            let (width, rest) = words.get(2)?.split_once('x')?;
+            let (height, offsets) = rest.split_once('+')?;
+            let geometry = format!(
+                "{}x{}+{}",
+                width.split('/').next()?,
+                height.split('/').next()?,
+                offsets
+            );
+
+            Some((index.to_string(), name.to_string(), geometry.parse().ok()?))
+        })
+        .collect()
+}
+
+/// The ffmpeg command that grabs a single frame of the capture source, cropped
+/// to `area`, into the PNG `output`.
+pub fn screenshot_command(
+    platform: Platform,
+    settings: &RecordSettings,
+    area: Option<Geometry>,
+    output: &Path,
+) -> Result<FfmpegCommand, Box<dyn std::error::Error>> {
+    let settings = RecordSettings {
+        audio: false,
+        webcam: None,
+        timelapse: None,
+        watermark: None,
+        ..settings.clone()
+    };
+
+    let mut command = capture_command(platform, &settings)?.overwrite();
+    if let Some(area) = area {
+        command = command.filter(FilterGraph::Video(vec![format!(
+            "crop={}:{}:{}:{}",
+            area.width, area.height, area.x, area.y
+        )]));
+    }
+
+    Ok(command
+        .output_option("frames:v", 1)
+        .output_option("update", 1)
+        .output(output))
+}
+
+/// Save a still of `area` of the capture source to `output`.
+pub fn take_screenshot(
+    runner: &dyn CommandRunner,
+    settings: &RecordSettings,
+    area: &ScreenshotArea,
+    output: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let geometry = area.geometry(runner)?;
+    let command = screenshot_command(Platform::current(), settings, geometry, output)?;
+
+    let result = runner.output("ffmpeg", &command.args()?)?;
+    if !result.success {
+        let _ = fs::remove_file(output);
+        let error = String::from_utf8_lossy(&result.stderr);
+        return Err(format!("Screenshot failed: {}", error).into());
+    }
+
+    Ok(())
+}
+
+/// Put the PNG `image` on the system clipboard.
+pub fn copy_to_clipboard(
+    runner: &dyn CommandRunner,
+    image: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = image.to_string_lossy();
+    let (program, args) = match Platform::current() {
+        Platform::Linux => (
+            "xclip",
+            vec![
+                "-selection".to_string(),
+                "clipboard".to_string(),
+                "-t".to_string(),
+                "image/png".to_string(),
+                "-i".to_string(),
+                path.to_string(),
+            ],
+        ),
+        Platform::MacOs => (
+            "osascript",
+            vec![
+                "-e".to_string(),
+                format!(
+                    "set the clipboard to (read (POSIX file \"{}\") as «class PNGf»)",
+                    path.replace('\\', "\\\\").replace('"', "\\\"")
+                ),
+            ],
+        ),
+        Platform::Windows => (
+            "powershell",
+            vec![
+                "-NoProfile".to_string(),
+                "-Command".to_string(),
+                format!(
+                    "Add-Type -AssemblyName System.Windows.Forms; [System.Windows.Forms.Clipboard]::SetImage([System.Drawing.Image]::FromFile('{}'))",
+                    path.replace('\'', "''")
+                ),
+            ],
+        ),
+    };
+
+    // xclip stays in the background to serve the clipboard, holding on to its
+    // pipes, so only wait for the process we started
+    let mut process = runner
+        .spawn(program, &args)
+        .map_err(|e| format!("Could not run {}: {}. Is it installed?", program, e))?;
+    if !process.wait()? {
+        return Err(format!(
+            "Copying the screenshot to the clipboard with {} failed.",
+            program
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+pub fn take_screenshot_gui(
+    runner: &dyn CommandRunner,
+    settings: &RecordSettings,
+    output: &Path,
+    clipboard: bool,
+    log_sender: Sender<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Err(e) = take_screenshot(runner, settings, &ScreenshotArea::Full, output) {
+        log_sender.send(e.to_string())?;
+        return Err(e);
+    }
+    log_sender.send(format!("Screenshot saved to {}", output.display()))?;
+
+    if clipboard {
+        if let Err(e) = copy_to_clipboard(runner, output) {
+            log_sender.send(e.to_string())?;
+            return Err(e);
+        }
+        log_sender.send("Screenshot copied to the clipboard.".to_string())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recorder::CaptureSource;
+    use crate::runner::ScriptedRunner;
+
+    #[test]
+    fn geometry_parses_x11_syntax() {
+        assert_eq!(
+            "800x600+100+50".parse(),
+            Ok(Geometry {
+                width: 800,
+                height: 600,
+                x: 100,
+                y: 50
+            })
+        );
+        assert_eq!(
+            "1280X720".parse::<Geometry>().unwrap().to_string(),
+            "1280x720+0+0"
+        );
+
+        for invalid in ["", "800", "800x600+100", "0x600", "800x600-1-1", "axb"] {
+            assert!(invalid.parse::<Geometry>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn monitors_are_found_by_name_or_index() {
+        let listing = "Monitors: 2\n 0: +*eDP-1 1920/344x1080/194+0+0  eDP-1\n 1: +HDMI-1 2560/597x1440/336+1920+0  HDMI-1\n";
+        let hdmi = Geometry {
+            width: 2560,
+            height: 1440,
+            x: 1920,
+            y: 0,
+        };
+
+        if Platform::current() == Platform::Linux {
+            let runner = ScriptedRunner::new()
+                .respond(true, listing, "")
+                .respond(true, listing, "")
+                .respond(true, listing, "");
+            assert_eq!(monitor_geometry(&runner, "HDMI-1").unwrap(), hdmi);
+            assert_eq!(monitor_geometry(&runner, "1").unwrap(), hdmi);
+
+            let error = monitor_geometry(&runner, "DP-3").unwrap_err().to_string();
+            assert!(error.contains("eDP-1, HDMI-1"), "{}", error);
+        }
+
+        assert_eq!(parse_monitors(listing)[1].2, hdmi);
+    }
+
+    #[test]
+    fn window_geometry_is_clipped_to_the_screen() {
+        if Platform::current() != Platform::Linux {
+            return;
+        }
+
+        let runner = ScriptedRunner::new()
+            .respond(
+                true,
+                "WINDOW=4194311\nX=-10\nY=40\nWIDTH=810\nHEIGHT=600\nSCREEN=0\n",
+                "",
+            )
+            .respond(true, "1920 1080\n", "");
+        assert_eq!(
+            window_geometry(&runner, None).unwrap(),
+            Geometry {
+                width: 800,
+                height: 600,
+                x: 0,
+                y: 40
+            }
+        );
+        assert_eq!(
+            runner.calls()[0],
+            ["xdotool", "getactivewindow", "getwindowgeometry", "--shell"]
+        );
+        assert_eq!(runner.calls()[1], ["xdotool", "getdisplaygeometry"]);
+    }
+
+    #[test]
+    fn window_geometry_is_clipped_at_the_right_and_bottom_edges() {
+        if Platform::current() != Platform::Linux {
+            return;
+        }
+
+        let runner = ScriptedRunner::new()
+            .respond(true, "X=1600\nY=900\nWIDTH=800\nHEIGHT=600\n", "")
+            .respond(true, "1920 1080\n", "")
+            .respond(true, "X=2000\nY=0\nWIDTH=800\nHEIGHT=600\n", "")
+            .respond(true, "1920 1080\n", "");
+        assert_eq!(
+            window_geometry(&runner, Some("4194311")).unwrap(),
+            Geometry {
+                width: 320,
+                height: 180,
+                x: 1600,
+                y: 900
+            }
+        );
+        assert!(window_geometry(&runner, Some("4194312")).is_err());
+    }
+
+    #[test]
+    fn screenshot_grabs_one_cropped_frame() {
+        let settings = RecordSettings {
+            source: CaptureSource::Display(":1".to_string()),
+            audio: true,
+            ..RecordSettings::default()
+        };
+        let area = Geometry {
+            width: 640,
+            height: 480,
+            x: 10,
+            y: 20,
+        };
+
+        assert_eq!(
+            screenshot_command(
+                Platform::Linux,
+                &settings,
+                Some(area),
+                Path::new("shot.png")
+            )
+            .unwrap()
+            .args()
+            .unwrap(),
+            [
+                "-y",
+                "-f",
+                "x11grab",
+                "-framerate",
+                "30",
+                "-i",
+                ":1",
+                "-vf",
+                "crop=640:480:10:20",
+                "-frames:v",
+                "1",
+                "-update",
+                "1",
+                "shot.png",
+            ]
+        );
+    }
+}
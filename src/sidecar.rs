@@ -0,0 +1,175 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::naming::hostname;
+use crate::recorder::{CaptureSource, RecordSettings, VideoCodec};
+
+/// What a recording was made with, kept next to it as `<output>.rcrdr.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordingMetadata {
+    pub rcrdr_version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    pub source: CaptureSource,
+    pub fps: u32,
+    pub codec: VideoCodec,
+    pub audio: bool,
+    /// Seconds between captured frames of a timelapse
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timelapse: Option<f64>,
+    /// The ffmpeg command line that captured the screen
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// RFC 3339 timestamps of when capture started and stopped
+    pub started: String,
+    pub ended: String,
+}
+
+impl RecordingMetadata {
+    pub fn new(
+        profile: Option<String>,
+        settings: &RecordSettings,
+        command: String,
+        started: DateTime<Local>,
+        ended: DateTime<Local>,
+    ) -> Self {
+        Self {
+            rcrdr_version: env!("CARGO_PKG_VERSION").to_string(),
+            profile,
+            source: settings.source.clone(),
+            fps: settings.fps,
+            codec: settings.codec,
+            audio: settings.audio,
+            timelapse: settings.timelapse,
+            command,
+            host: hostname(),
+            started: started.to_rfc3339(),
+            ended: ended.to_rfc3339(),
+        }
+    }
+
+    /// A one-line summary for the library, such as "demo • screen • 30 fps • h264".
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        parts.extend(self.profile.clone());
+        parts.push(self.source.to_string());
+        parts.push(format!("{} fps", self.fps));
+        parts.push(format!("{:?}", self.codec).to_lowercase());
+        parts.extend(self.host.clone());
+        parts.join("  •  ")
+    }
+}
+
+impl fmt::Display for RecordingMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Recorded with rcrdr {}", self.rcrdr_version)?;
+        if let Some(profile) = &self.profile {
+            writeln!(f, "  {:<14}{}", "Profile", profile)?;
+        }
+        writeln!(f, "  {:<14}{}", "Source", self.source)?;
+        writeln!(f, "  {:<14}{}", "Frame rate", self.fps)?;
+        writeln!(f, "  {:<14}{:?}", "Codec", self.codec)?;
+        writeln!(
+            f,
+            "  {:<14}{}",
+            "Audio",
+            if self.audio { "yes" } else { "no" }
+        )?;
+        if let Some(interval) = self.timelapse {
+            writeln!(f, "  {:<14}a frame every {}s", "Timelapse", interval)?;
+        }
+        if let Some(host) = &self.host {
+            writeln!(f, "  {:<14}{}", "Host", host)?;
+        }
+        writeln!(f, "  {:<14}{}", "Started", self.started)?;
+        writeln!(f, "  {:<14}{}", "Ended", self.ended)?;
+        writeln!(f, "  {:<14}{}", "Command", self.command)
+    }
+}
+
+/// "clip.mp4" -> "clip.mp4.rcrdr.json", where the recording's metadata is kept.
+/// The suffix goes after the extension so clip.mp4 and clip.mkv don't share one.
+pub fn sidecar_path(video: &Path) -> PathBuf {
+    let mut name = video.as_os_str().to_os_string();
+    name.push(".rcrdr.json");
+    PathBuf::from(name)
+}
+
+pub fn write_sidecar(
+    video: &Path,
+    metadata: &RecordingMetadata,
+) -> Result<(), Box<dyn std::error::Error>> {
+    fs::write(sidecar_path(video), serde_json::to_string_pretty(metadata)?)?;
+    Ok(())
+}
+
+/// The metadata kept next to `video`, if it was recorded with rcrdr.
+pub fn read_sidecar(video: &Path) -> Result<Option<RecordingMetadata>, Box<dyn std::error::Error>> {
+    let path = sidecar_path(video);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents = fs::read_to_string(&path)
+        .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    let metadata = serde_json::from_str(&contents)
+        .map_err(|e| format!("Invalid recording metadata {}: {}", path.display(), e))?;
+    Ok(Some(metadata))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn sidecar_round_trips_next_to_the_video() {
+        let dir = std::env::temp_dir().join(format!("rcrdr-sidecar-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let video = dir.join("take.1.mp4");
+
+        let settings = RecordSettings {
+            fps: 24,
+            timelapse: Some(2.0),
+            ..RecordSettings::default()
+        };
+        let started = Local.with_ymd_and_hms(2026, 10, 18, 9, 30, 0).unwrap();
+        let metadata = RecordingMetadata::new(
+            Some("demo".to_string()),
+            &settings,
+            "ffmpeg -f x11grab -i :0.0 take.1.mp4".to_string(),
+            started,
+            started + chrono::Duration::seconds(90),
+        );
+
+        assert_eq!(read_sidecar(&video).unwrap(), None);
+        write_sidecar(&video, &metadata).unwrap();
+        assert!(dir.join("take.1.mp4.rcrdr.json").exists());
+        assert_eq!(read_sidecar(&video).unwrap(), Some(metadata.clone()));
+        assert!(metadata
+            .summary()
+            .starts_with("demo  •  screen  •  24 fps  •  h264"));
+
+        // The same name with another extension keeps its own metadata
+        let other = dir.join("take.1.mkv");
+        assert_eq!(read_sidecar(&other).unwrap(), None);
+        write_sidecar(
+            &other,
+            &RecordingMetadata {
+                fps: 60,
+                ..metadata.clone()
+            },
+        )
+        .unwrap();
+        assert_eq!(read_sidecar(&other).unwrap().unwrap().fps, 60);
+        assert_eq!(read_sidecar(&video).unwrap(), Some(metadata.clone()));
+
+        fs::write(sidecar_path(&video), "{}").unwrap();
+        assert!(read_sidecar(&video).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
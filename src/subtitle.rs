@@ -0,0 +1,283 @@
+use clap::ValueEnum;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::ffmpeg::{escape_filter_value, Encoder, FfmpegCommand, FilterGraph, Input};
+use crate::recorder::VideoCodec;
+use crate::runner::CommandRunner;
+use crate::webcam::Rgb;
+
+/// Where burnt-in captions sit, centered horizontally.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum CaptionPosition {
+    Top,
+    #[default]
+    Bottom,
+}
+
+impl CaptionPosition {
+    // The numpad-style alignment of ASS styles
+    fn alignment(&self) -> u32 {
+        match self {
+            CaptionPosition::Top => 8,
+            CaptionPosition::Bottom => 2,
+        }
+    }
+}
+
+/// How burnt-in captions look, for videos and GIFs alike.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptionStyle {
+    /// A fontconfig name such as "DejaVu Sans"
+    pub font: Option<String>,
+    /// Font size; libass scales it with the video height
+    pub size: u32,
+    pub color: Rgb,
+    /// Outline thickness, 0 for none
+    pub outline: u32,
+    pub outline_color: Rgb,
+    pub position: CaptionPosition,
+    /// Distance from the top or bottom edge
+    pub margin: u32,
+}
+
+impl Default for CaptionStyle {
+    fn default() -> Self {
+        Self {
+            font: None,
+            size: 20,
+            color: Rgb::WHITE,
+            outline: 2,
+            outline_color: Rgb::BLACK,
+            position: CaptionPosition::Bottom,
+            margin: 20,
+        }
+    }
+}
+
+impl CaptionStyle {
+    // The style as ASS fields for the subtitles filter's force_style
+    fn force_style(&self) -> String {
+        let mut fields = Vec::new();
+        if let Some(font) = &self.font {
+            fields.push(format!("FontName={}", font));
+        }
+        fields.push(format!("FontSize={}", self.size));
+        fields.push(format!("PrimaryColour={}", ass_color(self.color)));
+        fields.push(format!("OutlineColour={}", ass_color(self.outline_color)));
+        fields.push("BorderStyle=1".to_string());
+        fields.push(format!("Outline={}", self.outline));
+        fields.push(format!("Alignment={}", self.position.alignment()));
+        fields.push(format!("MarginV={}", self.margin));
+        fields.join(",")
+    }
+}
+
+// ASS colors are &HAABBGGRR, with 00 for opaque
+fn ass_color(color: Rgb) -> String {
+    format!("&H00{:02X}{:02X}{:02X}", color.2, color.1, color.0)
+}
+
+/// Captions from an SRT file, burnt into the picture with `style`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Captions {
+    pub srt: PathBuf,
+    pub style: CaptionStyle,
+}
+
+impl Captions {
+    /// The subtitles filter that draws the captions.
+    pub fn filter(&self) -> String {
+        format!(
+            "subtitles=filename={}:force_style={}",
+            escape_filter_value(&self.srt.to_string_lossy()),
+            escape_filter_value(&self.style.force_style())
+        )
+    }
+}
+
+/// "clip.mp4" -> "clip.subtitled.mp4", where the captioned copy goes by default.
+pub fn subtitled_path(video: &Path) -> PathBuf {
+    match video.extension() {
+        Some(extension) => {
+            video.with_extension(format!("subtitled.{}", extension.to_string_lossy()))
+        }
+        None => video.with_extension("subtitled"),
+    }
+}
+
+// The subtitle codec a soft track is stored as in `output`'s container
+fn soft_subtitle_codec(output: &Path) -> Result<&'static str, String> {
+    let extension = output
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "mp4" | "m4v" | "mov" => Ok("mov_text"),
+        "mkv" => Ok("srt"),
+        "webm" => Ok("webvtt"),
+        _ => Err(format!(
+            "A subtitle track needs an MP4, MOV, MKV or WebM output, not {}. Burn the captions in instead.",
+            output.display()
+        )),
+    }
+}
+
+/// The ffmpeg command that adds the captions of `srt` to `input`: as a subtitle
+/// track players can switch on, or burnt into the picture with `burn`'s style.
+pub fn subtitle_command(
+    input: &Path,
+    output: &Path,
+    srt: &Path,
+    burn: Option<&CaptionStyle>,
+) -> Result<FfmpegCommand, String> {
+    let command = FfmpegCommand::new().overwrite().input(Input::file(input));
+
+    match burn {
+        Some(style) => {
+            let captions = Captions {
+                srt: srt.to_path_buf(),
+                style: style.clone(),
+            };
+            Ok(command
+                .filter(FilterGraph::Video(vec![captions.filter()]))
+                .map("0:v")
+                .map("0:a?")
+                .video_encoder(VideoCodec::for_path(output).encoder())
+                .audio_encoder(Encoder::new("copy"))
+                .output(output))
+        }
+        None => {
+            let codec = soft_subtitle_codec(output)?;
+            Ok(command
+                .input(Input::file(srt))
+                .map("0:v")
+                .map("0:a?")
+                .map("1:s")
+                .video_encoder(Encoder::new("copy"))
+                .audio_encoder(Encoder::new("copy"))
+                .output_option("c:s", codec)
+                .output(output))
+        }
+    }
+}
+
+/// Write `input` with the captions of `srt` to `output`.
+pub fn add_subtitles(
+    runner: &dyn CommandRunner,
+    input: &Path,
+    output: &Path,
+    srt: &Path,
+    burn: Option<&CaptionStyle>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !srt.exists() {
+        return Err(format!("Subtitle file does not exist: {}", srt.display()).into());
+    }
+
+    let args = subtitle_command(input, output, srt, burn)?.args()?;
+    let result = runner.output("ffmpeg", &args)?;
+    if !result.success {
+        let _ = fs::remove_file(output);
+        let error = String::from_utf8_lossy(&result.stderr);
+        return Err(format!("Adding the subtitles failed: {}", error).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn captions_are_styled_through_force_style() {
+        let captions = Captions {
+            srt: PathBuf::from("/tmp/step 1: setup.srt"),
+            style: CaptionStyle {
+                font: Some("DejaVu Sans".to_string()),
+                size: 24,
+                color: Rgb(255, 136, 0),
+                position: CaptionPosition::Top,
+                ..CaptionStyle::default()
+            },
+        };
+
+        assert_eq!(
+            captions.filter(),
+            r"subtitles=filename=/tmp/step 1\\: setup.srt:force_style=FontName=DejaVu Sans\,FontSize=24\,PrimaryColour=&H000088FF\,OutlineColour=&H00000000\,BorderStyle=1\,Outline=2\,Alignment=8\,MarginV=20"
+        );
+    }
+
+    #[test]
+    fn soft_subtitles_are_copied_in_as_a_track() {
+        let args = subtitle_command(
+            Path::new("clip.mp4"),
+            Path::new("clip.subtitled.mp4"),
+            Path::new("captions.srt"),
+            None,
+        )
+        .unwrap()
+        .args()
+        .unwrap();
+
+        assert_eq!(
+            args,
+            [
+                "-y",
+                "-i",
+                "clip.mp4",
+                "-i",
+                "captions.srt",
+                "-map",
+                "0:v",
+                "-map",
+                "0:a?",
+                "-map",
+                "1:s",
+                "-c:v",
+                "copy",
+                "-c:a",
+                "copy",
+                "-c:s",
+                "mov_text",
+                "clip.subtitled.mp4"
+            ]
+        );
+
+        let mkv = subtitle_command(
+            Path::new("clip.mp4"),
+            Path::new("clip.mkv"),
+            Path::new("captions.srt"),
+            None,
+        )
+        .unwrap()
+        .args()
+        .unwrap();
+        assert!(mkv.windows(2).any(|pair| pair == ["-c:s", "srt"]));
+
+        assert!(subtitle_command(
+            Path::new("clip.mp4"),
+            Path::new("clip.avi"),
+            Path::new("captions.srt"),
+            None,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn burnt_captions_re_encode_the_video() {
+        let args = subtitle_command(
+            Path::new("clip.webm"),
+            Path::new("clip.subtitled.webm"),
+            Path::new("captions.srt"),
+            Some(&CaptionStyle::default()),
+        )
+        .unwrap()
+        .args()
+        .unwrap();
+
+        assert_eq!(args[3], "-vf");
+        assert!(args[4].starts_with("subtitles=filename=captions.srt:force_style=FontSize=20"));
+        assert!(args.windows(2).any(|pair| pair == ["-c:v", "libvpx-vp9"]));
+        assert!(!args.contains(&"1:s".to_string()));
+    }
+}
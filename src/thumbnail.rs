@@ -0,0 +1,180 @@
+use std::fs;
+use std::path::Path;
+
+use crate::ffmpeg::{FfmpegCommand, FilterGraph, Input};
+use crate::media::probe;
+use crate::runner::CommandRunner;
+
+// More tiles than this make an unreadable sheet and a huge image
+const MAX_TILES: u32 = 400;
+
+/// Write a single frame of `input` to `output` as a PNG, `width` pixels wide.
+///
+/// Without `at`, the frame is taken 10% into the video, which skips the usual
+/// alt-tab at the start of a recording.
+pub fn extract_poster(
+    runner: &dyn CommandRunner,
+    input: &Path,
+    output: &Path,
+    at: Option<f64>,
+    width: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let command = poster_command(runner, input, output, at, width)?;
+    run_ffmpeg(runner, &command, output)
+}
+
+/// The ffmpeg command behind `extract_poster`.
+pub fn poster_command(
+    runner: &dyn CommandRunner,
+    input: &Path,
+    output: &Path,
+    at: Option<f64>,
+    width: u32,
+) -> Result<FfmpegCommand, Box<dyn std::error::Error>> {
+    let duration = probe(runner, input)?.duration_secs;
+
+    let at = match at {
+        Some(at) if at > duration => {
+            return Err(format!(
+                "--at {:.2}s is past the end of the video ({:.2}s)",
+                at, duration
+            )
+            .into())
+        }
+        Some(at) => at,
+        None => duration * 0.1,
+    };
+
+    Ok(FfmpegCommand::new()
+        .overwrite()
+        .input(Input::file(input).seek(at))
+        .filter(FilterGraph::Video(vec![format!(
+            "scale={}:-1:flags=lanczos",
+            width
+        )]))
+        .output_option("frames:v", 1)
+        .output_option("update", 1)
+        .output(output))
+}
+
+/// Write a `columns` x `rows` grid of evenly spaced frames to `output` as a PNG.
+pub fn contact_sheet(
+    runner: &dyn CommandRunner,
+    input: &Path,
+    output: &Path,
+    columns: u32,
+    rows: u32,
+    tile_width: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let command = contact_sheet_command(runner, input, output, columns, rows, tile_width)?;
+    run_ffmpeg(runner, &command, output)
+}
+
+/// The ffmpeg command behind `contact_sheet`.
+pub fn contact_sheet_command(
+    runner: &dyn CommandRunner,
+    input: &Path,
+    output: &Path,
+    columns: u32,
+    rows: u32,
+    tile_width: u32,
+) -> Result<FfmpegCommand, Box<dyn std::error::Error>> {
+    if columns == 0 || rows == 0 {
+        return Err("The contact sheet grid needs at least one row and column.".into());
+    }
+
+    let tiles = columns
+        .checked_mul(rows)
+        .filter(|tiles| *tiles <= MAX_TILES)
+        .ok_or_else(|| {
+            format!(
+                "A {}x{} contact sheet is too big, it can have at most {} tiles.",
+                columns, rows, MAX_TILES
+            )
+        })?;
+
+    let duration = probe(runner, input)?.duration_secs;
+    if duration <= 0.0 {
+        return Err(format!(
+            "{} has no known duration, so frames can't be spread over it.",
+            input.display()
+        )
+        .into());
+    }
+
+    // Sample the middle of each of the `columns * rows` equal slices of the video
+    let interval = duration / tiles as f64;
+
+    Ok(FfmpegCommand::new()
+        .overwrite()
+        .input(Input::file(input).seek(interval / 2.0))
+        .filter(FilterGraph::Video(vec![
+            format!("fps=1/{:.6}", interval),
+            format!("scale={}:-1:flags=lanczos", tile_width),
+            format!("tile={}x{}:padding=4:margin=4", columns, rows),
+        ]))
+        .output_option("frames:v", 1)
+        .output_option("update", 1)
+        .output(output))
+}
+
+fn run_ffmpeg(
+    runner: &dyn CommandRunner,
+    command: &FfmpegCommand,
+    output: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let _ = fs::remove_file(output);
+
+    let result = runner.output("ffmpeg", &command.args()?)?;
+    if !result.success {
+        let error = String::from_utf8_lossy(&result.stderr);
+        return Err(format!("Thumbnail extraction failed: {}", error).into());
+    }
+
+    // ffmpeg exits cleanly when the seek lands past the last frame, it just writes nothing
+    if !output.exists() {
+        return Err("Thumbnail extraction produced no image.".into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::ScriptedRunner;
+
+    fn runner(duration: &str) -> ScriptedRunner {
+        let info = format!(
+            r#"{{"format": {{"duration": "{}", "format_name": "mp4"}}, "streams": []}}"#,
+            duration
+        );
+        ScriptedRunner::new().respond(true, &info, "")
+    }
+
+    #[test]
+    fn contact_sheet_rejects_huge_grids_and_unknown_durations() {
+        let sheet = |runner: &ScriptedRunner, columns, rows| {
+            contact_sheet_command(
+                runner,
+                Path::new("clip.mp4"),
+                Path::new("sheet.png"),
+                columns,
+                rows,
+                320,
+            )
+        };
+
+        let args = sheet(&runner("40.0"), 4, 5).unwrap().args().unwrap();
+        assert!(args.contains(
+            &"fps=1/2.000000,scale=320:-1:flags=lanczos,tile=4x5:padding=4:margin=4".to_string()
+        ));
+
+        let error = sheet(&runner("40.0"), u32::MAX, 2).unwrap_err();
+        assert!(error.to_string().contains("at most 400 tiles"));
+        assert!(sheet(&runner("40.0"), 21, 20).is_err());
+
+        let error = sheet(&runner("0.0"), 4, 4).unwrap_err();
+        assert!(error.to_string().contains("no known duration"));
+    }
+}
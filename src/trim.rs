@@ -0,0 +1,387 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+
+use crate::ffmpeg::{Encoder, FfmpegCommand, FilterGraph, Input};
+use crate::media::probe;
+use crate::recorder::VideoCodec;
+use crate::runner::CommandRunner;
+use crate::watermark::{apply_watermark, Watermark};
+
+// ffprobe rounds keyframe timestamps, and cuts placed by frame in the GUI land
+// a little off them. Anything closer than this counts as on the keyframe.
+const KEYFRAME_TOLERANCE: f64 = 0.01;
+
+// Width of the frames along the trim editor's timeline
+const TIMELINE_FRAME_WIDTH: u32 = 160;
+
+/// How a trimmed copy is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrimMode {
+    /// Copy the packets between the cuts, possible when the in point is a keyframe
+    Copy,
+    /// Decode and encode again, cutting at any frame
+    Reencode,
+}
+
+impl TrimMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TrimMode::Copy => "stream copy",
+            TrimMode::Reencode => "re-encode",
+        }
+    }
+
+    /// Stream copy when the cut starts on a keyframe. The out point doesn't
+    /// matter, the copy just stops there.
+    pub fn for_cut(keyframes: &[f64], from: f64) -> Self {
+        let on_keyframe = from <= 0.0
+            || keyframes
+                .iter()
+                .any(|keyframe| (keyframe - from).abs() < KEYFRAME_TOLERANCE);
+
+        if on_keyframe {
+            TrimMode::Copy
+        } else {
+            TrimMode::Reencode
+        }
+    }
+}
+
+/// "clip.mp4" -> "clip.trimmed.mp4", where the trimmed copy goes by default.
+pub fn trimmed_path(video: &Path) -> PathBuf {
+    match video.extension() {
+        Some(extension) => video.with_extension(format!("trimmed.{}", extension.to_string_lossy())),
+        None => video.with_extension("trimmed"),
+    }
+}
+
+/// Timestamps of the video keyframes of `input`, in seconds.
+pub fn keyframes(
+    runner: &dyn CommandRunner,
+    input: &Path,
+) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+    // Packet flags come from the container, nothing has to be decoded
+    let args = [
+        "-v",
+        "error",
+        "-select_streams",
+        "v:0",
+        "-show_entries",
+        "packet=pts_time,flags",
+        "-of",
+        "csv=p=0",
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .chain([input.to_string_lossy().to_string()])
+    .collect::<Vec<_>>();
+
+    let output = runner
+        .output("ffprobe", &args)
+        .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+    if !output.success {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "Could not read the keyframes of {}: {}",
+            input.display(),
+            error.trim()
+        )
+        .into());
+    }
+
+    // Lines such as "2.000000,K__"
+    let mut keyframes: Vec<f64> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once(','))
+        .filter(|(_, flags)| flags.starts_with('K'))
+        .filter_map(|(time, _)| time.trim().parse().ok())
+        .collect();
+    keyframes.sort_by(f64::total_cmp);
+
+    Ok(keyframes)
+}
+
+/// The ffmpeg command that copies `from`..`to` seconds of `input` to `output`.
+/// A watermark can only be drawn with `TrimMode::Reencode`.
+pub fn trim_command(
+    input: &Path,
+    output: &Path,
+    from: f64,
+    to: f64,
+    mode: TrimMode,
+    watermark: Option<&Watermark>,
+) -> FfmpegCommand {
+    let command = FfmpegCommand::new()
+        .overwrite()
+        .input(Input::file(input).seek(from))
+        .map("0:v")
+        .map("0:a?");
+
+    // A re-encoded cut starts on the exact frame, so the audio is re-encoded with it
+    // to stay in sync, in a codec the output container takes
+    let command = match mode {
+        TrimMode::Copy => command
+            .video_encoder(Encoder::new("copy"))
+            .audio_encoder(Encoder::new("copy"))
+            .output_option("avoid_negative_ts", "make_zero"),
+        TrimMode::Reencode => {
+            let codec = VideoCodec::for_path(output);
+            command
+                .video_encoder(codec.encoder())
+                .audio_encoder(codec.audio_encoder())
+        }
+    };
+
+    apply_watermark(command, watermark)
+        .duration(format!("{:.3}", to - from))
+        .output(output)
+}
+
+/// Check the cut against the length of `input` and pick how to write it. Without
+/// `to` the cut runs to the end.
+pub fn plan_trim(
+    runner: &dyn CommandRunner,
+    input: &Path,
+    from: f64,
+    to: Option<f64>,
+) -> Result<(f64, TrimMode), Box<dyn std::error::Error>> {
+    let duration = probe(runner, input)?.duration_secs;
+    let to = to.unwrap_or(duration);
+
+    if to > duration + KEYFRAME_TOLERANCE {
+        return Err(format!(
+            "--to {:.2}s is past the end of the video ({:.2}s)",
+            to, duration
+        )
+        .into());
+    }
+    if from >= to {
+        return Err(format!(
+            "The cut starts at {:.2}s, which is not before its end at {:.2}s",
+            from, to
+        )
+        .into());
+    }
+
+    let mode = TrimMode::for_cut(&keyframes(runner, input)?, from);
+    Ok((to.min(duration), mode))
+}
+
+/// Write `from`..`to` seconds of `input` to `output`.
+pub fn trim(
+    runner: &dyn CommandRunner,
+    input: &Path,
+    output: &Path,
+    from: f64,
+    to: f64,
+    mode: TrimMode,
+    watermark: Option<&Watermark>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let args = trim_command(input, output, from, to, mode, watermark).args()?;
+    let result = runner.output("ffmpeg", &args)?;
+    if !result.success {
+        let _ = fs::remove_file(output);
+        let error = String::from_utf8_lossy(&result.stderr);
+        return Err(format!("Trimming failed: {}", error).into());
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn trim_gui(
+    runner: &dyn CommandRunner,
+    input: &Path,
+    output: &Path,
+    from: f64,
+    to: f64,
+    mode: TrimMode,
+    watermark: Option<&Watermark>,
+    log_sender: Sender<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    log_sender.send(format!(
+        "Trimming {} with {}...",
+        input.display(),
+        mode.label()
+    ))?;
+
+    if let Err(e) = trim(runner, input, output, from, to, mode, watermark) {
+        log_sender.send(e.to_string())?;
+        return Err(e);
+    }
+
+    log_sender.send(format!("Trimmed to {}", output.display()))?;
+    Ok(())
+}
+
+/// What the trim editor shows along its timeline.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Timeline {
+    pub keyframes: Vec<f64>,
+    /// Evenly spaced frames as PNG files, in order
+    pub frames: Vec<PathBuf>,
+}
+
+/// The ffmpeg command that writes `count` evenly spaced frames of `input`,
+/// `width` pixels wide, to `dir` as frame_001.png and so on.
+pub fn timeline_command(
+    input: &Path,
+    dir: &Path,
+    duration: f64,
+    count: u32,
+    width: u32,
+) -> FfmpegCommand {
+    let interval = duration / count as f64;
+
+    FfmpegCommand::new()
+        .overwrite()
+        .input(Input::file(input).seek(interval / 2.0))
+        .filter(FilterGraph::Video(vec![
+            format!("fps=1/{:.6}", interval),
+            format!("scale={}:-2", width),
+        ]))
+        .output_option("frames:v", count)
+        .output(dir.join("frame_%03d.png"))
+}
+
+/// Read the keyframes of `input` and extract `count` frames for its timeline into `dir`.
+pub fn load_timeline(
+    runner: &dyn CommandRunner,
+    input: &Path,
+    duration: f64,
+    dir: &Path,
+    count: u32,
+) -> Result<Timeline, Box<dyn std::error::Error>> {
+    let keyframes = keyframes(runner, input)?;
+
+    let _ = fs::remove_dir_all(dir);
+    fs::create_dir_all(dir)?;
+    let args = timeline_command(input, dir, duration, count, TIMELINE_FRAME_WIDTH).args()?;
+    let result = runner.output("ffmpeg", &args)?;
+    if !result.success {
+        let error = String::from_utf8_lossy(&result.stderr);
+        return Err(format!("Extracting timeline frames failed: {}", error).into());
+    }
+
+    let frames = (1..=count)
+        .map(|n| dir.join(format!("frame_{:03}.png", n)))
+        .take_while(|frame| frame.exists())
+        .collect();
+
+    Ok(Timeline { keyframes, frames })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::ScriptedRunner;
+
+    #[test]
+    fn cuts_on_keyframes_are_stream_copied() {
+        let keyframes = [0.0, 2.0, 4.033333];
+
+        assert_eq!(TrimMode::for_cut(&keyframes, 0.0), TrimMode::Copy);
+        assert_eq!(TrimMode::for_cut(&keyframes, 2.0), TrimMode::Copy);
+        assert_eq!(TrimMode::for_cut(&keyframes, 4.0333), TrimMode::Copy);
+        assert_eq!(TrimMode::for_cut(&keyframes, 1.5), TrimMode::Reencode);
+        assert_eq!(TrimMode::for_cut(&[], 1.0), TrimMode::Reencode);
+    }
+
+    #[test]
+    fn keyframes_are_read_from_packet_flags() {
+        let runner = ScriptedRunner::new().respond(
+            true,
+            "0.000000,K__\n0.033333,___\n2.000000,K__\n2.033333,__\n1.000000,K_D\n",
+            "",
+        );
+
+        assert_eq!(
+            keyframes(&runner, Path::new("clip.mp4")).unwrap(),
+            [0.0, 1.0, 2.0]
+        );
+        assert!(runner.calls()[0].contains(&"packet=pts_time,flags".to_string()));
+    }
+
+    #[test]
+    fn trim_command_copies_or_reencodes() {
+        let copy = trim_command(
+            Path::new("clip.mp4"),
+            Path::new("clip.trimmed.mp4"),
+            2.0,
+            5.5,
+            TrimMode::Copy,
+            None,
+        )
+        .args()
+        .unwrap();
+        assert_eq!(
+            copy,
+            [
+                "-y",
+                "-ss",
+                "2.000",
+                "-i",
+                "clip.mp4",
+                "-map",
+                "0:v",
+                "-map",
+                "0:a?",
+                "-c:v",
+                "copy",
+                "-c:a",
+                "copy",
+                "-avoid_negative_ts",
+                "make_zero",
+                "-t",
+                "3.500",
+                "clip.trimmed.mp4",
+            ]
+        );
+
+        let reencode = trim_command(
+            Path::new("clip.webm"),
+            Path::new("cut.webm"),
+            1.25,
+            2.0,
+            TrimMode::Reencode,
+            None,
+        )
+        .args()
+        .unwrap();
+        assert!(reencode
+            .windows(2)
+            .any(|pair| pair == ["-c:v", "libvpx-vp9"]));
+        assert!(reencode.windows(2).any(|pair| pair == ["-c:a", "libopus"]));
+        assert!(!reencode.contains(&"copy".to_string()));
+        assert!(reencode.windows(2).any(|pair| pair == ["-t", "0.750"]));
+
+        let reencode = trim_command(
+            Path::new("clip.mp4"),
+            Path::new("cut.mp4"),
+            1.25,
+            2.0,
+            TrimMode::Reencode,
+            None,
+        )
+        .args()
+        .unwrap();
+        assert!(reencode.windows(2).any(|pair| pair == ["-c:a", "aac"]));
+    }
+
+    #[test]
+    fn cut_must_fit_the_video() {
+        let info = r#"{"format": {"duration": "10.0", "format_name": "mp4"}, "streams": []}"#;
+        let runner = ScriptedRunner::new()
+            .respond(true, info, "")
+            .respond(true, info, "")
+            .respond(true, info, "")
+            .respond(true, "0.000000,K__\n", "");
+
+        assert!(plan_trim(&runner, Path::new("clip.mp4"), 0.0, Some(12.0)).is_err());
+        assert!(plan_trim(&runner, Path::new("clip.mp4"), 6.0, Some(4.0)).is_err());
+        assert_eq!(
+            plan_trim(&runner, Path::new("clip.mp4"), 3.0, None).unwrap(),
+            (10.0, TrimMode::Reencode)
+        );
+    }
+}
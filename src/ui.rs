@@ -1,7 +1,9 @@
+use chrono::TimeZone;
 use eframe::egui::{self, Color32, RichText, Ui};
 use egui::Context;
 use rfd::FileDialog;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+#[cfg(target_os = "macos")]
 use std::process::Command;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::channel;
@@ -10,10 +12,69 @@ use std::thread;
 use std::time::{Duration, Instant};
 
 use crate::app::{AppState, RcrdrApp};
+use crate::chapters::{add_chapters_gui, timelapse_marks, Mark, MarkRequest};
+use crate::concat::concat_gui;
+use crate::condense::{condense_gui, condensed_path, CondenseOptions, IdleAction};
+use crate::config::{Config, Profile};
+use crate::crop::{check_rect, crop_gui, cropped_path, ScaleSpec};
+use crate::cursor::highlight_cursor_gui;
+use crate::events::{self, save_events_gui};
+use crate::hotkeys::{listen, Hotkey, HotkeyAction};
+use crate::library::LibraryEntry;
+use crate::media::{probe, MediaInfo, StreamKind};
+use crate::naming::{
+    apply_overwrite_policy, resolve_output_path, OverwritePolicy, TemplateContext,
+};
+use crate::overlay::spawn_countdown_overlay;
+use crate::preview::{PreviewFrames, SourcePreview, PREVIEW_HEIGHT, PREVIEW_WIDTH};
 use crate::recorder::{
-    convert_to_gif_gui, is_command_available, record_screen_gui, test_recording_gui,
-    verify_video_file,
+    convert_to_gif_gui, is_command_available, record_command, record_screen_gui,
+    test_recording_gui, timelapse_length, verify_video_file, CaptureSource, CursorMode, GifOptions,
+    Platform, RecordSettings, RecordingControl, VideoCodec, TIMELAPSE_FPS,
+};
+use crate::redact::{
+    redact_gui, redact_spec_path, redacted_path, RedactSpec, RedactStyle, Redaction,
 };
+use crate::runner::SystemRunner;
+use crate::screenshot::{take_screenshot_gui, Geometry, SCREENSHOT_TEMPLATE};
+use crate::sidecar::{read_sidecar, write_sidecar, RecordingMetadata};
+use crate::thumbnail::extract_poster;
+use crate::trim::{load_timeline, trim_gui, trimmed_path, TrimMode};
+use crate::watermark::Watermark;
+use crate::webcam::{Corner, WebcamOverlay, WebcamShape};
+
+// Frames along the trim editor's timeline
+const TRIM_TIMELINE_FRAMES: u32 = 12;
+
+fn trim_timeline_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("rcrdr-trim-{}", std::process::id()))
+}
+
+fn crop_frame_path() -> PathBuf {
+    std::env::temp_dir().join(format!("rcrdr-crop-{}.png", std::process::id()))
+}
+
+// One file per requested frame, so a slow extraction can't overwrite a newer one
+fn redact_frame_path(at: f64) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "rcrdr-redact-{}-{}.png",
+        std::process::id(),
+        (at * 1000.0) as u64
+    ))
+}
+
+enum LibraryAction {
+    Open(PathBuf),
+    Reveal(PathBuf),
+    Convert(PathBuf),
+    Trim(PathBuf),
+    Crop(PathBuf),
+    Redact(PathBuf),
+    StartRename(PathBuf),
+    Rename(PathBuf, String),
+    CancelRename,
+    Delete(PathBuf),
+}
 
 impl RcrdrApp {
     pub fn show_setup_screen(&mut self, ui: &mut Ui) {
@@ -104,16 +165,106 @@ impl RcrdrApp {
         });
     }
 
-    pub fn show_main_screen(&mut self, ui: &mut Ui, ctx: &Context) {
-        ui.heading("Screen Recorder");
-
+    fn show_tabs(&mut self, ctx: &Context) {
         egui::TopBottomPanel::top("tabs").show(ctx, |ui| {
             ui.horizontal(|ui| {
-                if ui.button("Record & Convert").clicked() {
+                if ui
+                    .selectable_label(self.state == AppState::Main, "Record & Convert")
+                    .clicked()
+                {
                     self.state = AppState::Main;
                 }
+
+                if ui
+                    .selectable_label(self.state == AppState::Library, "Library")
+                    .clicked()
+                {
+                    self.state = AppState::Library;
+                }
             });
         });
+    }
+
+    pub fn show_main_screen(&mut self, ui: &mut Ui, ctx: &Context) {
+        if let Some(log_receiver) = &self.screenshot_log_receiver {
+            while let Ok(log) = log_receiver.try_recv() {
+                self.logs.push(log);
+            }
+        }
+
+        ui.heading("Screen Recorder");
+
+        self.show_tabs(ctx);
+
+        ui.add_space(10.0);
+
+        egui::CollapsingHeader::new("Profile")
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.add_space(5.0);
+
+                ui.horizontal(|ui| {
+                    ui.label("Active profile:");
+
+                    let selected = self
+                        .active_profile
+                        .clone()
+                        .unwrap_or_else(|| "(none)".to_string());
+                    let names: Vec<String> = self.config.profiles.keys().cloned().collect();
+                    let mut chosen = None;
+
+                    egui::ComboBox::from_id_source("profile")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            for name in &names {
+                                let is_active = self.active_profile.as_deref() == Some(name);
+                                if ui.selectable_label(is_active, name).clicked() {
+                                    chosen = Some(name.clone());
+                                }
+                            }
+                        });
+
+                    if let Some(name) = chosen {
+                        self.apply_profile(&name);
+                    }
+
+                    if let Some(name) = self.active_profile.clone() {
+                        if ui.button("Save").clicked() {
+                            self.save_profile(&name);
+                        }
+
+                        let is_default = self.config.default_profile.as_deref() == Some(&name);
+                        ui.add_enabled_ui(!is_default, |ui| {
+                            if ui
+                                .button("Use as default")
+                                .on_hover_text("Used by the GUI at startup and by `rcrdr record` without --profile")
+                                .clicked()
+                            {
+                                self.set_default_profile(&name);
+                            }
+                        });
+                    }
+
+                    if ui.button("Reload").clicked() {
+                        self.reload_config();
+                    }
+                });
+
+                ui.add_space(5.0);
+
+                ui.horizontal(|ui| {
+                    ui.label("New profile:");
+                    ui.text_edit_singleline(&mut self.new_profile_name);
+
+                    let name = self.new_profile_name.trim().to_string();
+                    ui.add_enabled_ui(!name.is_empty(), |ui| {
+                        if ui.button("Save as").clicked() {
+                            self.save_profile(&name);
+                            self.new_profile_name.clear();
+                        }
+                    });
+                });
+            });
 
         ui.add_space(10.0);
 
@@ -123,22 +274,56 @@ impl RcrdrApp {
                 ui.add_space(5.0);
 
                 ui.horizontal(|ui| {
-                    ui.label("Output File:");
-                    ui.text_edit_singleline(&mut self.output_path);
+                    ui.label("Output Directory:");
+                    ui.text_edit_singleline(&mut self.output_dir);
+
+                    if ui.button("Browse").clicked() {
+                        if let Some(path) = FileDialog::new().pick_folder() {
+                            self.output_dir = path.to_string_lossy().to_string();
+                        }
+                    }
+                });
+
+                ui.add_space(5.0);
+
+                ui.horizontal(|ui| {
+                    ui.label("File Name Template:");
+                    ui.text_edit_singleline(&mut self.filename_template)
+                        .on_hover_text(
+                            "Tokens: {date} {time} {profile} {window_title} {monitor} {counter} {hostname}",
+                        );
 
                     if ui.button("Browse").clicked() {
                         if let Some(path) = FileDialog::new()
-                            .set_file_name(&self.output_path)
+                            .set_directory(&self.output_dir)
                             .add_filter("MP4 Video", &["mp4"])
                             .save_file()
                         {
-                            self.output_path = path.to_string_lossy().to_string();
+                            if let Some(parent) = path.parent() {
+                                self.output_dir = parent.to_string_lossy().to_string();
+                            }
+                            if let Some(name) = path.file_name() {
+                                self.filename_template = name.to_string_lossy().to_string();
+                            }
                         }
                     }
                 });
 
                 ui.add_space(5.0);
 
+                ui.horizontal(|ui| {
+                    ui.label("If the file exists:");
+                    egui::ComboBox::from_id_source("overwrite")
+                        .selected_text(self.overwrite.label())
+                        .show_ui(ui, |ui| {
+                            for policy in OverwritePolicy::ALL {
+                                ui.selectable_value(&mut self.overwrite, policy, policy.label());
+                            }
+                        });
+                });
+
+                ui.add_space(5.0);
+
                 ui.horizontal(|ui| {
                     ui.label("Duration (seconds, 0 for manual stop):");
                     ui.add(egui::DragValue::new(&mut self.duration).speed(1.0));
@@ -146,6 +331,21 @@ impl RcrdrApp {
 
                 ui.add_space(5.0);
 
+                ui.horizontal(|ui| {
+                    ui.label("Countdown (seconds):");
+                    ui.add(
+                        egui::DragValue::new(&mut self.countdown)
+                            .speed(1.0)
+                            .clamp_range(0..=60),
+                    );
+                    ui.checkbox(
+                        &mut self.minimize_on_record,
+                        "Minimize this window when recording starts",
+                    );
+                });
+
+                ui.add_space(5.0);
+
                 ui.horizontal(|ui| {
                     ui.label("Frame Rate (FPS):");
                     ui.add(
@@ -153,21 +353,343 @@ impl RcrdrApp {
                             .speed(1.0)
                             .clamp_range(10..=60),
                     );
+
+                    ui.checkbox(&mut self.timelapse_enabled, "Timelapse, one frame every")
+                        .on_hover_text(format!(
+                            "Played back at {} fps, without audio",
+                            TIMELAPSE_FPS
+                        ));
+                    ui.add_enabled(
+                        self.timelapse_enabled,
+                        egui::DragValue::new(&mut self.timelapse_interval)
+                            .speed(0.1)
+                            .clamp_range(0.1..=600.0)
+                            .suffix(" s"),
+                    );
+                });
+
+                ui.add_space(5.0);
+
+                ui.horizontal(|ui| {
+                    ui.label("Capture Source:");
+                    ui.text_edit_singleline(&mut self.source_input)
+                        .on_hover_text("\"screen\", \"synthetic\" or \"display:<ffmpeg input>\"");
+                    ui.checkbox(&mut self.show_preview, "Show live preview");
+                });
+
+                self.update_source_preview();
+                if self.show_preview {
+                    ui.add_space(5.0);
+                    self.show_preview_image(ui, ctx);
+                }
+
+                ui.add_space(5.0);
+
+                ui.horizontal(|ui| {
+                    ui.label("Codec:");
+                    egui::ComboBox::from_id_source("codec")
+                        .selected_text(self.codec.label())
+                        .show_ui(ui, |ui| {
+                            for codec in VideoCodec::ALL {
+                                ui.selectable_value(&mut self.codec, codec, codec.label());
+                            }
+                        });
+
+                    ui.add_enabled(
+                        !self.timelapse_enabled,
+                        egui::Checkbox::new(&mut self.audio, "Record audio"),
+                    );
+                });
+
+                ui.add_space(5.0);
+
+                ui.horizontal(|ui| {
+                    ui.label("Cursor:");
+                    egui::ComboBox::from_id_source("cursor")
+                        .selected_text(self.cursor.label())
+                        .show_ui(ui, |ui| {
+                            for cursor in CursorMode::ALL {
+                                ui.selectable_value(&mut self.cursor, cursor, cursor.label());
+                            }
+                        })
+                        .response
+                        .on_hover_text("Highlight draws a halo around the pointer after recording (X11 only)");
+                });
+
+                ui.add_space(5.0);
+
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.webcam_enabled, "Overlay webcam:")
+                        .on_hover_text("Composited into a corner of the recording (Linux only)");
+                    ui.add_enabled(
+                        self.webcam_enabled,
+                        egui::TextEdit::singleline(&mut self.webcam.device).desired_width(120.0),
+                    );
+                });
+
+                if self.webcam_enabled {
+                    ui.horizontal(|ui| {
+                        ui.label("Position:");
+                        egui::ComboBox::from_id_source("webcam_corner")
+                            .selected_text(self.webcam.corner.label())
+                            .show_ui(ui, |ui| {
+                                for corner in Corner::ALL {
+                                    ui.selectable_value(
+                                        &mut self.webcam.corner,
+                                        corner,
+                                        corner.label(),
+                                    );
+                                }
+                            });
+
+                        ui.label("Shape:");
+                        egui::ComboBox::from_id_source("webcam_shape")
+                            .selected_text(self.webcam.shape.label())
+                            .show_ui(ui, |ui| {
+                                for shape in WebcamShape::ALL {
+                                    ui.selectable_value(&mut self.webcam.shape, shape, shape.label());
+                                }
+                            });
+
+                        ui.label("Width:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.webcam.width)
+                                .speed(4.0)
+                                .clamp_range(64..=1920),
+                        );
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Border:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.webcam.border)
+                                .speed(1.0)
+                                .clamp_range(0..=50),
+                        );
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.webcam_border_color)
+                                .desired_width(60.0),
+                        )
+                        .on_hover_text("Hex color, e.g. ff8800");
+
+                        ui.checkbox(&mut self.webcam.separate_file, "Also save the webcam on its own")
+                            .on_hover_text("Written next to the recording as .webcam.<ext>");
+                    });
+                }
+
+                ui.add_space(5.0);
+
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.watermark_enabled, "Watermark:");
+                    ui.add_enabled_ui(self.watermark_enabled, |ui| {
+                        ui.radio_value(&mut self.watermark_use_image, false, "Text");
+                        ui.radio_value(&mut self.watermark_use_image, true, "PNG logo");
+                        if self.watermark_use_image {
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.watermark_image)
+                                    .desired_width(200.0),
+                            );
+                            if ui.button("Browse...").clicked() {
+                                if let Some(path) = FileDialog::new()
+                                    .add_filter("PNG", &["png"])
+                                    .pick_file()
+                                {
+                                    self.watermark_image = path.to_string_lossy().to_string();
+                                }
+                            }
+                        } else {
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.watermark_text)
+                                    .desired_width(200.0),
+                            );
+                        }
+                    });
+                });
+
+                if self.watermark_enabled {
+                    if !self.watermark_use_image {
+                        ui.horizontal(|ui| {
+                            ui.label("Font:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.watermark_font)
+                                    .hint_text("default")
+                                    .desired_width(120.0),
+                            )
+                            .on_hover_text("A fontconfig name such as DejaVu Sans, or a font file");
+
+                            ui.label("Size:");
+                            ui.add(
+                                egui::DragValue::new(&mut self.watermark.size)
+                                    .speed(1.0)
+                                    .clamp_range(6..=400),
+                            );
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.watermark_color)
+                                    .desired_width(60.0),
+                            )
+                            .on_hover_text("Hex color, e.g. ff8800");
+                        });
+                    }
+
+                    ui.horizontal(|ui| {
+                        ui.label("Opacity:");
+                        ui.add(egui::Slider::new(&mut self.watermark.opacity, 0.0..=1.0));
+
+                        ui.label("Position:");
+                        egui::ComboBox::from_id_source("watermark_corner")
+                            .selected_text(self.watermark.corner.label())
+                            .show_ui(ui, |ui| {
+                                for corner in Corner::ALL {
+                                    ui.selectable_value(
+                                        &mut self.watermark.corner,
+                                        corner,
+                                        corner.label(),
+                                    );
+                                }
+                            });
+
+                        ui.label("Margin:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.watermark.margin)
+                                .speed(1.0)
+                                .clamp_range(0..=500),
+                        );
+                    });
+
+                    ui.checkbox(
+                        &mut self.watermark_exports,
+                        "Also draw it on GIFs and trimmed, cropped, redacted or joined videos",
+                    );
+                }
+
+                ui.add_space(5.0);
+
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.log_events, "Log clicks and key presses")
+                        .on_hover_text("Written next to the recording as .events.jsonl (X11 only)");
+                    ui.add_enabled_ui(self.log_events, |ui| {
+                        ui.checkbox(
+                            &mut self.annotate_events,
+                            "Burn them into an annotated copy afterwards",
+                        );
+                    });
+                });
+
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.condense_after, "Condense idle stretches")
+                        .on_hover_text(
+                            "Writes a .condensed copy without the stretches where nothing moves and nothing is said",
+                        );
+                    ui.add_enabled_ui(self.condense_after, |ui| {
+                        ui.label("longer than");
+                        ui.add(
+                            egui::DragValue::new(&mut self.condense.min_idle)
+                                .speed(0.1)
+                                .clamp_range(0.5..=60.0)
+                                .suffix(" s"),
+                        );
+                        ui.checkbox(&mut self.condense_speed_up, "speed them up")
+                            .on_hover_text("Instead of cutting them out");
+                        ui.add_enabled(
+                            self.condense_speed_up,
+                            egui::DragValue::new(&mut self.condense_speed)
+                                .speed(0.5)
+                                .clamp_range(1.5..=100.0)
+                                .prefix("×"),
+                        );
+                    });
                 });
 
                 ui.add_space(10.0);
 
                 ui.horizontal(|ui| {
                     if ui.button("Start Recording").clicked() {
-                        self.start_recording();
+                        self.begin_recording();
                     }
 
                     if ui.button("Test Recording (3s)").clicked() {
                         self.start_test_recording();
                     }
+
+                    if ui.button("Screenshot").clicked() {
+                        self.take_screenshot();
+                    }
+                    ui.checkbox(&mut self.screenshot_clipboard, "Copy to clipboard");
+                });
+            });
+
+        ui.add_space(10.0);
+
+        egui::CollapsingHeader::new("Global Hotkeys")
+            .default_open(false)
+            .show(ui, |ui| {
+                ui.add_space(5.0);
+
+                ui.checkbox(
+                    &mut self.config.hotkeys.enabled,
+                    "Enable global hotkeys (X11), even while this window is minimized",
+                );
+
+                ui.add_space(5.0);
+
+                egui::Grid::new("hotkeys").num_columns(2).show(ui, |ui| {
+                    for (action, input) in HotkeyAction::ALL.iter().zip(&mut self.hotkey_inputs) {
+                        ui.label(format!("{}:", action.label()));
+                        ui.text_edit_singleline(input)
+                            .on_hover_text("e.g. Ctrl+Alt+R, Super+F9 or Ctrl+Shift+PageUp");
+                        ui.end_row();
+                    }
+                });
+
+                ui.add_space(5.0);
+
+                ui.horizontal(|ui| {
+                    if ui.button("Apply & Save").clicked() {
+                        self.apply_hotkeys();
+                    }
+
+                    if !self.hotkey_status.is_empty() {
+                        ui.label(&self.hotkey_status);
+                    }
                 });
             });
 
+        if let Some(info) = &self.last_media_info {
+            ui.add_space(20.0);
+
+            let mut trim = None;
+            let mut crop = None;
+            let mut redact = None;
+            egui::CollapsingHeader::new("Last Recording")
+                .default_open(true)
+                .show(ui, |ui| {
+                    show_media_info(ui, info);
+
+                    ui.add_space(5.0);
+                    ui.horizontal(|ui| {
+                        if ui.button("Trim...").clicked() {
+                            trim = Some(info.path.clone());
+                        }
+                        if ui.button("Crop...").clicked() {
+                            crop = Some(info.path.clone());
+                        }
+                        if ui.button("Redact...").clicked() {
+                            redact = Some(info.path.clone());
+                        }
+                    });
+                });
+
+            if let Some(path) = trim {
+                self.open_trim_editor(&path);
+            }
+            if let Some(path) = crop {
+                self.open_crop_editor(&path);
+            }
+            if let Some(path) = redact {
+                self.open_redact_editor(&path);
+            }
+        }
+
         ui.add_space(20.0);
 
         egui::CollapsingHeader::new("Convert Video to GIF")
@@ -254,118 +776,584 @@ impl RcrdrApp {
         }
     }
 
-    pub fn show_recording_screen(&mut self, ui: &mut Ui, ctx: &Context) {
-        // Process any incoming logs if we have a receiver
-        if let Some(log_receiver) = &self.recording_log_receiver {
+    pub fn show_library_screen(&mut self, ui: &mut Ui, ctx: &Context) {
+        ui.heading("Screen Recorder");
+
+        self.show_tabs(ctx);
+
+        ui.add_space(10.0);
+
+        if let Some(log_receiver) = &self.concat_log_receiver {
             while let Ok(log) = log_receiver.try_recv() {
                 self.logs.push(log);
             }
         }
+        if let Some(receiver) = &self.concat_done_receiver {
+            if let Ok(output) = receiver.try_recv() {
+                self.concat_done_receiver = None;
+                if let Some(output) = output {
+                    self.library_selected.clear();
+                    self.add_to_library(&output);
+                }
+            }
+        }
 
-        let start_time = self.recording_start_time.unwrap_or_else(Instant::now);
-        let elapsed = start_time.elapsed();
-        let elapsed_secs = elapsed.as_secs();
-        let elapsed_str = format!(
-            "{:02}:{:02}:{:02}",
-            elapsed_secs / 3600,
-            (elapsed_secs % 3600) / 60,
-            elapsed_secs % 60
-        );
-
-        ui.vertical_centered(|ui| {
-            ui.heading("Recording in Progress");
-
-            ui.add_space(20.0);
-            ui.heading(RichText::new(elapsed_str).size(30.0));
-            ui.add_space(20.0);
-
-            // Pulsating record icon
-            let time = ui.input(|i| i.time);
-            let pulse = (time.sin() * 0.5 + 0.5) as f32;
-            let color = Color32::from_rgba_premultiplied(
-                255,
-                (40.0 + 60.0 * (1.0 - pulse)) as u8,
-                (40.0 + 60.0 * (1.0 - pulse)) as u8,
-                255,
-            );
+        if self.library.entries.is_empty() {
+            ui.label("No recordings yet. Finished recordings will show up here.");
+            return;
+        }
 
-            ui.label(RichText::new("⚫ RECORDING").color(color).size(24.0));
+        self.show_concat_bar(ui, ctx);
 
-            ui.add_space(30.0);
-            if ui.button("Stop Recording").clicked() {
-                if let Some(stop_flag) = &self.recording_stop_flag {
-                    stop_flag.store(false, Ordering::SeqCst);
-                    self.logs.push("Stopping recording...".to_string());
-                }
+        // Load thumbnails up front so the list below only needs shared borrows
+        for entry in &self.library.entries {
+            if let Some(thumbnail) = &entry.thumbnail {
+                self.library_thumbnails
+                    .entry(thumbnail.clone())
+                    .or_insert_with(|| load_texture(ctx, thumbnail));
             }
+            self.library_recordings
+                .entry(entry.path.clone())
+                .or_insert_with(|| read_sidecar(&entry.path).ok().flatten());
+        }
 
-            ui.add_space(10.0);
-            if let Some(output_path) = &self.recording_output_path {
-                ui.label(format!("Output file: {}", output_path));
-            }
+        let mut action = None;
+        let Self {
+            library,
+            library_thumbnails,
+            library_recordings,
+            library_selected,
+            library_rename,
+            library_pending_delete,
+            ..
+        } = self;
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for entry in &library.entries {
+                let exists = entry.path.exists();
 
-            // Show the most recent logs
-            ui.add_space(20.0);
-            egui::ScrollArea::vertical()
-                .max_height(150.0)
-                .show(ui, |ui| {
-                    ui.heading("Status");
-                    for log in self.logs.iter().rev().take(10).rev() {
-                        ui.label(log);
+                ui.horizontal(|ui| {
+                    // Ticked recordings are joined in the order they were ticked
+                    let position = library_selected.iter().position(|p| *p == entry.path);
+                    let mut selected = position.is_some();
+                    let label = position.map(|i| format!("{}", i + 1)).unwrap_or_default();
+                    if ui
+                        .add_enabled(exists, egui::Checkbox::new(&mut selected, label))
+                        .on_hover_text("Select to concatenate")
+                        .changed()
+                    {
+                        match position {
+                            Some(i) => {
+                                library_selected.remove(i);
+                            }
+                            None => library_selected.push(entry.path.clone()),
+                        }
                     }
-                });
-
-            // Check if the recording has stopped
-            if let Some(stop_flag) = &self.recording_stop_flag {
-                if !stop_flag.load(Ordering::SeqCst) {
-                    // Add a small delay to allow the final messages to come through
-                    thread::sleep(Duration::from_millis(100));
 
-                    if let Some(log_receiver) = &self.recording_log_receiver {
-                        while let Ok(log) = log_receiver.try_recv() {
-                            self.logs.push(log);
+                    let texture = entry
+                        .thumbnail
+                        .as_ref()
+                        .and_then(|t| library_thumbnails.get(t))
+                        .and_then(|t| t.as_ref());
+                    match texture {
+                        Some(texture) => {
+                            let size = texture.size_vec2();
+                            ui.image(texture, size * (90.0 / size.y));
+                        }
+                        None => {
+                            ui.add_sized([160.0, 90.0], egui::Label::new("No preview"));
                         }
                     }
 
-                    // Check if recording is truly done
-                    if let Some(output_path) = &self.recording_output_path {
-                        if verify_video_file(output_path) {
-                            self.logs
-                                .push(format!("Recording completed successfully: {}", output_path));
-                            self.state = AppState::Main;
-
-                            // Set the input video path to the recording for easy conversion
-                            self.input_video_path = output_path.clone();
-
-                            // Update output gif path
-                            if let Some(input_path) = Path::new(output_path).file_stem() {
-                                let parent =
-                                    Path::new(output_path).parent().unwrap_or(Path::new(""));
-                                self.output_gif_path = parent
-                                    .join(format!("{}.gif", input_path.to_string_lossy()))
-                                    .to_string_lossy()
-                                    .to_string();
+                    ui.vertical(|ui| {
+                        match library_rename {
+                            Some((path, name)) if *path == entry.path => {
+                                ui.horizontal(|ui| {
+                                    ui.text_edit_singleline(name);
+                                    if ui.button("OK").clicked() {
+                                        action =
+                                            Some(LibraryAction::Rename(path.clone(), name.clone()));
+                                    }
+                                    if ui.button("Cancel").clicked() {
+                                        action = Some(LibraryAction::CancelRename);
+                                    }
+                                });
                             }
+                            _ => {
+                                ui.strong(entry.file_name());
+                            }
+                        }
 
-                            // Clean up recording state
-                            self.recording_start_time = None;
-                            self.recording_stop_flag = None;
-                            self.recording_log_receiver = None;
-                            self.recording_output_path = None;
-
-                            // Request context update to refresh UI immediately
-                            ctx.request_repaint();
+                        let created = chrono::Local
+                            .timestamp_opt(entry.created_at, 0)
+                            .single()
+                            .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                            .unwrap_or_default();
+                        let resolution = match (entry.width, entry.height) {
+                            (Some(w), Some(h)) => format!("{}x{}", w, h),
+                            _ => "?".to_string(),
+                        };
+                        let duration = entry
+                            .duration_secs
+                            .map(format_duration)
+                            .unwrap_or_else(|| "?".to_string());
+
+                        ui.label(format!(
+                            "{}  •  {}  •  {}  •  {}",
+                            created,
+                            duration,
+                            resolution,
+                            format_size(entry.size_bytes)
+                        ));
+                        if let Some(Some(recording)) = library_recordings.get(&entry.path) {
+                            ui.label(recording.summary())
+                                .on_hover_text(recording.to_string());
                         }
-                    }
-                }
-            }
-        });
-    }
 
-    pub fn show_converting_screen(&mut self, ui: &mut Ui) {
-        // Flag to track if we should transition to main screen
-        let mut should_transition = false;
-        let time = ui.input(|i| i.time);
+                        if !exists {
+                            ui.colored_label(Color32::RED, "File is missing");
+                        }
+
+                        ui.horizontal(|ui| {
+                            ui.add_enabled_ui(exists, |ui| {
+                                if ui.button("Open").clicked() {
+                                    action = Some(LibraryAction::Open(entry.path.clone()));
+                                }
+                                if ui.button("Show in Folder").clicked() {
+                                    action = Some(LibraryAction::Reveal(entry.path.clone()));
+                                }
+                                if ui.button("Convert").clicked() {
+                                    action = Some(LibraryAction::Convert(entry.path.clone()));
+                                }
+                                if ui.button("Trim").clicked() {
+                                    action = Some(LibraryAction::Trim(entry.path.clone()));
+                                }
+                                if ui.button("Crop").clicked() {
+                                    action = Some(LibraryAction::Crop(entry.path.clone()));
+                                }
+                                if ui.button("Redact").clicked() {
+                                    action = Some(LibraryAction::Redact(entry.path.clone()));
+                                }
+                                if ui.button("Rename").clicked() {
+                                    action = Some(LibraryAction::StartRename(entry.path.clone()));
+                                }
+                            });
+
+                            if library_pending_delete.as_ref() == Some(&entry.path) {
+                                ui.colored_label(Color32::RED, "Delete this recording?");
+                                if ui.button("Yes").clicked() {
+                                    action = Some(LibraryAction::Delete(entry.path.clone()));
+                                }
+                                if ui.button("No").clicked() {
+                                    *library_pending_delete = None;
+                                }
+                            } else if ui.button("Delete").clicked() {
+                                *library_pending_delete = Some(entry.path.clone());
+                            }
+                        });
+                    });
+                });
+
+                ui.separator();
+            }
+        });
+
+        if let Some(action) = action {
+            self.handle_library_action(action);
+        }
+    }
+
+    fn show_concat_bar(&mut self, ui: &mut Ui, ctx: &Context) {
+        // Forget recordings that were removed or renamed since they were ticked
+        let library = &self.library;
+        self.library_selected
+            .retain(|path| library.entries.iter().any(|entry| entry.path == *path));
+
+        let concatenating = self.concat_done_receiver.is_some();
+
+        ui.horizontal(|ui| {
+            ui.label(format!("{} selected", self.library_selected.len()));
+
+            ui.add_enabled_ui(self.library_selected.len() >= 2 && !concatenating, |ui| {
+                if ui.button("Concatenate").clicked() {
+                    self.start_concat();
+                }
+            });
+
+            ui.checkbox(&mut self.concat_crossfade_enabled, "Crossfade");
+            ui.add_enabled(
+                self.concat_crossfade_enabled,
+                egui::DragValue::new(&mut self.concat_crossfade)
+                    .speed(0.05)
+                    .clamp_range(0.1..=5.0)
+                    .suffix(" s"),
+            );
+
+            if !self.library_selected.is_empty() && ui.button("Clear selection").clicked() {
+                self.library_selected.clear();
+            }
+
+            if concatenating {
+                ui.spinner();
+                ctx.request_repaint_after(Duration::from_millis(100));
+            }
+        });
+
+        // The library has no log of its own, show how the last concatenation went
+        if let (Some(log), Some(_)) = (self.logs.last(), &self.concat_log_receiver) {
+            ui.label(log);
+        }
+
+        ui.separator();
+    }
+
+    fn start_concat(&mut self) {
+        let inputs = self.library_selected.clone();
+        let first = match inputs.first() {
+            Some(first) => first,
+            None => return,
+        };
+
+        // Next to the first recording, e.g. "take1_joined.mp4"
+        let joined = first.with_file_name(format!(
+            "{}_joined.{}",
+            first.file_stem().unwrap_or_default().to_string_lossy(),
+            first.extension().unwrap_or_default().to_string_lossy()
+        ));
+        let output = match apply_overwrite_policy(&joined, OverwritePolicy::Increment) {
+            Ok(output) => output,
+            Err(e) => {
+                self.logs.push(e);
+                return;
+            }
+        };
+        let crossfade = self
+            .concat_crossfade_enabled
+            .then_some(self.concat_crossfade);
+        let watermark = match self.export_watermark() {
+            Ok(watermark) => watermark,
+            Err(e) => {
+                self.logs.push(e);
+                return;
+            }
+        };
+
+        // Set up log channel
+        let (tx, rx) = channel();
+        let (done_tx, done_rx) = channel();
+
+        thread::spawn(move || {
+            let result = concat_gui(
+                &SystemRunner,
+                &inputs,
+                &output,
+                crossfade,
+                watermark.as_ref(),
+                tx,
+            );
+            if let Err(e) = &result {
+                eprintln!("Concatenation error: {}", e);
+            }
+            let _ = done_tx.send(result.ok().map(|_| output));
+        });
+
+        self.concat_log_receiver = Some(rx);
+        self.concat_done_receiver = Some(done_rx);
+    }
+
+    fn handle_library_action(&mut self, action: LibraryAction) {
+        match action {
+            LibraryAction::Open(path) => {
+                if let Err(e) = open::that(&path) {
+                    self.logs
+                        .push(format!("Failed to open {}: {}", path.display(), e));
+                }
+            }
+            LibraryAction::Reveal(path) => {
+                let folder = path.parent().unwrap_or(Path::new("."));
+                if let Err(e) = open::that(folder) {
+                    self.logs
+                        .push(format!("Failed to open {}: {}", folder.display(), e));
+                }
+            }
+            LibraryAction::Convert(path) => {
+                self.set_conversion_input(&path.to_string_lossy());
+                self.state = AppState::Main;
+            }
+            LibraryAction::Trim(path) => self.open_trim_editor(&path),
+            LibraryAction::Crop(path) => self.open_crop_editor(&path),
+            LibraryAction::Redact(path) => self.open_redact_editor(&path),
+            LibraryAction::StartRename(path) => {
+                let name = path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                self.library_rename = Some((path, name));
+            }
+            LibraryAction::Rename(path, name) => match self.library.rename(&path, &name) {
+                Ok(new_path) => {
+                    self.library_recordings.remove(&path);
+                    self.library_recordings.remove(&new_path);
+                    self.logs.push(format!("Renamed to {}", new_path.display()));
+                    self.library_rename = None;
+                    self.save_library();
+                }
+                Err(e) => self.logs.push(format!("Rename failed: {}", e)),
+            },
+            LibraryAction::CancelRename => self.library_rename = None,
+            LibraryAction::Delete(path) => {
+                if let Err(e) = self.library.delete(&path) {
+                    self.logs
+                        .push(format!("Failed to delete {}: {}", path.display(), e));
+                    return;
+                }
+                self.library_recordings.remove(&path);
+                self.library_pending_delete = None;
+                self.logs.push(format!("Deleted {}", path.display()));
+                self.save_library();
+            }
+        }
+    }
+
+    /// Add a recording to the library once it has been probed and its thumbnail
+    /// extracted on a worker thread.
+    pub fn add_to_library(&mut self, path: &Path) {
+        let path = path.to_path_buf();
+        let sender = self.library_entry_sender.clone();
+        self.library_entries_pending += 1;
+        thread::spawn(move || {
+            let entry = LibraryEntry::from_recording(&SystemRunner, &path)
+                .map_err(|e| format!("Failed to add {} to the library: {}", path.display(), e));
+            let _ = sender.send(entry);
+        });
+    }
+
+    pub fn receive_library_entries(&mut self, ctx: &Context) {
+        while let Ok(entry) = self.library_entry_receiver.try_recv() {
+            self.library_entries_pending -= 1;
+            match entry {
+                Ok(entry) => {
+                    if let Some(thumbnail) = &entry.thumbnail {
+                        // Drop any stale texture for a reused thumbnail path
+                        self.library_thumbnails.remove(thumbnail);
+                    }
+                    self.library_recordings.remove(&entry.path);
+                    self.library.add(entry);
+                    self.save_library();
+                }
+                Err(e) => self.logs.push(e),
+            }
+        }
+        if self.library_entries_pending > 0 {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+    }
+
+    fn save_library(&mut self) {
+        if let Err(e) = self.library.save() {
+            self.logs.push(format!("Failed to save library: {}", e));
+        }
+    }
+
+    fn set_conversion_input(&mut self, video_path: &str) {
+        self.input_video_path = video_path.to_string();
+
+        // Suggest a GIF next to the video
+        if let Some(input_path) = Path::new(video_path).file_stem() {
+            let parent = Path::new(video_path).parent().unwrap_or(Path::new(""));
+            self.output_gif_path = parent
+                .join(format!("{}.gif", input_path.to_string_lossy()))
+                .to_string_lossy()
+                .to_string();
+        }
+    }
+
+    pub fn show_recording_screen(&mut self, ui: &mut Ui, ctx: &Context) {
+        // Process any incoming logs if we have a receiver
+        if let Some(log_receiver) = &self.recording_log_receiver {
+            while let Ok(log) = log_receiver.try_recv() {
+                self.logs.push(log);
+            }
+        }
+
+        let elapsed = self.recording_elapsed();
+        let elapsed_secs = elapsed.as_secs();
+        let elapsed_str = format!(
+            "{:02}:{:02}:{:02}",
+            elapsed_secs / 3600,
+            (elapsed_secs % 3600) / 60,
+            elapsed_secs % 60
+        );
+        let paused = self.recording_paused_at.is_some();
+        let finishing = self.recording_finishing();
+
+        ui.vertical_centered(|ui| {
+            ui.heading(if finishing {
+                "Finishing Recording"
+            } else {
+                "Recording in Progress"
+            });
+
+            ui.add_space(20.0);
+            // The clock would keep running through post-processing
+            if !finishing {
+                ui.heading(RichText::new(elapsed_str).size(30.0));
+            }
+            if let Some(interval) = self.recording_timelapse.filter(|_| !finishing) {
+                let length = timelapse_length(elapsed, interval);
+                ui.label(format!(
+                    "Timelapse output: {}",
+                    format_duration(length.as_secs_f64())
+                ));
+            }
+            ui.add_space(20.0);
+
+            // Pulsating record icon
+            let time = ui.input(|i| i.time);
+            let pulse = (time.sin() * 0.5 + 0.5) as f32;
+            let color = Color32::from_rgba_premultiplied(
+                255,
+                (40.0 + 60.0 * (1.0 - pulse)) as u8,
+                (40.0 + 60.0 * (1.0 - pulse)) as u8,
+                255,
+            );
+
+            if finishing {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Post-processing the recording...");
+                });
+            } else if paused {
+                ui.label(RichText::new("⏸ PAUSED").size(24.0));
+            } else {
+                ui.label(RichText::new("⚫ RECORDING").color(color).size(24.0));
+            }
+
+            ui.add_space(30.0);
+            ui.add_enabled_ui(!finishing, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Stop Recording").clicked() {
+                        self.stop_recording();
+                    }
+
+                    // Only manual-stop recordings are split into segments
+                    if self.recording_pause_flag.is_some() {
+                        let label = if paused { "Resume" } else { "Pause" };
+                        if ui.button(label).clicked() {
+                            self.toggle_pause();
+                        }
+                    }
+
+                    if ui.button("Mark").clicked() {
+                        self.add_mark();
+                    }
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.recording_mark_label)
+                            .hint_text("Chapter label")
+                            .desired_width(140.0),
+                    )
+                    .on_hover_text("Each mark starts a chapter of the recording");
+                });
+            });
+
+            if !self.recording_marks.is_empty() {
+                let marks: Vec<String> = self
+                    .recording_marks
+                    .iter()
+                    .map(|mark| match &mark.label {
+                        Some(label) => format!("{} {}", format_duration(mark.t), label),
+                        None => format_duration(mark.t),
+                    })
+                    .collect();
+                ui.label(format!("Marks: {}", marks.join(", ")));
+            }
+
+            ui.add_space(10.0);
+            if let Some(output_path) = &self.recording_output_path {
+                ui.label(format!("Output file: {}", output_path));
+            }
+
+            if self.preview_frames.is_some() {
+                ui.add_space(10.0);
+                self.show_preview_image(ui, ctx);
+            }
+
+            // Show the most recent logs
+            ui.add_space(20.0);
+            egui::ScrollArea::vertical()
+                .max_height(150.0)
+                .show(ui, |ui| {
+                    ui.heading("Status");
+                    for log in self.logs.iter().rev().take(10).rev() {
+                        ui.label(log);
+                    }
+                });
+        });
+
+        if let Some(receiver) = &self.recording_done_receiver {
+            if let Ok(outcome) = receiver.try_recv() {
+                self.finish_recording(outcome);
+                ctx.request_repaint();
+            }
+        }
+    }
+
+    // Capture has stopped but the recording thread is still post-processing
+    fn recording_finishing(&self) -> bool {
+        self.recording_stop_flag
+            .as_ref()
+            .is_some_and(|running| !running.load(Ordering::SeqCst))
+    }
+
+    // Leave the recording screen once the recording thread is done with the file
+    fn finish_recording(&mut self, outcome: Result<Vec<PathBuf>, String>) {
+        // Whatever the thread logged last
+        if let Some(log_receiver) = &self.recording_log_receiver {
+            while let Ok(log) = log_receiver.try_recv() {
+                self.logs.push(log);
+            }
+        }
+
+        let copies = match outcome {
+            Ok(copies) => copies,
+            Err(e) => {
+                self.logs.push(format!("Recording failed: {}", e));
+                Vec::new()
+            }
+        };
+
+        // Even after a failed post-processing step the recording itself may be fine
+        if let Some(output_path) = self.recording_output_path.clone() {
+            if let Ok(info) = verify_video_file(&SystemRunner, &output_path) {
+                self.logs
+                    .push(format!("Recording completed successfully: {}", output_path));
+                self.last_media_info = Some(info);
+
+                // Set the input video path to the recording for easy conversion
+                self.set_conversion_input(&output_path);
+                self.add_to_library(Path::new(&output_path));
+            }
+        }
+        for copy in copies {
+            self.add_to_library(&copy);
+        }
+
+        // Clean up recording state
+        self.state = AppState::Main;
+        self.recording_start_time = None;
+        self.recording_stop_flag = None;
+        self.recording_log_receiver = None;
+        self.recording_done_receiver = None;
+        self.recording_output_path = None;
+        self.recording_pause_flag = None;
+        self.recording_paused_at = None;
+        self.recording_mark_sender = None;
+    }
+
+    pub fn show_converting_screen(&mut self, ui: &mut Ui) {
+        // Flag to track if we should transition to main screen
+        let mut should_transition = false;
+        let time = ui.input(|i| i.time);
 
         // Process any incoming logs
         if let Some(log_receiver) = &self.converting_log_receiver {
@@ -405,59 +1393,1080 @@ impl RcrdrApp {
             }
         }
 
-        // Check if we should transition after processing logs
-        if self.converting_progress >= 1.0 && time > 1.0 {
-            // Allow a few seconds for user to see the completion message
-            if !should_transition {
-                should_transition = true;
+        // Check if we should transition after processing logs
+        if self.converting_progress >= 1.0 && time > 1.0 {
+            // Allow a few seconds for user to see the completion message
+            if !should_transition {
+                should_transition = true;
+            }
+        }
+
+        ui.vertical_centered(|ui| {
+            ui.heading("Converting Video to GIF");
+            ui.add_space(20.0);
+
+            // Progress bar
+            ui.add(
+                egui::ProgressBar::new(self.converting_progress)
+                    .show_percentage()
+                    .animate(true),
+            );
+
+            ui.add_space(20.0);
+
+            if self.converting_progress >= 1.0 {
+                ui.label(
+                    RichText::new("Conversion complete!")
+                        .color(Color32::GREEN)
+                        .size(18.0),
+                );
+                ui.label("Returning to main screen...");
+            } else {
+                ui.label("This may take a while depending on the video length...");
+            }
+
+            // Show the most recent logs
+            ui.add_space(20.0);
+            egui::ScrollArea::vertical()
+                .max_height(150.0)
+                .show(ui, |ui| {
+                    ui.heading("Status");
+                    for log in self.logs.iter().rev().take(10).rev() {
+                        ui.label(log);
+                    }
+                });
+        });
+
+        // Handle transition after UI is drawn
+        if should_transition {
+            // Add a delay before transitioning to ensure user sees completion
+            thread::sleep(Duration::from_millis(1500));
+
+            self.state = AppState::Main;
+            self.converting_log_receiver = None;
+            self.converting_progress = 0.0;
+        }
+    }
+
+    pub fn open_trim_editor(&mut self, path: &Path) {
+        let info = match probe(&SystemRunner, path) {
+            Ok(info) if info.duration_secs > 0.0 => info,
+            Ok(_) => {
+                self.logs
+                    .push(format!("{} has no duration to trim", path.display()));
+                return;
+            }
+            Err(e) => {
+                self.logs.push(e.to_string());
+                return;
+            }
+        };
+        let duration = info.duration_secs;
+        let fps = info.video_stream().and_then(|stream| stream.fps);
+
+        self.trim_input = Some(path.to_path_buf());
+        self.trim_output = trimmed_path(path).to_string_lossy().to_string();
+        self.trim_duration = duration;
+        self.trim_frame_secs = 1.0 / fps.unwrap_or(30.0);
+        self.trim_playhead = 0.0;
+        self.trim_in = 0.0;
+        self.trim_out = duration;
+        self.trim_timeline = None;
+        self.trim_frames.clear();
+        self.trim_log_receiver = None;
+        self.trim_export_receiver = None;
+
+        // Keyframes and timeline frames load in the background
+        let (tx, rx) = channel();
+        let input = path.to_path_buf();
+        thread::spawn(move || {
+            let timeline = load_timeline(
+                &SystemRunner,
+                &input,
+                duration,
+                &trim_timeline_dir(),
+                TRIM_TIMELINE_FRAMES,
+            );
+            let _ = tx.send(timeline.map_err(|e| e.to_string()));
+        });
+        self.trim_timeline_receiver = Some(rx);
+
+        self.state = AppState::Trim;
+    }
+
+    fn close_trim_editor(&mut self) {
+        self.trim_input = None;
+        self.trim_timeline = None;
+        self.trim_timeline_receiver = None;
+        self.trim_frames.clear();
+        let _ = std::fs::remove_dir_all(trim_timeline_dir());
+        self.state = AppState::Main;
+    }
+
+    pub fn show_trim_screen(&mut self, ui: &mut Ui, ctx: &Context) {
+        let input = match self.trim_input.clone() {
+            Some(input) => input,
+            None => {
+                self.state = AppState::Main;
+                return;
+            }
+        };
+
+        if let Some(receiver) = &self.trim_timeline_receiver {
+            if let Ok(result) = receiver.try_recv() {
+                match result {
+                    Ok(timeline) => {
+                        self.trim_frames = timeline
+                            .frames
+                            .iter()
+                            .filter_map(|frame| load_texture(ctx, frame))
+                            .collect();
+                        self.trim_timeline = Some(timeline);
+                    }
+                    Err(e) => self.logs.push(e),
+                }
+                self.trim_timeline_receiver = None;
+            }
+        }
+        if let Some(log_receiver) = &self.trim_log_receiver {
+            while let Ok(log) = log_receiver.try_recv() {
+                self.logs.push(log);
+            }
+        }
+        if let Some(receiver) = &self.trim_export_receiver {
+            if let Ok(success) = receiver.try_recv() {
+                self.trim_export_receiver = None;
+                if success {
+                    self.add_to_library(Path::new(&self.trim_output.clone()));
+                }
+            }
+        }
+
+        // Arrow keys step by a frame, or a second with Shift. I and O set the markers.
+        if !ctx.wants_keyboard_input() {
+            ctx.input(|input| {
+                let step = if input.modifiers.shift {
+                    1.0
+                } else {
+                    self.trim_frame_secs
+                };
+                if input.key_pressed(egui::Key::ArrowLeft) {
+                    self.trim_playhead -= step;
+                }
+                if input.key_pressed(egui::Key::ArrowRight) {
+                    self.trim_playhead += step;
+                }
+                if input.key_pressed(egui::Key::Home) {
+                    self.trim_playhead = 0.0;
+                }
+                if input.key_pressed(egui::Key::End) {
+                    self.trim_playhead = self.trim_duration;
+                }
+            });
+            if ctx.input(|input| input.key_pressed(egui::Key::I)) {
+                self.set_trim_in();
+            }
+            if ctx.input(|input| input.key_pressed(egui::Key::O)) {
+                self.set_trim_out();
+            }
+        }
+        self.trim_playhead = self.trim_playhead.clamp(0.0, self.trim_duration);
+
+        ui.heading("Trim Recording");
+        ui.label(input.display().to_string());
+        ui.add_space(10.0);
+
+        self.show_trim_timeline(ui);
+
+        ui.add_space(5.0);
+        ui.add(
+            egui::Slider::new(&mut self.trim_playhead, 0.0..=self.trim_duration).show_value(false),
+        );
+
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "Playhead: {}",
+                format_timestamp(self.trim_playhead)
+            ));
+            ui.separator();
+            ui.label(format!("In: {}", format_timestamp(self.trim_in)));
+            ui.label(format!("Out: {}", format_timestamp(self.trim_out)));
+            ui.label(format!(
+                "Length: {}",
+                format_timestamp(self.trim_out - self.trim_in)
+            ));
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Set In (I)").clicked() {
+                self.set_trim_in();
+            }
+            if ui.button("Set Out (O)").clicked() {
+                self.set_trim_out();
+            }
+
+            // Moving the in point back to a keyframe allows a stream copy
+            let previous_keyframe = self.trim_timeline.as_ref().and_then(|timeline| {
+                timeline
+                    .keyframes
+                    .iter()
+                    .rev()
+                    .find(|keyframe| **keyframe <= self.trim_in + self.trim_frame_secs / 2.0)
+                    .copied()
+            });
+            ui.add_enabled_ui(previous_keyframe.is_some(), |ui| {
+                if ui.button("Snap In to keyframe").clicked() {
+                    if let Some(keyframe) = previous_keyframe {
+                        self.trim_in = keyframe;
+                    }
+                }
+            });
+        });
+        ui.label(
+            RichText::new("←/→ step one frame, Shift+←/→ one second, Home/End jump to the ends")
+                .small(),
+        );
+
+        ui.add_space(10.0);
+
+        let mode = match &self.trim_timeline {
+            Some(timeline) => TrimMode::for_cut(&timeline.keyframes, self.trim_in),
+            None => TrimMode::Reencode,
+        };
+        if self.trim_timeline_receiver.is_some() {
+            ui.label("Loading keyframes and timeline...");
+            ctx.request_repaint_after(Duration::from_millis(100));
+        } else {
+            ui.label(match mode {
+                TrimMode::Copy => {
+                    "The in point is on a keyframe, the cut is copied without re-encoding."
+                }
+                TrimMode::Reencode => {
+                    "The in point is between keyframes, the cut will be re-encoded."
+                }
+            });
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Output:");
+            ui.text_edit_singleline(&mut self.trim_output);
+        });
+
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            let exporting = self.trim_export_receiver.is_some();
+            let can_export = !exporting
+                && self.trim_timeline_receiver.is_none()
+                && self.trim_in < self.trim_out
+                && !self.trim_output.trim().is_empty()
+                && Path::new(self.trim_output.trim()) != input;
+            ui.add_enabled_ui(can_export, |ui| {
+                if ui.button("Export").clicked() {
+                    self.start_trim_export(mode);
+                }
+            });
+            if exporting {
+                ui.spinner();
+                ctx.request_repaint_after(Duration::from_millis(100));
+            }
+
+            if ui.button("Close").clicked() {
+                self.close_trim_editor();
+            }
+        });
+
+        ui.add_space(10.0);
+        egui::ScrollArea::vertical()
+            .max_height(150.0)
+            .show(ui, |ui| {
+                for log in self.logs.iter().rev().take(10).rev() {
+                    ui.label(log);
+                }
+            });
+    }
+
+    // Frames along the video, the part outside the cut dimmed, keyframe ticks
+    // at the bottom. Clicking or dragging moves the playhead.
+    fn show_trim_timeline(&mut self, ui: &mut Ui) {
+        let (rect, response) = ui.allocate_exact_size(
+            egui::vec2(ui.available_width(), 90.0),
+            egui::Sense::click_and_drag(),
+        );
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, Color32::BLACK);
+
+        if !self.trim_frames.is_empty() {
+            let width = rect.width() / self.trim_frames.len() as f32;
+            let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+            for (i, texture) in self.trim_frames.iter().enumerate() {
+                let tile = egui::Rect::from_min_size(
+                    rect.min + egui::vec2(i as f32 * width, 0.0),
+                    egui::vec2(width, rect.height()),
+                );
+                painter.image(texture.id(), tile, uv, Color32::WHITE);
+            }
+        }
+
+        let duration = self.trim_duration;
+        let x = |t: f64| rect.left() + (t / duration) as f32 * rect.width();
+
+        let dim = Color32::from_black_alpha(170);
+        painter.rect_filled(
+            egui::Rect::from_x_y_ranges(rect.left()..=x(self.trim_in), rect.y_range()),
+            0.0,
+            dim,
+        );
+        painter.rect_filled(
+            egui::Rect::from_x_y_ranges(x(self.trim_out)..=rect.right(), rect.y_range()),
+            0.0,
+            dim,
+        );
+
+        if let Some(timeline) = &self.trim_timeline {
+            for keyframe in &timeline.keyframes {
+                painter.line_segment(
+                    [
+                        egui::pos2(x(*keyframe), rect.bottom() - 8.0),
+                        egui::pos2(x(*keyframe), rect.bottom()),
+                    ],
+                    egui::Stroke::new(1.0, Color32::LIGHT_BLUE),
+                );
+            }
+        }
+
+        for (t, color) in [
+            (self.trim_in, Color32::GREEN),
+            (self.trim_out, Color32::RED),
+            (self.trim_playhead, Color32::WHITE),
+        ] {
+            painter.line_segment(
+                [
+                    egui::pos2(x(t), rect.top()),
+                    egui::pos2(x(t), rect.bottom()),
+                ],
+                egui::Stroke::new(2.0, color),
+            );
+        }
+
+        if let Some(pointer) = response.interact_pointer_pos() {
+            let fraction = ((pointer.x - rect.left()) / rect.width()).clamp(0.0, 1.0);
+            self.trim_playhead = fraction as f64 * duration;
+        }
+    }
+
+    fn set_trim_in(&mut self) {
+        self.trim_in = self.trim_playhead;
+        if self.trim_out <= self.trim_in {
+            self.trim_out = self.trim_duration;
+        }
+    }
+
+    fn set_trim_out(&mut self) {
+        self.trim_out = self.trim_playhead;
+        if self.trim_in >= self.trim_out {
+            self.trim_in = 0.0;
+        }
+    }
+
+    fn start_trim_export(&mut self, mode: TrimMode) {
+        let input = match &self.trim_input {
+            Some(input) => input.clone(),
+            None => return,
+        };
+        let output = PathBuf::from(self.trim_output.trim());
+        let (from, to) = (self.trim_in, self.trim_out);
+        let watermark = match self.export_watermark() {
+            Ok(watermark) => watermark,
+            Err(e) => {
+                self.logs.push(e);
+                return;
+            }
+        };
+        // Drawing the watermark needs decoded frames
+        let mode = if watermark.is_some() {
+            TrimMode::Reencode
+        } else {
+            mode
+        };
+
+        // Set up log channel
+        let (tx, rx) = channel();
+        let (done_tx, done_rx) = channel();
+
+        thread::spawn(move || {
+            let result = trim_gui(
+                &SystemRunner,
+                &input,
+                &output,
+                from,
+                to,
+                mode,
+                watermark.as_ref(),
+                tx,
+            );
+            if let Err(e) = &result {
+                eprintln!("Trim error: {}", e);
+            }
+            let _ = done_tx.send(result.is_ok());
+        });
+
+        self.trim_log_receiver = Some(rx);
+        self.trim_export_receiver = Some(done_rx);
+    }
+
+    pub fn open_crop_editor(&mut self, path: &Path) {
+        let size = match probe(&SystemRunner, path) {
+            Ok(info) => info
+                .video_stream()
+                .and_then(|stream| Some((stream.width?, stream.height?))),
+            Err(e) => {
+                self.logs.push(e.to_string());
+                return;
+            }
+        };
+        let (width, height) = match size {
+            Some(size) => size,
+            None => {
+                self.logs
+                    .push(format!("{} has no video to crop", path.display()));
+                return;
+            }
+        };
+
+        self.crop_input = Some(path.to_path_buf());
+        self.crop_output = cropped_path(path).to_string_lossy().to_string();
+        self.crop_size = (width, height);
+        self.crop_rect = None;
+        self.crop_rect_input.clear();
+        self.crop_scale_input.clear();
+        self.crop_drag_start = None;
+        self.crop_frame = None;
+        self.crop_log_receiver = None;
+        self.crop_export_receiver = None;
+
+        // A full size frame to draw the rectangle on, extracted in the background
+        let (tx, rx) = channel();
+        let input = path.to_path_buf();
+        thread::spawn(move || {
+            let frame = crop_frame_path();
+            let result = extract_poster(&SystemRunner, &input, &frame, None, width);
+            let _ = tx.send(result.map(|_| frame).map_err(|e| e.to_string()));
+        });
+        self.crop_frame_receiver = Some(rx);
+
+        self.state = AppState::Crop;
+    }
+
+    fn close_crop_editor(&mut self) {
+        self.crop_input = None;
+        self.crop_frame = None;
+        self.crop_frame_receiver = None;
+        let _ = std::fs::remove_file(crop_frame_path());
+        self.state = AppState::Main;
+    }
+
+    pub fn show_crop_screen(&mut self, ui: &mut Ui, ctx: &Context) {
+        let input = match self.crop_input.clone() {
+            Some(input) => input,
+            None => {
+                self.state = AppState::Main;
+                return;
+            }
+        };
+
+        if let Some(receiver) = &self.crop_frame_receiver {
+            if let Ok(result) = receiver.try_recv() {
+                match result {
+                    Ok(frame) => self.crop_frame = load_texture(ctx, &frame),
+                    Err(e) => self.logs.push(e),
+                }
+                self.crop_frame_receiver = None;
+            }
+        }
+        if let Some(log_receiver) = &self.crop_log_receiver {
+            while let Ok(log) = log_receiver.try_recv() {
+                self.logs.push(log);
+            }
+        }
+        if let Some(receiver) = &self.crop_export_receiver {
+            if let Ok(success) = receiver.try_recv() {
+                self.crop_export_receiver = None;
+                if success {
+                    self.add_to_library(Path::new(&self.crop_output.clone()));
+                }
+            }
+        }
+
+        let (width, height) = self.crop_size;
+        ui.heading("Crop Recording");
+        ui.label(format!("{} ({}x{})", input.display(), width, height));
+        ui.add_space(10.0);
+
+        if self.crop_frame_receiver.is_some() {
+            ui.label("Loading a frame...");
+            ctx.request_repaint_after(Duration::from_millis(100));
+        } else {
+            self.show_crop_frame(ui);
+            ui.label(RichText::new("Drag across the frame to draw the crop rectangle").small());
+        }
+
+        ui.add_space(10.0);
+        // The same geometry syntax as region capture
+        let rect_error = ui
+            .horizontal(|ui| {
+                ui.label("Rectangle:");
+                let edit = ui.add(
+                    egui::TextEdit::singleline(&mut self.crop_rect_input)
+                        .hint_text("WIDTHxHEIGHT+X+Y"),
+                );
+                if ui.button("Clear").clicked() {
+                    self.crop_rect_input.clear();
+                }
+
+                let input = self.crop_rect_input.trim();
+                let parsed = if input.is_empty() {
+                    Ok(None)
+                } else {
+                    input
+                        .parse::<Geometry>()
+                        .and_then(|rect| check_rect(&rect, width, height).map(|_| Some(rect)))
+                };
+                if edit.changed() || input.is_empty() {
+                    if let Ok(rect) = &parsed {
+                        self.crop_rect = *rect;
+                    }
+                }
+                parsed.err()
+            })
+            .inner;
+        if let Some(error) = &rect_error {
+            ui.colored_label(Color32::RED, error);
+        }
+
+        let scale = ui
+            .horizontal(|ui| {
+                ui.label("Scale:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.crop_scale_input)
+                        .hint_text("1280, 1280x720 or 50%"),
+                );
+                let input = self.crop_scale_input.trim();
+                if input.is_empty() {
+                    Ok(None)
+                } else {
+                    input.parse::<ScaleSpec>().map(Some)
+                }
+            })
+            .inner;
+        if let Err(error) = &scale {
+            ui.colored_label(Color32::RED, error);
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Output:");
+            ui.text_edit_singleline(&mut self.crop_output);
+        });
+
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            let exporting = self.crop_export_receiver.is_some();
+            let scale = scale.ok().flatten();
+            let can_export = !exporting
+                && rect_error.is_none()
+                && (self.crop_rect.is_some() || scale.is_some())
+                && !self.crop_output.trim().is_empty()
+                && Path::new(self.crop_output.trim()) != input;
+            ui.add_enabled_ui(can_export, |ui| {
+                if ui.button("Export").clicked() {
+                    self.start_crop_export(scale);
+                }
+            });
+            if exporting {
+                ui.spinner();
+                ctx.request_repaint_after(Duration::from_millis(100));
+            }
+
+            if ui.button("Close").clicked() {
+                self.close_crop_editor();
+            }
+        });
+
+        ui.add_space(10.0);
+        egui::ScrollArea::vertical()
+            .max_height(150.0)
+            .show(ui, |ui| {
+                for log in self.logs.iter().rev().take(10).rev() {
+                    ui.label(log);
+                }
+            });
+    }
+
+    // The frame with everything outside the crop rectangle dimmed. Dragging
+    // draws a new rectangle.
+    fn show_crop_frame(&mut self, ui: &mut Ui) {
+        let (view, response) = FrameView::show(ui, self.crop_frame.as_ref(), self.crop_size);
+
+        if let Some(crop) = view.drag_rectangle(&response, &mut self.crop_drag_start) {
+            self.crop_rect = Some(crop);
+            self.crop_rect_input = crop.to_string();
+        }
+
+        if let Some(crop) = self.crop_rect {
+            let inner = view.screen_rect(&crop);
+            let outer = view.rect;
+            let painter = ui.painter_at(outer);
+            let dim = Color32::from_black_alpha(170);
+            for outside in [
+                egui::Rect::from_x_y_ranges(outer.x_range(), outer.top()..=inner.top()),
+                egui::Rect::from_x_y_ranges(outer.x_range(), inner.bottom()..=outer.bottom()),
+                egui::Rect::from_x_y_ranges(outer.left()..=inner.left(), inner.y_range()),
+                egui::Rect::from_x_y_ranges(inner.right()..=outer.right(), inner.y_range()),
+            ] {
+                painter.rect_filled(outside, 0.0, dim);
+            }
+            painter.rect_stroke(inner, 0.0, egui::Stroke::new(2.0, Color32::YELLOW));
+        }
+    }
+
+    fn start_crop_export(&mut self, scale: Option<ScaleSpec>) {
+        let input = match &self.crop_input {
+            Some(input) => input.clone(),
+            None => return,
+        };
+        let output = PathBuf::from(self.crop_output.trim());
+        let rect = self.crop_rect;
+        let watermark = match self.export_watermark() {
+            Ok(watermark) => watermark,
+            Err(e) => {
+                self.logs.push(e);
+                return;
+            }
+        };
+
+        // Set up log channel
+        let (tx, rx) = channel();
+        let (done_tx, done_rx) = channel();
+
+        thread::spawn(move || {
+            let result = crop_gui(
+                &SystemRunner,
+                &input,
+                &output,
+                rect,
+                scale,
+                watermark.as_ref(),
+                tx,
+            );
+            if let Err(e) = &result {
+                eprintln!("Crop error: {}", e);
+            }
+            let _ = done_tx.send(result.is_ok());
+        });
+
+        self.crop_log_receiver = Some(rx);
+        self.crop_export_receiver = Some(done_rx);
+    }
+
+    pub fn open_redact_editor(&mut self, path: &Path) {
+        let info = match probe(&SystemRunner, path) {
+            Ok(info) => info,
+            Err(e) => {
+                self.logs.push(e.to_string());
+                return;
+            }
+        };
+        let size = info
+            .video_stream()
+            .and_then(|stream| Some((stream.width?, stream.height?)));
+        let size = match size {
+            Some(size) if info.duration_secs > 0.0 => size,
+            _ => {
+                self.logs
+                    .push(format!("{} has no video to redact", path.display()));
+                return;
+            }
+        };
+
+        // Pick up the boxes saved for this video last time
+        let spec_path = redact_spec_path(path);
+        let regions = if spec_path.exists() {
+            match RedactSpec::load(&spec_path) {
+                Ok(spec) => spec.regions,
+                Err(e) => {
+                    self.logs.push(e.to_string());
+                    Vec::new()
+                }
+            }
+        } else {
+            Vec::new()
+        };
+
+        self.redact_input = Some(path.to_path_buf());
+        self.redact_output = redacted_path(path).to_string_lossy().to_string();
+        self.redact_size = size;
+        self.redact_duration = info.duration_secs;
+        self.redact_time = 0.0;
+        self.redact_regions = regions;
+        self.redact_selected = None;
+        self.redact_drag_start = None;
+        self.redact_drawing = None;
+        self.redact_frame = None;
+        self.redact_log_receiver = None;
+        self.redact_export_receiver = None;
+        self.load_redact_frame();
+
+        self.state = AppState::Redact;
+    }
+
+    // Extract the frame at `redact_time` in the background
+    fn load_redact_frame(&mut self) {
+        let input = match &self.redact_input {
+            Some(input) => input.clone(),
+            None => return,
+        };
+        let (at, width) = (self.redact_time, self.redact_size.0);
+
+        let (tx, rx) = channel();
+        thread::spawn(move || {
+            let frame = redact_frame_path(at);
+            let result = extract_poster(&SystemRunner, &input, &frame, Some(at), width)
+                .map(|_| frame.clone())
+                .map_err(|e| e.to_string());
+            // Nobody waits for it once a newer frame was asked for
+            if tx.send(result).is_err() {
+                let _ = std::fs::remove_file(frame);
+            }
+        });
+        self.redact_frame_receiver = Some(rx);
+    }
+
+    fn close_redact_editor(&mut self) {
+        self.redact_input = None;
+        self.redact_regions.clear();
+        self.redact_frame = None;
+        self.redact_frame_receiver = None;
+        self.state = AppState::Main;
+    }
+
+    pub fn show_redact_screen(&mut self, ui: &mut Ui, ctx: &Context) {
+        let input = match self.redact_input.clone() {
+            Some(input) => input,
+            None => {
+                self.state = AppState::Main;
+                return;
+            }
+        };
+
+        if let Some(receiver) = &self.redact_frame_receiver {
+            if let Ok(result) = receiver.try_recv() {
+                match result {
+                    Ok(frame) => {
+                        self.redact_frame = load_texture(ctx, &frame);
+                        let _ = std::fs::remove_file(frame);
+                    }
+                    Err(e) => self.logs.push(e),
+                }
+                self.redact_frame_receiver = None;
+            }
+        }
+        if let Some(log_receiver) = &self.redact_log_receiver {
+            while let Ok(log) = log_receiver.try_recv() {
+                self.logs.push(log);
+            }
+        }
+        if let Some(receiver) = &self.redact_export_receiver {
+            if let Ok(success) = receiver.try_recv() {
+                self.redact_export_receiver = None;
+                if success {
+                    self.add_to_library(Path::new(&self.redact_output.clone()));
+                }
+            }
+        }
+
+        ui.heading("Redact Recording");
+        ui.label(input.display().to_string());
+        ui.add_space(10.0);
+
+        self.show_redact_frame(ui);
+        ui.label(
+            RichText::new(
+                "Drag across the frame to add a box from this time on, click a box to select it",
+            )
+            .small(),
+        );
+
+        ui.horizontal(|ui| {
+            let slider = ui.add(
+                egui::Slider::new(&mut self.redact_time, 0.0..=self.redact_duration)
+                    .show_value(false),
+            );
+            ui.label(format_timestamp(self.redact_time));
+            // Only once the slider is let go, extracting a frame takes a moment
+            if slider.drag_released() || (slider.changed() && !slider.dragged()) {
+                self.load_redact_frame();
+            }
+            if self.redact_frame_receiver.is_some() {
+                ui.spinner();
+                ctx.request_repaint_after(Duration::from_millis(100));
+            }
+        });
+
+        ui.add_space(10.0);
+        self.show_redact_regions(ui);
+
+        ui.add_space(10.0);
+        let spec = RedactSpec {
+            regions: self.redact_regions.clone(),
+        };
+        let (width, height) = self.redact_size;
+        let problem = spec.check(width, height, self.redact_duration).err();
+        if let Some(problem) = &problem {
+            ui.colored_label(Color32::RED, problem);
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Output:");
+            ui.text_edit_singleline(&mut self.redact_output);
+        });
+
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            let exporting = self.redact_export_receiver.is_some();
+            let can_export = !exporting
+                && problem.is_none()
+                && !self.redact_output.trim().is_empty()
+                && Path::new(self.redact_output.trim()) != input;
+            ui.add_enabled_ui(can_export, |ui| {
+                if ui.button("Export").clicked() {
+                    self.start_redact_export(spec.clone());
+                }
+            });
+            if exporting {
+                ui.spinner();
+                ctx.request_repaint_after(Duration::from_millis(100));
+            }
+
+            let spec_path = redact_spec_path(&input);
+            if ui
+                .button("Save Boxes")
+                .on_hover_text(format!(
+                    "Save to {}, for `rcrdr redact --spec` and to edit again later",
+                    spec_path.display()
+                ))
+                .clicked()
+            {
+                match spec.save(&spec_path) {
+                    Ok(()) => self
+                        .logs
+                        .push(format!("Saved the boxes to {}", spec_path.display())),
+                    Err(e) => self.logs.push(format!("Could not save the boxes: {}", e)),
+                }
+            }
+
+            if ui.button("Close").clicked() {
+                self.close_redact_editor();
+            }
+        });
+
+        ui.add_space(10.0);
+        egui::ScrollArea::vertical()
+            .max_height(150.0)
+            .show(ui, |ui| {
+                for log in self.logs.iter().rev().take(10).rev() {
+                    ui.label(log);
+                }
+            });
+    }
+
+    // The paused frame with the boxes over it: filled while they are active at
+    // this time, outlined otherwise, the selected one in yellow
+    fn show_redact_frame(&mut self, ui: &mut Ui) {
+        let (view, response) = FrameView::show(ui, self.redact_frame.as_ref(), self.redact_size);
+
+        if let Some(drawing) = view.drag_rectangle(&response, &mut self.redact_drag_start) {
+            self.redact_drawing = Some(drawing);
+        }
+        if response.drag_released() {
+            if let Some(drawing) = self.redact_drawing.take() {
+                self.redact_regions
+                    .push(Redaction::new(drawing, self.redact_time));
+                self.redact_selected = Some(self.redact_regions.len() - 1);
+            }
+        }
+        if response.clicked() {
+            let pointer = response
+                .interact_pointer_pos()
+                .map(|pos| view.to_video(pos));
+            self.redact_selected = pointer.and_then(|(x, y)| {
+                self.redact_regions.iter().rposition(|region| {
+                    let rect = region.rect;
+                    (rect.x..rect.x + rect.width).contains(&x)
+                        && (rect.y..rect.y + rect.height).contains(&y)
+                })
+            });
+        }
+
+        let painter = ui.painter_at(view.rect);
+        for (i, region) in self.redact_regions.iter().enumerate() {
+            let rect = view.screen_rect(&region.rect);
+            let selected = self.redact_selected == Some(i);
+            if region.is_active(self.redact_time) {
+                painter.rect_filled(rect, 0.0, Color32::from_rgba_unmultiplied(255, 0, 0, 90));
+            }
+            let color = if selected {
+                Color32::YELLOW
+            } else if region.is_active(self.redact_time) {
+                Color32::RED
+            } else {
+                Color32::GRAY
+            };
+            painter.rect_stroke(rect, 0.0, egui::Stroke::new(2.0, color));
+            painter.text(
+                rect.left_top() + egui::vec2(4.0, 2.0),
+                egui::Align2::LEFT_TOP,
+                (i + 1).to_string(),
+                egui::FontId::proportional(14.0),
+                color,
+            );
+        }
+        if let Some(drawing) = self.redact_drawing {
+            painter.rect_stroke(
+                view.screen_rect(&drawing),
+                0.0,
+                egui::Stroke::new(2.0, Color32::YELLOW),
+            );
+        }
+    }
+
+    fn show_redact_regions(&mut self, ui: &mut Ui) {
+        if self.redact_regions.is_empty() {
+            ui.label("No boxes yet.");
+            return;
+        }
+
+        let time = self.redact_time;
+        let duration = self.redact_duration;
+        let mut delete = None;
+        egui::ScrollArea::vertical()
+            .id_source("redact_regions")
+            .max_height(200.0)
+            .show(ui, |ui| {
+                egui::Grid::new("redact_regions_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (i, region) in self.redact_regions.iter_mut().enumerate() {
+                            let selected = self.redact_selected == Some(i);
+                            if ui
+                                .selectable_label(selected, format!("{}: {}", i + 1, region.rect))
+                                .clicked()
+                            {
+                                self.redact_selected = Some(i);
+                            }
+
+                            ui.horizontal(|ui| {
+                                ui.label("From");
+                                ui.add(
+                                    egui::DragValue::new(&mut region.start)
+                                        .speed(0.1)
+                                        .clamp_range(0.0..=duration)
+                                        .suffix(" s"),
+                                );
+                                if ui.small_button("Now").clicked() {
+                                    region.start = time;
+                                }
+                            });
+
+                            ui.horizontal(|ui| {
+                                let mut until_end = region.end.is_none();
+                                if ui.checkbox(&mut until_end, "to the end").changed() {
+                                    region.end = if until_end {
+                                        None
+                                    } else {
+                                        Some(duration.max(region.start))
+                                    };
+                                }
+                                if let Some(end) = &mut region.end {
+                                    ui.label("to");
+                                    ui.add(
+                                        egui::DragValue::new(end)
+                                            .speed(0.1)
+                                            .clamp_range(0.0..=duration)
+                                            .suffix(" s"),
+                                    );
+                                    if ui.small_button("Now").clicked() {
+                                        *end = time;
+                                    }
+                                }
+                            });
+
+                            egui::ComboBox::from_id_source(("redact_style", i))
+                                .selected_text(region.style.label())
+                                .show_ui(ui, |ui| {
+                                    for style in RedactStyle::ALL {
+                                        ui.selectable_value(
+                                            &mut region.style,
+                                            style,
+                                            style.label(),
+                                        );
+                                    }
+                                });
+                            ui.add(
+                                egui::DragValue::new(&mut region.strength)
+                                    .clamp_range(1..=64)
+                                    .suffix(" px"),
+                            )
+                            .on_hover_text("Blur radius, or the size of the pixel blocks");
+
+                            if ui.button("Delete").clicked() {
+                                delete = Some(i);
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        if let Some(i) = delete {
+            self.redact_regions.remove(i);
+            self.redact_selected = None;
+        }
+    }
+
+    fn start_redact_export(&mut self, spec: RedactSpec) {
+        let input = match &self.redact_input {
+            Some(input) => input.clone(),
+            None => return,
+        };
+        let output = PathBuf::from(self.redact_output.trim());
+        let watermark = match self.export_watermark() {
+            Ok(watermark) => watermark,
+            Err(e) => {
+                self.logs.push(e);
+                return;
             }
-        }
+        };
 
-        ui.vertical_centered(|ui| {
-            ui.heading("Converting Video to GIF");
-            ui.add_space(20.0);
+        // Set up log channel
+        let (tx, rx) = channel();
+        let (done_tx, done_rx) = channel();
 
-            // Progress bar
-            ui.add(
-                egui::ProgressBar::new(self.converting_progress)
-                    .show_percentage()
-                    .animate(true),
+        thread::spawn(move || {
+            let result = redact_gui(
+                &SystemRunner,
+                &input,
+                &output,
+                &spec,
+                watermark.as_ref(),
+                tx,
             );
-
-            ui.add_space(20.0);
-
-            if self.converting_progress >= 1.0 {
-                ui.label(
-                    RichText::new("Conversion complete!")
-                        .color(Color32::GREEN)
-                        .size(18.0),
-                );
-                ui.label("Returning to main screen...");
-            } else {
-                ui.label("This may take a while depending on the video length...");
+            if let Err(e) = &result {
+                eprintln!("Redact error: {}", e);
             }
-
-            // Show the most recent logs
-            ui.add_space(20.0);
-            egui::ScrollArea::vertical()
-                .max_height(150.0)
-                .show(ui, |ui| {
-                    ui.heading("Status");
-                    for log in self.logs.iter().rev().take(10).rev() {
-                        ui.label(log);
-                    }
-                });
+            let _ = done_tx.send(result.is_ok());
         });
 
-        // Handle transition after UI is drawn
-        if should_transition {
-            // Add a delay before transitioning to ensure user sees completion
-            thread::sleep(Duration::from_millis(1500));
-
-            self.state = AppState::Main;
-            self.converting_log_receiver = None;
-            self.converting_progress = 0.0;
-        }
+        self.redact_log_receiver = Some(rx);
+        self.redact_export_receiver = Some(done_rx);
     }
 
     pub fn show_testing_screen(&mut self, ui: &mut Ui) {
@@ -531,25 +2540,250 @@ impl RcrdrApp {
         });
     }
 
+    /// Start recording, after the countdown if one is set.
+    pub fn begin_recording(&mut self) {
+        self.countdown_minimize_pending = self.minimize_on_record;
+
+        if self.countdown == 0 {
+            self.start_recording();
+            return;
+        }
+
+        match spawn_countdown_overlay(self.countdown) {
+            Ok(child) => self.countdown_overlay = Some(child),
+            // The countdown in this window still works without the overlay
+            Err(e) => self
+                .logs
+                .push(format!("Failed to show the countdown overlay: {}", e)),
+        }
+
+        self.countdown_deadline = Some(Instant::now() + Duration::from_secs(self.countdown));
+        self.state = AppState::Countdown;
+    }
+
+    pub fn show_countdown_screen(&mut self, ui: &mut Ui) {
+        let deadline = self.countdown_deadline.unwrap_or_else(Instant::now);
+        let remaining = deadline.saturating_duration_since(Instant::now());
+
+        ui.vertical_centered(|ui| {
+            ui.heading("Recording starts in");
+
+            ui.add_space(20.0);
+            ui.heading(
+                RichText::new((remaining.as_secs_f32().ceil() as u64).to_string()).size(60.0),
+            );
+            ui.add_space(30.0);
+
+            if ui.button("Cancel").clicked() {
+                self.cancel_countdown();
+            }
+        });
+
+        if self.state != AppState::Countdown || !remaining.is_zero() {
+            return;
+        }
+
+        // Don't capture the overlay on its way out. It closes itself, but give
+        // up on it if it hangs around for longer than expected.
+        if let Some(overlay) = &mut self.countdown_overlay {
+            let exited = !matches!(overlay.try_wait(), Ok(None));
+            let overdue = deadline.elapsed() > Duration::from_secs(2);
+            if !exited && !overdue {
+                return;
+            }
+            if !exited {
+                let _ = overlay.kill();
+                let _ = overlay.wait();
+            }
+        }
+
+        self.countdown_overlay = None;
+        self.countdown_deadline = None;
+        // start_recording() only moves on to Recording if it succeeds
+        self.state = AppState::Main;
+        self.start_recording();
+    }
+
+    pub fn cancel_countdown(&mut self) {
+        if let Some(mut overlay) = self.countdown_overlay.take() {
+            let _ = overlay.kill();
+            let _ = overlay.wait();
+        }
+
+        self.countdown_deadline = None;
+        self.state = AppState::Main;
+        self.logs.push("Recording cancelled.".to_string());
+    }
+
     pub fn start_recording(&mut self) {
-        let output_path = self.output_path.clone();
+        let settings = match self.record_settings() {
+            Ok(settings) => settings,
+            Err(e) => {
+                self.logs.push(e);
+                return;
+            }
+        };
+
+        let dir = PathBuf::from(self.output_dir.trim());
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            self.logs
+                .push(format!("Failed to create {}: {}", dir.display(), e));
+            return;
+        }
+
+        let context = TemplateContext {
+            profile: self.active_profile.clone(),
+            source: settings.source.clone(),
+        };
+        let output_path =
+            match resolve_output_path(&dir, &self.filename_template, &context, self.overwrite) {
+                Ok(path) => path,
+                Err(e) => {
+                    self.logs.push(e);
+                    return;
+                }
+            };
+        let output_path = output_path.to_string_lossy().to_string();
         let duration = self.duration;
-        let fps = self.fps;
 
         // Set up log channel
         let (tx, rx) = channel();
 
-        // Set up stop flag
+        // Set up stop and pause flags
         let stop_flag = Arc::new(AtomicBool::new(true));
-        let stop_flag_clone = stop_flag.clone();
+        let pause_flag = Arc::new(AtomicBool::new(false));
+
+        // The recording feeds the preview itself, so the source isn't captured twice
+        self.source_preview = None;
+        self.preview_source = None;
+        self.preview_frames = self.show_preview.then(PreviewFrames::default);
+
+        let control = RecordingControl {
+            running: stop_flag.clone(),
+            paused: pause_flag.clone(),
+            preview: self.preview_frames.clone(),
+        };
+
+        // Clicks and keys are logged on the same clock as the recording, paused with it.
+        // The cursor highlight is drawn afterwards from the logged pointer positions.
+        let highlight = settings.cursor == CursorMode::Highlight;
+        let capture = if self.log_events || highlight {
+            match events::capture(pause_flag.clone(), highlight) {
+                Ok(capture) => Some(capture),
+                Err(e) => {
+                    self.logs
+                        .push(format!("Not logging clicks and key presses: {}", e));
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let log_events = self.log_events;
+        let annotate_events = self.annotate_events;
+        let codec = settings.codec;
+        let timelapse = settings.timelapse;
+        let (mark_sender, mark_receiver) = channel::<MarkRequest>();
+        let (done_tx, done_rx) = channel();
+        let condense = self.condense_after.then(|| CondenseOptions {
+            action: match self.condense_speed_up {
+                true => IdleAction::SpeedUp(self.condense_speed),
+                false => IdleAction::Cut,
+            },
+            ..self.condense.clone()
+        });
+
+        // Kept in the recording's metadata, without the preview output
+        let command = record_command(Platform::current(), &settings, &output_path, duration)
+            .and_then(|command| Ok(command.to_shell_string()?))
+            .unwrap_or_default();
+        let profile = self.active_profile.clone();
 
         // Start recording in a background thread
         let output_path_clone = output_path.clone();
+        let running = stop_flag.clone();
         thread::spawn(move || {
-            let result = record_screen_gui(&output_path_clone, duration, fps, stop_flag_clone, tx);
-            if let Err(e) = result {
-                eprintln!("Recording error: {}", e);
+            let started = chrono::Local::now();
+            // Marks and events are timed from when ffmpeg really starts capturing
+            let (capture_sender, capture_receiver) = channel();
+            let requested = Instant::now();
+            let result = record_screen_gui(
+                &SystemRunner,
+                &output_path_clone,
+                duration,
+                &settings,
+                control,
+                tx.clone(),
+                capture_sender,
+            );
+            let capture_start = capture_receiver.try_recv().unwrap_or(requested);
+            // Also when a timed recording ran out, so the UI shows the post-processing
+            running.store(false, Ordering::SeqCst);
+            let output = Path::new(&output_path_clone);
+            if result.is_ok() {
+                let metadata = RecordingMetadata::new(
+                    profile,
+                    &settings,
+                    command,
+                    started,
+                    chrono::Local::now(),
+                );
+                if let Err(e) = write_sidecar(output, &metadata) {
+                    let _ = tx.send(format!("Failed to save the recording metadata: {}", e));
+                }
+            }
+            // Drawn and logged where they play in the video
+            let events = capture.map(|capture| match timelapse {
+                Some(interval) => events::timelapse_events(capture.finish(capture_start), interval),
+                None => capture.finish(capture_start),
+            });
+
+            let mut result = result;
+            if let Some(events) = &events {
+                if result.is_ok() && highlight {
+                    result = highlight_cursor_gui(&SystemRunner, output, codec, events, tx.clone());
+                }
+            }
+            let marks: Vec<Mark> = mark_receiver
+                .try_iter()
+                .map(|request| request.place(capture_start))
+                .collect();
+            if result.is_ok() && !marks.is_empty() {
+                let marks = match timelapse {
+                    Some(interval) => timelapse_marks(marks, interval),
+                    None => marks,
+                };
+                result = add_chapters_gui(&SystemRunner, output, &marks, tx.clone());
+            }
+            // Copies made next to the recording, for the library
+            let mut copies = Vec::new();
+            if let Some(events) = &events {
+                if result.is_ok() && log_events {
+                    match save_events_gui(
+                        &SystemRunner,
+                        events,
+                        output,
+                        annotate_events,
+                        tx.clone(),
+                    ) {
+                        Ok(annotated) => copies.extend(annotated),
+                        Err(e) => result = Err(e),
+                    }
+                }
+            }
+            if let Some(options) = &condense {
+                if result.is_ok() {
+                    let condensed = condensed_path(output);
+                    match condense_gui(&SystemRunner, output, &condensed, options, tx) {
+                        Ok(report) if report.stretches > 0 => copies.push(condensed),
+                        Ok(_) => {}
+                        Err(e) => result = Err(e),
+                    }
+                }
             }
+
+            // The UI waits for this before it touches the file
+            let _ = done_tx.send(result.map(|()| copies).map_err(|e| e.to_string()));
         });
 
         // Update app state
@@ -557,19 +2791,423 @@ impl RcrdrApp {
         self.recording_start_time = Some(Instant::now());
         self.recording_stop_flag = Some(stop_flag);
         self.recording_log_receiver = Some(rx);
+        self.recording_done_receiver = Some(done_rx);
         self.recording_output_path = Some(output_path);
+        self.recording_pause_flag = (duration == 0).then_some(pause_flag);
+        self.recording_paused_at = None;
+        self.recording_paused_total = Duration::ZERO;
+        self.recording_marks.clear();
+        self.recording_mark_label.clear();
+        self.recording_mark_sender = Some(mark_sender);
+        self.recording_timelapse = timelapse;
+    }
+
+    pub fn stop_recording(&mut self) {
+        if let Some(stop_flag) = &self.recording_stop_flag {
+            stop_flag.store(false, Ordering::SeqCst);
+            self.logs.push("Stopping recording...".to_string());
+        }
+    }
+
+    pub fn toggle_pause(&mut self) {
+        let pause_flag = match &self.recording_pause_flag {
+            Some(pause_flag) => pause_flag,
+            None => return,
+        };
+
+        match self.recording_paused_at.take() {
+            Some(paused_at) => {
+                self.recording_paused_total += paused_at.elapsed();
+                pause_flag.store(false, Ordering::SeqCst);
+            }
+            None => {
+                self.recording_paused_at = Some(Instant::now());
+                pause_flag.store(true, Ordering::SeqCst);
+            }
+        }
+    }
+
+    pub fn add_mark(&mut self) {
+        // Pauses are left out of the recording, so the mark moves back by them
+        let now = Instant::now();
+        let request = MarkRequest {
+            at: now.checked_sub(self.recording_paused()).unwrap_or(now),
+            label: self.recording_mark_label.clone(),
+        };
+        // Shown on the recording screen's clock
+        let mark = Mark::new(self.recording_elapsed(), &self.recording_mark_label);
+        self.recording_mark_label.clear();
+        self.logs.push(format!(
+            "Mark {} at {}",
+            self.recording_marks.len() + 1,
+            format_duration(mark.t)
+        ));
+        if let Some(sender) = &self.recording_mark_sender {
+            let _ = sender.send(request);
+        }
+        self.recording_marks.push(mark);
+    }
+
+    // Time recorded so far, not counting pauses
+    fn recording_elapsed(&self) -> Duration {
+        match self.recording_start_time {
+            Some(start_time) => start_time.elapsed().saturating_sub(self.recording_paused()),
+            None => Duration::ZERO,
+        }
+    }
+
+    // Time spent paused so far, including a pause still going on
+    fn recording_paused(&self) -> Duration {
+        let paused_now = self
+            .recording_paused_at
+            .map(|paused_at| paused_at.elapsed())
+            .unwrap_or_default();
+        self.recording_paused_total + paused_now
+    }
+
+    pub fn register_hotkeys(&mut self, ctx: &Context) {
+        // Release the old grabs before taking the new ones
+        self.hotkey_listener = None;
+        self.hotkey_receiver = None;
+
+        if !self.config.hotkeys.enabled {
+            self.hotkey_status = "Global hotkeys are disabled.".to_string();
+            return;
+        }
+
+        let (tx, rx) = channel();
+        let ctx = ctx.clone();
+        match listen(&self.config.hotkeys, tx, move || ctx.request_repaint()) {
+            Ok(listener) => {
+                self.hotkey_listener = Some(listener);
+                self.hotkey_receiver = Some(rx);
+                self.hotkey_status = format!(
+                    "Listening: {} to start, {} to stop.",
+                    self.config.hotkeys.start, self.config.hotkeys.stop
+                );
+            }
+            Err(e) => {
+                self.hotkey_status = format!("Global hotkeys unavailable: {}", e);
+                self.logs.push(self.hotkey_status.clone());
+            }
+        }
+    }
+
+    pub fn apply_hotkeys(&mut self) {
+        let mut bindings = self.config.hotkeys.clone();
+        for (action, input) in HotkeyAction::ALL.iter().zip(&self.hotkey_inputs) {
+            match input.parse::<Hotkey>() {
+                Ok(hotkey) => bindings.set(*action, hotkey),
+                Err(e) => {
+                    self.hotkey_status = e;
+                    return;
+                }
+            }
+        }
+        if let Err(e) = bindings.validate() {
+            self.hotkey_status = e;
+            return;
+        }
+
+        self.config.hotkeys = bindings;
+        self.refresh_hotkey_inputs();
+        match self.config.save() {
+            Ok(path) => self
+                .logs
+                .push(format!("Saved hotkeys to {}", path.display())),
+            Err(e) => self.logs.push(format!("Failed to save config: {}", e)),
+        }
+
+        // Registered again on the next frame, where the context is at hand
+        self.hotkeys_initialized = false;
+    }
+
+    fn refresh_hotkey_inputs(&mut self) {
+        self.hotkey_inputs = HotkeyAction::ALL
+            .iter()
+            .map(|action| self.config.hotkeys.get(*action).to_string())
+            .collect();
+    }
+
+    pub fn handle_hotkeys(&mut self) {
+        let actions: Vec<HotkeyAction> = match &self.hotkey_receiver {
+            Some(receiver) => receiver.try_iter().collect(),
+            None => return,
+        };
+
+        for action in actions {
+            let recording = self.state == AppState::Recording && !self.recording_finishing();
+            match action {
+                HotkeyAction::Start if matches!(self.state, AppState::Main | AppState::Library) => {
+                    self.begin_recording()
+                }
+                HotkeyAction::Stop if recording => self.stop_recording(),
+                HotkeyAction::Pause if recording => self.toggle_pause(),
+                HotkeyAction::Mark if recording => self.add_mark(),
+                _ => {}
+            }
+        }
+    }
+
+    // Keep the preview running for the current capture source while it's enabled
+    fn update_source_preview(&mut self) {
+        if !self.show_preview {
+            self.source_preview = None;
+            self.preview_source = None;
+            self.preview_frames = None;
+            self.preview_texture = None;
+            return;
+        }
+
+        let changed = self.preview_source.as_deref() != Some(self.source_input.as_str());
+        if let Some(preview) = &mut self.source_preview {
+            if changed {
+                self.source_preview = None;
+            } else {
+                if preview.has_exited() {
+                    self.logs.push(
+                        "Live preview stopped: FFmpeg could not capture the source.".to_string(),
+                    );
+                    self.source_preview = None;
+                }
+                return;
+            }
+        } else if !changed {
+            // Already failed for this source, wait until it's edited
+            return;
+        }
+
+        self.preview_source = Some(self.source_input.clone());
+        self.preview_frames = None;
+        self.preview_texture = None;
+
+        // An invalid source is reported once recording starts
+        let Ok(settings) = self.record_settings() else {
+            return;
+        };
+        match SourcePreview::start(&SystemRunner, &settings) {
+            Ok(preview) => {
+                self.preview_frames = Some(preview.frames.clone());
+                self.source_preview = Some(preview);
+            }
+            Err(e) => self
+                .logs
+                .push(format!("Failed to start the live preview: {}", e)),
+        }
+    }
+
+    fn show_preview_image(&mut self, ui: &mut Ui, ctx: &Context) {
+        if let Some(rgba) = self
+            .preview_frames
+            .as_ref()
+            .and_then(|frames| frames.take())
+        {
+            let image =
+                egui::ColorImage::from_rgba_unmultiplied([PREVIEW_WIDTH, PREVIEW_HEIGHT], &rgba);
+            match &mut self.preview_texture {
+                Some(texture) => texture.set(image, egui::TextureOptions::default()),
+                None => {
+                    self.preview_texture =
+                        Some(ctx.load_texture("preview", image, egui::TextureOptions::default()))
+                }
+            }
+        }
+
+        let size = egui::vec2(PREVIEW_WIDTH as f32, PREVIEW_HEIGHT as f32);
+        match &self.preview_texture {
+            Some(texture) => {
+                ui.image(texture, size);
+            }
+            None => {
+                let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+                ui.painter().rect_filled(rect, 0.0, Color32::BLACK);
+                ui.painter().text(
+                    rect.center(),
+                    egui::Align2::CENTER_CENTER,
+                    "Waiting for preview...",
+                    egui::FontId::default(),
+                    Color32::GRAY,
+                );
+            }
+        }
+
+        // New frames arrive a couple of times per second
+        ctx.request_repaint_after(Duration::from_millis(250));
+    }
+
+    fn record_settings(&self) -> Result<RecordSettings, String> {
+        Ok(RecordSettings {
+            source: self.source_input.parse::<CaptureSource>()?,
+            codec: self.codec,
+            fps: self.fps,
+            audio: self.audio && !self.timelapse_enabled,
+            cursor: self.cursor,
+            webcam: if self.webcam_enabled {
+                Some(WebcamOverlay {
+                    border_color: self.webcam_border_color.parse()?,
+                    ..self.webcam.clone()
+                })
+            } else {
+                None
+            },
+            timelapse: self.timelapse_enabled.then_some(self.timelapse_interval),
+            watermark: self.watermark_settings()?,
+        })
+    }
+
+    fn watermark_settings(&self) -> Result<Option<Watermark>, String> {
+        if !self.watermark_enabled {
+            return Ok(None);
+        }
+
+        let font = self.watermark_font.trim();
+        let watermark = Watermark {
+            text: (!self.watermark_use_image).then(|| self.watermark_text.clone()),
+            image: self
+                .watermark_use_image
+                .then(|| PathBuf::from(self.watermark_image.trim())),
+            font: (!font.is_empty()).then(|| font.to_string()),
+            color: self.watermark_color.parse()?,
+            ..self.watermark.clone()
+        };
+        watermark.check()?;
+        Ok(Some(watermark))
+    }
+
+    /// The watermark for GIF and video exports, which only get one when asked
+    /// so that exports of watermarked recordings don't get a second one.
+    pub fn export_watermark(&self) -> Result<Option<Watermark>, String> {
+        if self.watermark_exports {
+            self.watermark_settings()
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn apply_profile(&mut self, name: &str) {
+        let profile = match self.config.profiles.get(name) {
+            Some(profile) => profile.clone(),
+            None => {
+                self.logs.push(format!("Unknown profile '{}'", name));
+                return;
+            }
+        };
+
+        self.source_input = profile.source.to_string();
+        self.codec = profile.codec;
+        self.cursor = profile.cursor;
+        self.fps = profile.fps;
+        self.audio = profile.audio;
+        self.watermark_enabled = profile.watermark.is_some();
+        if let Some(watermark) = profile.watermark.clone() {
+            self.watermark_use_image = watermark.image.is_some();
+            self.watermark_text = watermark.text.clone().unwrap_or_default();
+            self.watermark_image = watermark
+                .image
+                .as_ref()
+                .map(|image| image.to_string_lossy().to_string())
+                .unwrap_or_default();
+            self.watermark_font = watermark.font.clone().unwrap_or_default();
+            self.watermark_color = watermark.color.to_string();
+            self.watermark = watermark;
+        }
+
+        let (output_dir, filename_template) = self.config.output_target(&profile);
+        self.output_dir = output_dir.to_string_lossy().to_string();
+        self.filename_template = filename_template;
+        self.active_profile = Some(name.to_string());
+    }
+
+    pub fn save_profile(&mut self, name: &str) {
+        let settings = match self.record_settings() {
+            Ok(settings) => settings,
+            Err(e) => {
+                self.logs.push(e);
+                return;
+            }
+        };
+
+        // Only pin the output location in the profile if it differs from the global one
+        let (global_dir, global_template) = self.config.output_target(&Profile::default());
+        let output_dir = PathBuf::from(self.output_dir.trim());
+        let profile = Profile {
+            source: settings.source,
+            codec: settings.codec,
+            fps: settings.fps,
+            audio: settings.audio,
+            cursor: settings.cursor,
+            output_dir: (output_dir != global_dir).then_some(output_dir),
+            filename_template: (self.filename_template != global_template)
+                .then(|| self.filename_template.clone()),
+            watermark: settings.watermark,
+        };
+
+        self.config.profiles.insert(name.to_string(), profile);
+        self.active_profile = Some(name.to_string());
+
+        match self.config.save() {
+            Ok(path) => self
+                .logs
+                .push(format!("Saved profile '{}' to {}", name, path.display())),
+            Err(e) => self.logs.push(format!("Failed to save config: {}", e)),
+        }
+    }
+
+    pub fn set_default_profile(&mut self, name: &str) {
+        self.config.default_profile = Some(name.to_string());
+
+        match self.config.save() {
+            Ok(path) => self.logs.push(format!(
+                "'{}' is now the default profile in {}",
+                name,
+                path.display()
+            )),
+            Err(e) => self.logs.push(format!("Failed to save config: {}", e)),
+        }
+    }
+
+    pub fn reload_config(&mut self) {
+        match Config::load() {
+            Ok(config) => {
+                self.config = config;
+                let name = self
+                    .active_profile
+                    .clone()
+                    .or_else(|| self.config.default_profile.clone());
+                match name {
+                    Some(name) if self.config.profiles.contains_key(&name) => {
+                        self.apply_profile(&name)
+                    }
+                    _ => self.active_profile = None,
+                }
+                self.refresh_hotkey_inputs();
+                self.hotkeys_initialized = false;
+                self.logs.push("Config reloaded.".to_string());
+            }
+            Err(e) => self.logs.push(format!("Failed to load config: {}", e)),
+        }
     }
 
     pub fn start_gif_conversion(&mut self) {
         let input_path = self.input_video_path.clone();
         let output_path = self.output_gif_path.clone();
+        let options = match self.export_watermark() {
+            Ok(watermark) => GifOptions {
+                watermark,
+                ..GifOptions::default()
+            },
+            Err(e) => {
+                self.logs.push(e);
+                return;
+            }
+        };
 
         // Set up log channel
         let (tx, rx) = channel();
 
         // Start conversion in a background thread
         thread::spawn(move || {
-            let result = convert_to_gif_gui(&input_path, &output_path, tx);
+            let result = convert_to_gif_gui(&SystemRunner, &input_path, &output_path, &options, tx);
             if let Err(e) = result {
                 eprintln!("Conversion error: {}", e);
             }
@@ -581,6 +3219,49 @@ impl RcrdrApp {
         self.converting_progress = 0.0;
     }
 
+    pub fn take_screenshot(&mut self) {
+        let settings = match self.record_settings() {
+            Ok(settings) => settings,
+            Err(e) => {
+                self.logs.push(e);
+                return;
+            }
+        };
+
+        let dir = PathBuf::from(self.output_dir.trim());
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            self.logs
+                .push(format!("Failed to create {}: {}", dir.display(), e));
+            return;
+        }
+
+        let context = TemplateContext {
+            profile: self.active_profile.clone(),
+            source: settings.source.clone(),
+        };
+        let output_path =
+            match resolve_output_path(&dir, SCREENSHOT_TEMPLATE, &context, self.overwrite) {
+                Ok(path) => path,
+                Err(e) => {
+                    self.logs.push(e);
+                    return;
+                }
+            };
+        let clipboard = self.screenshot_clipboard;
+
+        // Set up log channel
+        let (tx, rx) = channel();
+
+        thread::spawn(move || {
+            let result = take_screenshot_gui(&SystemRunner, &settings, &output_path, clipboard, tx);
+            if let Err(e) = result {
+                eprintln!("Screenshot error: {}", e);
+            }
+        });
+
+        self.screenshot_log_receiver = Some(rx);
+    }
+
     pub fn start_test_recording(&mut self) {
         // Create a temp file for test output
         let temp_dir = std::env::temp_dir();
@@ -594,7 +3275,7 @@ impl RcrdrApp {
 
         // Start test in a background thread
         thread::spawn(move || {
-            let result = test_recording_gui(&test_output, tx);
+            let result = test_recording_gui(&SystemRunner, &test_output, tx);
             if let Err(e) = result {
                 eprintln!("Test recording error: {}", e);
             }
@@ -718,7 +3399,7 @@ impl RcrdrApp {
                 self.installation_logs
                     .push(format!("Detected package manager: {}", pkg_mgr));
                 self.installation_logs
-                    .push(format!("Please run this command in terminal:"));
+                    .push("Please run this command in terminal:".to_string());
                 self.installation_logs.push(install_cmd.join(" "));
             } else {
                 self.installation_logs
@@ -730,3 +3411,177 @@ impl RcrdrApp {
         }
     }
 }
+
+/// A video frame drawn scaled down to fit, for drawing rectangles on in video pixels.
+struct FrameView {
+    rect: egui::Rect,
+    /// Points per video pixel
+    scale: f32,
+    size: (u32, u32),
+}
+
+impl FrameView {
+    // At most 400 points high
+    fn show(
+        ui: &mut Ui,
+        texture: Option<&egui::TextureHandle>,
+        size: (u32, u32),
+    ) -> (Self, egui::Response) {
+        let (width, height) = (size.0.max(1) as f32, size.1.max(1) as f32);
+        let scale = (ui.available_width() / width).min(400.0 / height);
+        let (rect, response) = ui.allocate_exact_size(
+            egui::vec2(width * scale, height * scale),
+            egui::Sense::click_and_drag(),
+        );
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, Color32::BLACK);
+
+        if let Some(texture) = texture {
+            let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+            painter.image(texture.id(), rect, uv, Color32::WHITE);
+        }
+
+        (Self { rect, scale, size }, response)
+    }
+
+    fn to_video(&self, pos: egui::Pos2) -> (u32, u32) {
+        let x = ((pos.x - self.rect.left()) / self.scale).clamp(0.0, self.size.0 as f32);
+        let y = ((pos.y - self.rect.top()) / self.scale).clamp(0.0, self.size.1 as f32);
+        (x as u32, y as u32)
+    }
+
+    fn screen_rect(&self, geometry: &Geometry) -> egui::Rect {
+        egui::Rect::from_min_size(
+            self.rect.min + egui::vec2(geometry.x as f32, geometry.y as f32) * self.scale,
+            egui::vec2(geometry.width as f32, geometry.height as f32) * self.scale,
+        )
+    }
+
+    /// The rectangle dragged out so far, in even sizes which the encoder needs
+    /// anyway. `start` keeps where the drag began between frames.
+    fn drag_rectangle(
+        &self,
+        response: &egui::Response,
+        start: &mut Option<(u32, u32)>,
+    ) -> Option<Geometry> {
+        if response.drag_started() {
+            *start = response
+                .interact_pointer_pos()
+                .map(|pos| self.to_video(pos));
+        }
+        let dragged = match (*start, response.interact_pointer_pos()) {
+            (Some((start_x, start_y)), Some(pointer)) => {
+                let (end_x, end_y) = self.to_video(pointer);
+                Some(Geometry {
+                    width: start_x.abs_diff(end_x) & !1,
+                    height: start_y.abs_diff(end_y) & !1,
+                    x: start_x.min(end_x),
+                    y: start_y.min(end_y),
+                })
+            }
+            _ => None,
+        };
+        if response.drag_released() {
+            *start = None;
+        }
+
+        dragged.filter(|geometry| geometry.width > 0 && geometry.height > 0)
+    }
+}
+
+fn load_texture(ctx: &Context, path: &Path) -> Option<egui::TextureHandle> {
+    let image = image::open(path).ok()?.to_rgba8();
+    let size = [image.width() as usize, image.height() as usize];
+    let color_image = egui::ColorImage::from_rgba_unmultiplied(size, image.as_raw());
+
+    Some(ctx.load_texture(
+        path.to_string_lossy(),
+        color_image,
+        egui::TextureOptions::default(),
+    ))
+}
+
+fn show_media_info(ui: &mut Ui, info: &MediaInfo) {
+    egui::Grid::new(("media_info", &info.path))
+        .num_columns(2)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("File");
+            ui.label(info.path.to_string_lossy());
+            ui.end_row();
+
+            ui.label("Container");
+            ui.label(&info.container);
+            ui.end_row();
+
+            ui.label("Duration");
+            ui.label(format_duration(info.duration_secs));
+            ui.end_row();
+
+            if let Some(size) = info.size_bytes {
+                ui.label("Size");
+                ui.label(format_size(size));
+                ui.end_row();
+            }
+
+            if let Some(bitrate) = info.bitrate {
+                ui.label("Bitrate");
+                ui.label(format!("{} kb/s", bitrate / 1000));
+                ui.end_row();
+            }
+
+            for stream in &info.streams {
+                let details = match stream.kind {
+                    StreamKind::Video => format!(
+                        "{} {}x{} @ {:.2} fps, {}",
+                        stream.codec,
+                        stream.width.unwrap_or(0),
+                        stream.height.unwrap_or(0),
+                        stream.fps.unwrap_or(0.0),
+                        stream.pixel_format.as_deref().unwrap_or("?")
+                    ),
+                    StreamKind::Audio => format!(
+                        "{} {} Hz, {} channel(s)",
+                        stream.codec,
+                        stream.sample_rate.unwrap_or(0),
+                        stream.channels.unwrap_or(0)
+                    ),
+                    _ => stream.codec.clone(),
+                };
+
+                ui.label(format!("Stream #{} ({:?})", stream.index, stream.kind));
+                ui.label(details);
+                ui.end_row();
+            }
+        });
+}
+
+fn format_duration(secs: f64) -> String {
+    let secs = secs.round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}",
+        secs / 3600,
+        (secs % 3600) / 60,
+        secs % 60
+    )
+}
+
+// Seconds as MM:SS.mmm, precise enough to place cuts by frame
+fn format_timestamp(secs: f64) -> String {
+    let millis = (secs.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}.{:03}",
+        millis / 60_000,
+        (millis / 1000) % 60,
+        millis % 1000
+    )
+}
+
+fn format_size(bytes: u64) -> String {
+    const MB: f64 = 1024.0 * 1024.0;
+    if bytes as f64 >= MB {
+        format!("{:.1} MB", bytes as f64 / MB)
+    } else {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    }
+}
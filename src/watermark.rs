@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::ffmpeg::{escape_filter_value, FfmpegCommand, Input};
+use crate::webcam::{Corner, Rgb};
+
+/// A text or PNG logo drawn into a corner of recordings and exports, saved
+/// with a profile:
+///
+/// ```toml
+/// [profiles.share.watermark]
+/// text = "ACME internal"
+/// size = 28
+/// opacity = 0.6
+/// corner = "top-right"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Watermark {
+    /// Text to draw, or
+    pub text: Option<String>,
+    /// a PNG logo to lay over the video
+    pub image: Option<PathBuf>,
+    /// Font of the text, a fontconfig name such as "DejaVu Sans" or a font file
+    pub font: Option<String>,
+    /// Text height in pixels
+    pub size: u32,
+    pub color: Rgb,
+    /// From 0.0, invisible, to 1.0, opaque
+    pub opacity: f32,
+    pub corner: Corner,
+    /// Distance from the edges in pixels
+    pub margin: u32,
+}
+
+impl Default for Watermark {
+    fn default() -> Self {
+        Self {
+            text: None,
+            image: None,
+            font: None,
+            size: 24,
+            color: Rgb::WHITE,
+            opacity: 0.7,
+            corner: Corner::BottomRight,
+            margin: 20,
+        }
+    }
+}
+
+impl Watermark {
+    pub fn check(&self) -> Result<(), String> {
+        match (&self.text, &self.image) {
+            (Some(text), None) if !text.trim().is_empty() => {}
+            (None, Some(_)) => {}
+            (Some(_), Some(_)) => {
+                return Err("A watermark is either text or an image, not both".to_string())
+            }
+            _ => return Err("The watermark needs a text or an image".to_string()),
+        }
+        if !(0.0..=1.0).contains(&self.opacity) {
+            return Err(format!(
+                "The watermark opacity {} is not between 0 and 1",
+                self.opacity
+            ));
+        }
+        Ok(())
+    }
+
+    /// The logo, read as an input of its own.
+    pub fn input(&self) -> Option<Input> {
+        self.image.as_ref().map(Input::file)
+    }
+
+    /// Filter chains that draw the watermark over `video`, as "[watermarked]".
+    /// The logo, if any, is input `image_input`.
+    pub fn filter_chains(&self, video: &str, image_input: usize) -> Vec<String> {
+        if self.image.is_some() {
+            return vec![
+                format!(
+                    "[{}:v]format=rgba,colorchannelmixer=aa={:.2}[logo]",
+                    image_input, self.opacity
+                ),
+                format!(
+                    "{}[logo]overlay={}[watermarked]",
+                    video,
+                    self.corner.position(self.margin)
+                ),
+            ];
+        }
+
+        let font = match &self.font {
+            // A path rather than a fontconfig name
+            Some(font) if font.contains(['/', '\\']) => {
+                format!("fontfile={}:", escape_filter_value(font))
+            }
+            Some(font) => format!("font={}:", escape_filter_value(font)),
+            None => String::new(),
+        };
+        let (x, y) = self.corner.offsets(self.margin, "w-text_w", "h-text_h");
+        vec![format!(
+            "{}drawtext=text={}:expansion=none:{}fontsize={}:fontcolor=0x{}@{:.2}:x={}:y={}[watermarked]",
+            video,
+            escape_filter_value(self.text.as_deref().unwrap_or_default()),
+            font,
+            self.size,
+            self.color,
+            self.opacity,
+            x,
+            y
+        )]
+    }
+}
+
+/// Draw `watermark` over the finished video of `command`, if there is one.
+pub fn apply_watermark(command: FfmpegCommand, watermark: Option<&Watermark>) -> FfmpegCommand {
+    match watermark {
+        Some(watermark) => {
+            let inputs = watermark.input().into_iter().collect();
+            command.then_filter(inputs, "[watermarked]", |video, image_input| {
+                watermark.filter_chains(video, image_input)
+            })
+        }
+        None => command,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_in_the_top_left_corner() {
+        let watermark = Watermark {
+            text: Some("ACME: internal".to_string()),
+            font: Some("DejaVu Sans".to_string()),
+            size: 32,
+            opacity: 0.5,
+            corner: Corner::TopLeft,
+            margin: 10,
+            ..Watermark::default()
+        };
+
+        assert_eq!(
+            watermark.filter_chains("[0:v]", 1),
+            [
+                r"[0:v]drawtext=text=ACME\\: internal:expansion=none:font=DejaVu Sans:fontsize=32:fontcolor=0xffffff@0.50:x=10:y=10[watermarked]"
+            ]
+        );
+    }
+
+    #[test]
+    fn logo_is_faded_and_laid_over() {
+        let watermark = Watermark {
+            image: Some(PathBuf::from("logo.png")),
+            ..Watermark::default()
+        };
+
+        assert_eq!(
+            watermark.filter_chains("[v]", 2),
+            [
+                "[2:v]format=rgba,colorchannelmixer=aa=0.70[logo]",
+                "[v][logo]overlay=x=W-w-20:y=H-h-20[watermarked]",
+            ]
+        );
+    }
+
+    #[test]
+    fn needs_exactly_one_of_text_and_image() {
+        let text = Watermark {
+            text: Some("internal".to_string()),
+            ..Watermark::default()
+        };
+        assert!(text.check().is_ok());
+        assert!(Watermark::default().check().is_err());
+        assert!(Watermark {
+            image: Some(PathBuf::from("logo.png")),
+            ..text.clone()
+        }
+        .check()
+        .is_err());
+        assert!(Watermark {
+            opacity: 1.5,
+            ..text
+        }
+        .check()
+        .is_err());
+    }
+}
@@ -0,0 +1,279 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::ffmpeg::Input;
+
+/// Which corner of the recording the webcam or a watermark sits in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+impl Corner {
+    pub const ALL: [Corner; 4] = [
+        Corner::TopLeft,
+        Corner::TopRight,
+        Corner::BottomLeft,
+        Corner::BottomRight,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Corner::TopLeft => "Top left",
+            Corner::TopRight => "Top right",
+            Corner::BottomLeft => "Bottom left",
+            Corner::BottomRight => "Bottom right",
+        }
+    }
+
+    /// x and y for this corner, `margin` pixels in from the edges. `spare_width`
+    /// and `spare_height` are the filter's expressions for the room around the
+    /// placed picture, such as "W-w" for overlay.
+    pub fn offsets(&self, margin: u32, spare_width: &str, spare_height: &str) -> (String, String) {
+        let x = match self {
+            Corner::TopLeft | Corner::BottomLeft => margin.to_string(),
+            Corner::TopRight | Corner::BottomRight => format!("{}-{}", spare_width, margin),
+        };
+        let y = match self {
+            Corner::TopLeft | Corner::TopRight => margin.to_string(),
+            Corner::BottomLeft | Corner::BottomRight => format!("{}-{}", spare_height, margin),
+        };
+        (x, y)
+    }
+
+    /// overlay's x and y for this corner
+    pub fn position(&self, margin: u32) -> String {
+        let (x, y) = self.offsets(margin, "W-w", "H-h");
+        format!("x={}:y={}", x, y)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum WebcamShape {
+    #[default]
+    Rect,
+    Circle,
+}
+
+impl WebcamShape {
+    pub const ALL: [WebcamShape; 2] = [WebcamShape::Rect, WebcamShape::Circle];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            WebcamShape::Rect => "Rectangle",
+            WebcamShape::Circle => "Circle",
+        }
+    }
+}
+
+/// An RGB color, written as hex such as "ffffff" or "#ff8800".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    pub const WHITE: Rgb = Rgb(255, 255, 255);
+    pub const BLACK: Rgb = Rgb(0, 0, 0);
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+impl FromStr for Rgb {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.trim().trim_start_matches('#');
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+        };
+
+        match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Rgb(r, g, b)),
+            _ => Err(format!(
+                "Invalid color '{}', expected hex such as ffffff or #ff8800",
+                s
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for Rgb {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Rgb> for String {
+    fn from(color: Rgb) -> Self {
+        color.to_string()
+    }
+}
+
+// Distance of the webcam from the edges of the recording, in pixels
+const MARGIN: u32 = 20;
+
+/// The presenter's webcam, composited into a corner of the recording.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebcamOverlay {
+    /// V4L2 device, e.g. "/dev/video0"
+    pub device: String,
+    pub corner: Corner,
+    /// Width in pixels. The height keeps the camera's aspect ratio, circles are square.
+    pub width: u32,
+    pub shape: WebcamShape,
+    /// Border thickness in pixels, 0 for none
+    pub border: u32,
+    pub border_color: Rgb,
+    /// Also save the camera on its own, see `webcam_path`
+    pub separate_file: bool,
+}
+
+impl Default for WebcamOverlay {
+    fn default() -> Self {
+        Self {
+            device: "/dev/video0".to_string(),
+            corner: Corner::default(),
+            width: 320,
+            shape: WebcamShape::default(),
+            border: 0,
+            border_color: Rgb::WHITE,
+            separate_file: false,
+        }
+    }
+}
+
+impl WebcamOverlay {
+    pub fn input(&self) -> Input {
+        Input::device("v4l2", &self.device)
+    }
+
+    /// Filter chains that put input `camera` over the video of input 0, as "[v]".
+    pub fn filter_chains(&self, camera: usize) -> Vec<String> {
+        let Rgb(red, green, blue) = self.border_color;
+        let filters = match self.shape {
+            WebcamShape::Rect => {
+                let mut filters = vec![format!("scale={}:-2", self.width)];
+                if self.border > 0 {
+                    filters.push(format!(
+                        "pad=iw+{twice}:ih+{twice}:{border}:{border}:color=0x{color}",
+                        twice = self.border * 2,
+                        border = self.border,
+                        color = self.border_color
+                    ));
+                }
+                filters
+            }
+            WebcamShape::Circle => {
+                // Cut a square out of the middle, then clear everything outside the
+                // circle and paint the outer ring in the border color
+                let radius = self.width / 2;
+                let distance = format!("hypot(X-{radius},Y-{radius})", radius = radius);
+                let ring = format!("gt({},{})", distance, radius.saturating_sub(self.border));
+                vec![
+                    "crop='min(iw,ih)':'min(iw,ih)'".to_string(),
+                    format!("scale={width}:{width}", width = self.width),
+                    "format=rgba".to_string(),
+                    format!(
+                        "geq=r='if({ring},{red},r(X,Y))':g='if({ring},{green},g(X,Y))':b='if({ring},{blue},b(X,Y))':a='255*lte({distance},{radius})'",
+                        ring = ring,
+                        red = red,
+                        green = green,
+                        blue = blue,
+                        distance = distance,
+                        radius = radius
+                    ),
+                ]
+            }
+        };
+
+        vec![
+            format!("[{}:v]{}[camera]", camera, filters.join(",")),
+            format!("[0:v][camera]overlay={}[v]", self.corner.position(MARGIN)),
+        ]
+    }
+}
+
+/// "clip.mp4" -> "clip.webcam.mp4", where the camera on its own is saved.
+pub fn webcam_path(output: &str) -> String {
+    let path = Path::new(output);
+    let name = match path.extension() {
+        Some(extension) => format!("webcam.{}", extension.to_string_lossy()),
+        None => "webcam".to_string(),
+    };
+    path.with_extension(name).to_string_lossy().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_parse_as_hex() {
+        assert_eq!("ff8800".parse(), Ok(Rgb(255, 136, 0)));
+        assert_eq!(" #FFFFFF".parse(), Ok(Rgb::WHITE));
+        assert_eq!(Rgb(1, 2, 255).to_string(), "0102ff");
+
+        for invalid in ["", "fff", "ff88000", "gg8800", "#ff88"] {
+            assert!(invalid.parse::<Rgb>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn rectangle_with_border_in_the_top_left() {
+        let webcam = WebcamOverlay {
+            corner: Corner::TopLeft,
+            width: 240,
+            border: 4,
+            border_color: Rgb(255, 136, 0),
+            ..WebcamOverlay::default()
+        };
+
+        assert_eq!(
+            webcam.filter_chains(1),
+            [
+                "[1:v]scale=240:-2,pad=iw+8:ih+8:4:4:color=0xff8800[camera]",
+                "[0:v][camera]overlay=x=20:y=20[v]",
+            ]
+        );
+    }
+
+    #[test]
+    fn circle_is_masked_and_ringed() {
+        let webcam = WebcamOverlay {
+            corner: Corner::TopRight,
+            width: 200,
+            shape: WebcamShape::Circle,
+            border: 5,
+            ..WebcamOverlay::default()
+        };
+
+        assert_eq!(
+            webcam.filter_chains(2),
+            [
+                "[2:v]crop='min(iw,ih)':'min(iw,ih)',scale=200:200,format=rgba,geq=r='if(gt(hypot(X-100,Y-100),95),255,r(X,Y))':g='if(gt(hypot(X-100,Y-100),95),255,g(X,Y))':b='if(gt(hypot(X-100,Y-100),95),255,b(X,Y))':a='255*lte(hypot(X-100,Y-100),100)'[camera]",
+                "[0:v][camera]overlay=x=W-w-20:y=20[v]",
+            ]
+        );
+    }
+
+    #[test]
+    fn webcam_file_sits_next_to_the_recording() {
+        assert_eq!(webcam_path("/videos/clip.mp4"), "/videos/clip.webcam.mp4");
+        assert_eq!(webcam_path("clip"), "clip.webcam");
+    }
+}
@@ -0,0 +1,768 @@
+//! End-to-end checks of record -> verify -> convert against the real ffmpeg.
+//!
+//! These need ffmpeg/ffprobe on the PATH (and Xvfb for the display test) and
+//! skip themselves when the tools are missing.
+
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+
+fn is_available(command: &str) -> bool {
+    Command::new("which")
+        .arg(command)
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+fn has_ffmpeg() -> bool {
+    if is_available("ffmpeg") && is_available("ffprobe") {
+        return true;
+    }
+    eprintln!("skipping: ffmpeg/ffprobe not installed");
+    false
+}
+
+/// A fresh directory under the system temp dir, also used as the config and
+/// data home so the user's own profiles and library are never touched.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rcrdr-test-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn rcrdr(scratch: &Path) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_rcrdr"));
+    cmd.env("XDG_CONFIG_HOME", scratch.join("config"))
+        .env("XDG_DATA_HOME", scratch.join("data"));
+    cmd
+}
+
+fn assert_success(output: &Output) {
+    assert!(
+        output.status.success(),
+        "rcrdr failed\nstdout:\n{}\nstderr:\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+fn media_info(scratch: &Path, file: &Path) -> serde_json::Value {
+    let output = rcrdr(scratch)
+        .args(["info", "--json"])
+        .arg(file)
+        .output()
+        .unwrap();
+    assert_success(&output);
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+fn video_stream(info: &serde_json::Value) -> &serde_json::Value {
+    info["streams"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|s| s["kind"] == "video")
+        .expect("no video stream")
+}
+
+// Record -> info -> GIF -> thumbnail, the way a user would run it
+fn check_pipeline(scratch: &Path, record: &mut Command, expected_size: (u64, u64)) {
+    let video = scratch.join("recording.mp4");
+    let output = record
+        .args(["-d", "2", "--overwrite", "overwrite", "-o"])
+        .arg(&video)
+        .output()
+        .unwrap();
+    assert_success(&output);
+
+    let info = media_info(scratch, &video);
+    let duration = info["duration_secs"].as_f64().unwrap();
+    assert!((1.5..=2.5).contains(&duration), "duration was {}", duration);
+    let stream = video_stream(&info);
+    assert_eq!(stream["width"].as_u64(), Some(expected_size.0));
+    assert_eq!(stream["height"].as_u64(), Some(expected_size.1));
+
+    // Read back from the recording.mp4.rcrdr.json sidecar
+    assert!(scratch.join("recording.mp4.rcrdr.json").exists());
+    let recording = &info["recording"];
+    assert!(recording["command"]
+        .as_str()
+        .unwrap()
+        .starts_with("ffmpeg "));
+    assert!(recording["started"].as_str().unwrap() <= recording["ended"].as_str().unwrap());
+
+    let gif = scratch.join("recording.gif");
+    let output = rcrdr(scratch)
+        .args(["convert-to-gif", "-i"])
+        .arg(&video)
+        .arg("-o")
+        .arg(&gif)
+        .output()
+        .unwrap();
+    assert_success(&output);
+    assert!(fs::metadata(&gif).unwrap().len() > 0);
+
+    let sheet = scratch.join("sheet.png");
+    let output = rcrdr(scratch)
+        .arg("thumbnail")
+        .arg(&video)
+        .args(["--grid", "2x2", "-o"])
+        .arg(&sheet)
+        .output()
+        .unwrap();
+    assert_success(&output);
+    assert!(fs::metadata(&sheet).unwrap().len() > 0);
+}
+
+#[test]
+fn synthetic_source_records_converts_and_verifies() {
+    if !has_ffmpeg() {
+        return;
+    }
+    let scratch = scratch_dir("synthetic");
+
+    let mut record = rcrdr(&scratch);
+    record.args(["record", "--source", "synthetic", "--fps", "15"]);
+    check_pipeline(&scratch, &mut record, (1280, 720));
+
+    let _ = fs::remove_dir_all(&scratch);
+}
+
+#[test]
+fn synthetic_source_records_audio() {
+    if !has_ffmpeg() {
+        return;
+    }
+    let scratch = scratch_dir("synthetic-audio");
+    let video = scratch.join("audio.mp4");
+
+    let output = rcrdr(&scratch)
+        .args([
+            "record",
+            "--source",
+            "synthetic",
+            "--audio",
+            "-d",
+            "1",
+            "-o",
+        ])
+        .arg(&video)
+        .output()
+        .unwrap();
+    assert_success(&output);
+
+    let info = media_info(&scratch, &video);
+    let audio = info["streams"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|s| s["kind"] == "audio")
+        .expect("no audio stream");
+    assert_eq!(audio["codec"], "aac");
+
+    let _ = fs::remove_dir_all(&scratch);
+}
+
+#[test]
+fn refuses_to_overwrite_when_asked() {
+    if !has_ffmpeg() {
+        return;
+    }
+    let scratch = scratch_dir("refuse");
+    let video = scratch.join("existing.mp4");
+    fs::write(&video, b"not a video").unwrap();
+
+    let output = rcrdr(&scratch)
+        .args(["record", "--source", "synthetic", "-d", "1"])
+        .args(["--overwrite", "refuse", "-o"])
+        .arg(&video)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert_eq!(fs::read(&video).unwrap(), b"not a video");
+
+    let _ = fs::remove_dir_all(&scratch);
+}
+
+#[test]
+fn countdown_runs_before_capture() {
+    if !has_ffmpeg() {
+        return;
+    }
+    let scratch = scratch_dir("countdown");
+    let video = scratch.join("countdown.mp4");
+
+    let started = std::time::Instant::now();
+    let output = rcrdr(&scratch)
+        .args([
+            "record",
+            "--source",
+            "synthetic",
+            "-d",
+            "1",
+            "--countdown",
+            "2",
+        ])
+        .arg("-o")
+        .arg(&video)
+        .output()
+        .unwrap();
+    assert_success(&output);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Recording starts in 2"), "{}", stdout);
+    assert!(started.elapsed().as_secs_f64() >= 3.0);
+
+    // The countdown is not part of the video
+    let duration = media_info(&scratch, &video)["duration_secs"]
+        .as_f64()
+        .unwrap();
+    assert!(duration < 1.5, "duration was {}", duration);
+
+    let _ = fs::remove_dir_all(&scratch);
+}
+
+#[test]
+fn timelapse_plays_back_faster_than_it_was_recorded() {
+    if !has_ffmpeg() {
+        return;
+    }
+    let scratch = scratch_dir("timelapse");
+    let video = scratch.join("timelapse.mp4");
+
+    // 3 seconds at one frame every 0.1s is 30 frames, one second at 30 fps
+    let output = rcrdr(&scratch)
+        .args([
+            "record",
+            "--source",
+            "synthetic",
+            "--timelapse",
+            "0.1",
+            "-d",
+            "3",
+            "-o",
+        ])
+        .arg(&video)
+        .output()
+        .unwrap();
+    assert_success(&output);
+
+    let info = media_info(&scratch, &video);
+    let duration = info["duration_secs"].as_f64().unwrap();
+    assert!((0.8..=1.2).contains(&duration), "duration was {}", duration);
+    assert_eq!(video_stream(&info)["fps"].as_f64(), Some(30.0));
+
+    let _ = fs::remove_dir_all(&scratch);
+}
+
+#[test]
+fn print_command_shows_the_ffmpeg_invocation_without_running_it() {
+    let scratch = scratch_dir("print-command");
+    let video = scratch.join("not recorded.mp4");
+
+    let output = rcrdr(&scratch)
+        .args([
+            "record",
+            "--source",
+            "synthetic",
+            "-d",
+            "1",
+            "--print-command",
+        ])
+        .arg("-o")
+        .arg(&video)
+        .output()
+        .unwrap();
+    assert_success(&output);
+
+    let line = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        line.starts_with("ffmpeg -y -f lavfi -i testsrc2="),
+        "{}",
+        line
+    );
+    assert!(line.contains("-t 1"), "{}", line);
+    assert!(line.trim_end().ends_with(&format!("'{}'", video.display())));
+    assert!(!video.exists());
+
+    let _ = fs::remove_dir_all(&scratch);
+}
+
+#[test]
+fn trim_cuts_a_recording() {
+    if !has_ffmpeg() {
+        return;
+    }
+    let scratch = scratch_dir("trim");
+    let video = scratch.join("long.mp4");
+
+    let output = rcrdr(&scratch)
+        .args(["record", "--source", "synthetic", "-d", "4", "-o"])
+        .arg(&video)
+        .output()
+        .unwrap();
+    assert_success(&output);
+
+    // Between keyframes, so this one is re-encoded
+    let output = rcrdr(&scratch)
+        .arg("trim")
+        .arg(&video)
+        .args(["--from", "1.5", "--to", "3"])
+        .output()
+        .unwrap();
+    assert_success(&output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("re-encode"));
+
+    let info = media_info(&scratch, &scratch.join("long.trimmed.mp4"));
+    let duration = info["duration_secs"].as_f64().unwrap();
+    assert!((1.3..=1.7).contains(&duration), "duration was {}", duration);
+
+    let _ = fs::remove_dir_all(&scratch);
+}
+
+#[test]
+fn concat_joins_recordings_in_different_formats() {
+    if !has_ffmpeg() {
+        return;
+    }
+    let scratch = scratch_dir("concat");
+    let first = scratch.join("first.mp4");
+    let second = scratch.join("second.mp4");
+
+    for (video, fps) in [(&first, "30"), (&second, "15")] {
+        let output = rcrdr(&scratch)
+            .args([
+                "record",
+                "--source",
+                "synthetic",
+                "--fps",
+                fps,
+                "-d",
+                "2",
+                "-o",
+            ])
+            .arg(video)
+            .output()
+            .unwrap();
+        assert_success(&output);
+    }
+
+    let joined = scratch.join("joined.mp4");
+    let output = rcrdr(&scratch)
+        .arg("concat")
+        .arg(&first)
+        .arg(&second)
+        .arg("-o")
+        .arg(&joined)
+        .output()
+        .unwrap();
+    assert_success(&output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("re-encode"));
+
+    let info = media_info(&scratch, &joined);
+    let duration = info["duration_secs"].as_f64().unwrap();
+    assert!((3.5..=4.5).contains(&duration), "duration was {}", duration);
+    assert_eq!(video_stream(&info)["fps"].as_f64(), Some(30.0));
+
+    let _ = fs::remove_dir_all(&scratch);
+}
+
+#[test]
+fn screenshot_crops_the_capture_source_to_the_region() {
+    let scratch = scratch_dir("screenshot");
+    let image = scratch.join("shot.png");
+
+    let output = rcrdr(&scratch)
+        .args([
+            "screenshot",
+            "--source",
+            "synthetic",
+            "--region",
+            "640x360+10+20",
+            "--print-command",
+            "-o",
+        ])
+        .arg(&image)
+        .output()
+        .unwrap();
+    assert_success(&output);
+
+    let line = String::from_utf8_lossy(&output.stdout);
+    assert!(line.contains("-vf crop=640:360:10:20"), "{}", line);
+    assert!(line.contains("-frames:v 1"), "{}", line);
+    assert!(!image.exists());
+
+    if has_ffmpeg() {
+        let output = rcrdr(&scratch)
+            .args([
+                "screenshot",
+                "--source",
+                "synthetic",
+                "--region",
+                "640x360+10+20",
+                "-o",
+            ])
+            .arg(&image)
+            .output()
+            .unwrap();
+        assert_success(&output);
+
+        let stream = video_stream(&media_info(&scratch, &image)).clone();
+        assert_eq!(stream["width"].as_u64(), Some(640));
+        assert_eq!(stream["height"].as_u64(), Some(360));
+    }
+
+    let _ = fs::remove_dir_all(&scratch);
+}
+
+#[test]
+fn crop_uses_the_region_geometry_and_scales_after() {
+    let scratch = scratch_dir("crop");
+    let video = scratch.join("clip.mp4");
+
+    if has_ffmpeg() {
+        let output = rcrdr(&scratch)
+            .args(["record", "--source", "synthetic", "-d", "1", "-o"])
+            .arg(&video)
+            .output()
+            .unwrap();
+        assert_success(&output);
+    } else {
+        // Printing the command never opens the video
+        fs::write(&video, "").unwrap();
+    }
+
+    let output = rcrdr(&scratch)
+        .arg("crop")
+        .arg(&video)
+        .args([
+            "--rect",
+            "640x360+10+20",
+            "--scale",
+            "50%",
+            "--print-command",
+        ])
+        .output()
+        .unwrap();
+    assert_success(&output);
+
+    let line = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        line.contains("-vf 'crop=640:360:10:20,scale=trunc(iw*50/200)*2:-2'"),
+        "{}",
+        line
+    );
+    assert!(!scratch.join("clip.cropped.mp4").exists());
+
+    if has_ffmpeg() {
+        let output = rcrdr(&scratch)
+            .arg("crop")
+            .arg(&video)
+            .args(["--rect", "640x360+10+20", "--scale", "50%"])
+            .output()
+            .unwrap();
+        assert_success(&output);
+
+        let info = media_info(&scratch, &scratch.join("clip.cropped.mp4"));
+        assert_eq!(video_stream(&info)["width"].as_u64(), Some(320));
+        assert_eq!(video_stream(&info)["height"].as_u64(), Some(180));
+    }
+
+    let _ = fs::remove_dir_all(&scratch);
+}
+
+#[test]
+fn redact_hides_regions_from_a_spec_file() {
+    let scratch = scratch_dir("redact");
+    let video = scratch.join("clip.mp4");
+    let spec = scratch.join("spec.toml");
+    fs::write(
+        &spec,
+        concat!(
+            "[[regions]]\n",
+            "rect = \"200x100+10+20\"\n",
+            "start = 0.5\n",
+            "end = 1.5\n",
+            "style = \"pixelate\"\n",
+        ),
+    )
+    .unwrap();
+
+    if has_ffmpeg() {
+        let output = rcrdr(&scratch)
+            .args(["record", "--source", "synthetic", "-d", "2", "-o"])
+            .arg(&video)
+            .output()
+            .unwrap();
+        assert_success(&output);
+    } else {
+        // Printing the command never opens the video
+        fs::write(&video, "").unwrap();
+    }
+
+    let output = rcrdr(&scratch)
+        .arg("redact")
+        .arg(&video)
+        .arg("--spec")
+        .arg(&spec)
+        .arg("--print-command")
+        .output()
+        .unwrap();
+    assert_success(&output);
+
+    let line = String::from_utf8_lossy(&output.stdout);
+    assert!(line.contains("crop=200:100:10:20"), "{}", line);
+    assert!(line.contains("between(t,0.500,1.500)"), "{}", line);
+    assert!(!scratch.join("clip.redacted.mp4").exists());
+
+    if has_ffmpeg() {
+        let output = rcrdr(&scratch)
+            .arg("redact")
+            .arg(&video)
+            .arg("--spec")
+            .arg(&spec)
+            .output()
+            .unwrap();
+        assert_success(&output);
+
+        let info = media_info(&scratch, &scratch.join("clip.redacted.mp4"));
+        let duration = info["duration_secs"].as_f64().unwrap();
+        assert!((1.8..=2.2).contains(&duration), "duration was {}", duration);
+    }
+
+    let _ = fs::remove_dir_all(&scratch);
+}
+
+#[test]
+fn annotate_reads_the_event_log_next_to_the_video() {
+    let scratch = scratch_dir("annotate");
+    let video = scratch.join("clip.mp4");
+    // Printing the command never opens the video
+    fs::write(&video, "").unwrap();
+    fs::write(
+        scratch.join("clip.mp4.events.jsonl"),
+        concat!(
+            r#"{"type":"click","t":1.0,"x":100,"y":100,"button":1}"#,
+            "\n",
+            r#"{"type":"key","t":2.0,"key":"Ctrl+S"}"#,
+            "\n",
+        ),
+    )
+    .unwrap();
+
+    let output = rcrdr(&scratch)
+        .arg("annotate")
+        .arg(&video)
+        .arg("--print-command")
+        .output()
+        .unwrap();
+    assert_success(&output);
+
+    let line = String::from_utf8_lossy(&output.stdout);
+    assert!(line.contains("drawbox=x=88:y=88"), "{}", line);
+    assert!(line.contains("drawtext=text=Ctrl+S"), "{}", line);
+    assert!(
+        line.trim_end()
+            .ends_with(&scratch.join("clip.annotated.mp4").display().to_string()),
+        "{}",
+        line
+    );
+
+    let _ = fs::remove_dir_all(&scratch);
+}
+
+#[test]
+fn convert_to_gif_draws_the_watermark_from_the_flags() {
+    let scratch = scratch_dir("watermark");
+    let video = scratch.join("clip.mp4");
+    // Printing the command never opens the video
+    fs::write(&video, "").unwrap();
+
+    let output = rcrdr(&scratch)
+        .arg("convert-to-gif")
+        .arg("-i")
+        .arg(&video)
+        .args([
+            "-o",
+            "clip.gif",
+            "--watermark-text",
+            "Internal",
+            "--watermark-position",
+            "top-left",
+            "--watermark-opacity",
+            "0.5",
+            "--print-command",
+        ])
+        .output()
+        .unwrap();
+    assert_success(&output);
+
+    let line = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        line.contains("[scaled]drawtext=text=Internal:expansion=none:fontsize=24:fontcolor=0xffffff@0.50:x=20:y=20[watermarked]"),
+        "{}",
+        line
+    );
+    assert!(line.contains("[watermarked]split"), "{}", line);
+
+    let output = rcrdr(&scratch)
+        .arg("convert-to-gif")
+        .arg("-i")
+        .arg(&video)
+        .args(["--watermark-opacity", "0.5", "--print-command"])
+        .output()
+        .unwrap();
+    assert_success(&output);
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("drawtext"));
+
+    let _ = fs::remove_dir_all(&scratch);
+}
+
+#[test]
+fn subtitle_muxes_a_track_or_burns_styled_captions() {
+    let scratch = scratch_dir("subtitle");
+    let video = scratch.join("clip.mp4");
+    // Printing the command never opens the video
+    fs::write(&video, "").unwrap();
+
+    let output = rcrdr(&scratch)
+        .arg("subtitle")
+        .arg(&video)
+        .args(["--srt", "steps.srt", "--print-command"])
+        .output()
+        .unwrap();
+    assert_success(&output);
+    let line = String::from_utf8_lossy(&output.stdout);
+    assert!(line.contains("-i steps.srt"), "{}", line);
+    assert!(line.contains("-c:s mov_text"), "{}", line);
+
+    let output = rcrdr(&scratch)
+        .arg("subtitle")
+        .arg(&video)
+        .args([
+            "--srt",
+            "steps.srt",
+            "--burn",
+            "--caption-position",
+            "top",
+            "--caption-color",
+            "ffff00",
+            "--print-command",
+        ])
+        .output()
+        .unwrap();
+    assert_success(&output);
+    let line = String::from_utf8_lossy(&output.stdout);
+    assert!(line.contains("subtitles=filename=steps.srt"), "{}", line);
+    assert!(line.contains(r"PrimaryColour=&H0000FFFF\,"), "{}", line);
+    assert!(line.contains(r"Alignment=8"), "{}", line);
+    assert!(!line.contains("-c:s"), "{}", line);
+
+    let _ = fs::remove_dir_all(&scratch);
+}
+
+#[test]
+fn condense_cuts_out_the_still_stretch() {
+    if !has_ffmpeg() {
+        return;
+    }
+    let scratch = scratch_dir("condense");
+    let video = scratch.join("idle.mp4");
+
+    // Two seconds of motion, then the last frame held for four
+    let output = Command::new("ffmpeg")
+        .args([
+            "-y",
+            "-f",
+            "lavfi",
+            "-i",
+            "testsrc=s=320x240:r=30:d=2,tpad=stop_mode=clone:stop_duration=4",
+            "-pix_fmt",
+            "yuv420p",
+        ])
+        .arg(&video)
+        .output()
+        .unwrap();
+    assert_success(&output);
+
+    let output = rcrdr(&scratch)
+        .arg("condense")
+        .arg(&video)
+        .output()
+        .unwrap();
+    assert_success(&output);
+    let report = String::from_utf8_lossy(&output.stdout);
+    assert!(report.contains("in 1 idle stretch"), "{}", report);
+
+    let info = media_info(&scratch, &scratch.join("idle.condensed.mp4"));
+    let duration = info["duration_secs"].as_f64().unwrap();
+    assert!((2.2..=2.9).contains(&duration), "duration was {}", duration);
+
+    let _ = fs::remove_dir_all(&scratch);
+}
+
+struct Xvfb {
+    child: Child,
+    display: String,
+}
+
+impl Drop for Xvfb {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// Start Xvfb on a free display number, which it reports on stdout
+fn start_xvfb() -> Xvfb {
+    let mut child = Command::new("Xvfb")
+        .args([
+            "-displayfd",
+            "1",
+            "-screen",
+            "0",
+            "640x480x24",
+            "-nolisten",
+            "tcp",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let mut line = String::new();
+    BufReader::new(child.stdout.take().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+
+    Xvfb {
+        child,
+        display: format!(":{}", line.trim()),
+    }
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn xvfb_display_records_converts_and_verifies() {
+    if !has_ffmpeg() {
+        return;
+    }
+    if !is_available("Xvfb") {
+        eprintln!("skipping: Xvfb not installed");
+        return;
+    }
+    let scratch = scratch_dir("xvfb");
+    let xvfb = start_xvfb();
+
+    let mut record = rcrdr(&scratch);
+    record
+        .env("DISPLAY", &xvfb.display)
+        .args(["record", "--source", "screen"]);
+    check_pipeline(&scratch, &mut record, (640, 480));
+
+    drop(xvfb);
+    let _ = fs::remove_dir_all(&scratch);
+}